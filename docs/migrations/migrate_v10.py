@@ -0,0 +1,28 @@
+# === Migration from v9 to v10 ===
+# 1. Animations now have a "root_motion" action track
+
+import json
+
+files = [
+    "bnuuy.json",
+    "shooter.json",
+    "stabber.json",
+]
+
+ROOT_MOTION = "root_motion"
+
+for out in files:
+    path = "assets/anim/" + "_" + out
+    out = "assets/anim/" + out
+    pack = None
+
+    with open(path) as f:
+        pack = json.load(f)
+    for anim in pack.values():
+        anim["action_tracks"][ROOT_MOTION] = {
+            "clips": [],
+            "tracks": [],
+        }
+
+    with open(out, "w") as f:
+        json.dump(pack, f, ensure_ascii=True, indent=2)
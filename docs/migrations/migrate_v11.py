@@ -0,0 +1,34 @@
+# === Migration from v10 to v11 ===
+# 1. Animations now have an "event" action track
+# 2. Animations now have a "cancel_window" action track
+
+import json
+
+files = [
+    "bnuuy.json",
+    "shooter.json",
+    "stabber.json",
+]
+
+EVENT = "event"
+CANCEL_WINDOW = "cancel_window"
+
+for out in files:
+    path = "assets/anim/" + "_" + out
+    out = "assets/anim/" + out
+    pack = None
+
+    with open(path) as f:
+        pack = json.load(f)
+    for anim in pack.values():
+        anim["action_tracks"][EVENT] = {
+            "clips": [],
+            "tracks": [],
+        }
+        anim["action_tracks"][CANCEL_WINDOW] = {
+            "clips": [],
+            "tracks": [],
+        }
+
+    with open(out, "w") as f:
+        json.dump(pack, f, ensure_ascii=True, indent=2)
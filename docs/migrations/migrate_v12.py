@@ -0,0 +1,28 @@
+# === Migration from v11 to v12 ===
+# 1. Animations now have a "hurtbox" action track
+
+import json
+
+files = [
+    "bnuuy.json",
+    "shooter.json",
+    "stabber.json",
+]
+
+HURTBOX = "hurtbox"
+
+for out in files:
+    path = "assets/anim/" + "_" + out
+    out = "assets/anim/" + out
+    pack = None
+
+    with open(path) as f:
+        pack = json.load(f)
+    for anim in pack.values():
+        anim["action_tracks"][HURTBOX] = {
+            "clips": [],
+            "tracks": [],
+        }
+
+    with open(out, "w") as f:
+        json.dump(pack, f, ensure_ascii=True, indent=2)
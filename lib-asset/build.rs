@@ -0,0 +1,55 @@
+//! Generates typed [AnimationId] constants from the animation packs, so a
+//! typo in an id used by the code fails the build instead of the game.
+//! The ids used only by the data don't need the constants.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+const PACKS_DIR: &str = "../assets/anim";
+
+fn main() {
+    println!("cargo::rerun-if-changed={PACKS_DIR}");
+
+    let mut ids = Vec::new();
+    // The game and lib-game use the constants, so they can't be built without the packs
+    let dir = fs::read_dir(PACKS_DIR).unwrap_or_else(|e| {
+        panic!("The animation packs are needed for the animation ids, reading {PACKS_DIR:?}: {e}")
+    });
+    for entry in dir {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        println!("cargo::rerun-if-changed={}", path.display());
+        let json = fs::read_to_string(&path).unwrap();
+        let pack: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&json).unwrap_or_else(|e| panic!("decoding {path:?}: {e}"));
+        ids.extend(pack.into_iter().map(|(id, _)| id));
+    }
+    ids.sort();
+    ids.dedup();
+
+    let mut out = String::new();
+    writeln!(out, "pub(crate) const PRESET_IDS: &[&str] = &[").unwrap();
+    for id in &ids {
+        writeln!(out, "    {id:?},").unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+    writeln!(out, "#[allow(non_upper_case_globals)]").unwrap();
+    writeln!(out, "impl AnimationId {{").unwrap();
+    for (idx, id) in ids.iter().enumerate() {
+        assert!(
+            id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && id.starts_with(|c: char| c.is_ascii_alphabetic()),
+            "Animation id {id:?} is not a valid identifier",
+        );
+        writeln!(
+            out,
+            "    pub const {id}: AnimationId = AnimationId(Symbol::preset({idx}));"
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    let dst = Path::new(&env::var("OUT_DIR").unwrap()).join("animation_ids.rs");
+    fs::write(dst, out).unwrap();
+}
@@ -1,4 +1,4 @@
-use std::{fs::File, path::Path, str::FromStr};
+use std::{fs::File, path::Path};
 
 use anyhow::{Context, bail, ensure};
 use glam::{uvec2, vec2};
@@ -40,6 +40,7 @@ fn load_animations_from_aseprite(
                 Animation {
                     action_tracks,
                     is_looping,
+                    crossfade: 0.0,
                 },
             )
         })
@@ -61,16 +62,14 @@ fn load_clips_from_aseprite(
     let frames = collect_frames(sheet, layer)?;
     let mut result = HashMap::new();
     for tag in &sheet.meta.frame_tags {
-        let anim_id = match AnimationId::from_str(&tag.data) {
-            Ok(x) => x,
-            Err(_) => {
-                warn!(
-                    "Skipping tag {:?}: does not correspond to any animation id",
-                    tag.data
-                );
-                continue;
-            }
-        };
+        if tag.data.is_empty() {
+            warn!(
+                "Skipping tag {:?}: no animation id in the user data",
+                tag.name
+            );
+            continue;
+        }
+        let anim_id = AnimationId::new(&tag.data);
         let is_looping = tag.repeat.is_empty();
         if !(tag.from..=tag.to).all(|x| frames.contains_key(&x)) {
             info!("Skipping tag {:?}: some frames are absent", tag.data);
@@ -132,6 +131,9 @@ fn collect_frames(
                 rect_size: uvec2(frame.frame.w, frame.frame.h),
                 sort_offset: 0.0f32,
                 rotate_with_parent: false,
+                flip_x: false,
+                flip_y: false,
+                tint: [1.0; 4],
             },
         ))
     }
@@ -174,7 +176,6 @@ pub struct SheetMeta {
 
 #[derive(Debug, Deserialize)]
 pub struct SheetTag {
-    #[allow(dead_code)]
     pub name: String,
     pub from: u32,
     pub to: u32,
@@ -8,6 +8,8 @@ use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use crate::Symbol;
+
 pub async fn load_animation_manifest(path: &Path) -> anyhow::Result<AnimationPack> {
     let json = load_string(path.to_str().unwrap())
         .await
@@ -20,6 +22,10 @@ pub type AnimationPack = HashMap<AnimationId, Animation>;
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Animation {
     pub is_looping: bool,
+    /// For how long (in seconds) the previous animation fades out,
+    /// when switching to this one
+    #[serde(default)]
+    pub crossfade: f32,
     pub action_tracks: HashMap<String, Clips>,
 }
 
@@ -42,37 +48,40 @@ pub struct Track {
     pub name: String,
 }
 
-// TODO: macro for generating this id AND mapping from pack to ids
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    Serialize,
-    Deserialize,
-    strum::EnumString,
-    strum::VariantArray,
-    strum::IntoStaticStr,
-    PartialEq,
-    Eq,
-    Hash,
-)]
-pub enum AnimationId {
-    BnuuyIdleR,
-    BnuuyIdleD,
-    BnuuyIdleL,
-    BnuuyIdleU,
-    BnuuyWalkR,
-    BnuuyWalkD,
-    BnuuyWalkL,
-    BnuuyWalkU,
-    BnuuyAttackD,
-    BnuuyDash,
-    StabberIdle,
-    StabberAttack,
-    ShooterIdle,
-    ShooterAttack,
+/// The name of an animation in a pack. The ids are interned, so new
+/// animations can be added to the packs and referenced from the data
+/// without touching the code.
+///
+/// The ids present in `assets/anim` at build time are also available
+/// as constants, like `AnimationId::BnuuyIdleR`. Prefer those in the code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnimationId(Symbol);
+
+impl AnimationId {
+    pub fn new(name: &str) -> Self {
+        AnimationId(Symbol::new(name))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0.as_str()
+    }
+}
+
+impl std::fmt::Debug for AnimationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Display for AnimationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
+include!(concat!(env!("OUT_DIR"), "/animation_ids.rs"));
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct DrawSprite {
     pub layer: u32,
@@ -83,4 +92,34 @@ pub struct DrawSprite {
     pub rect_size: UVec2,
     pub sort_offset: f32,
     pub rotate_with_parent: bool,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    /// Multiplies the texture's colors
+    #[serde(default = "tint_none")]
+    pub tint: [f32; 4],
+}
+
+fn tint_none() -> [f32; 4] {
+    [1.0; 4]
+}
+
+/// The mixer channel of a sound. Each bus has its own volume.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioBus {
+    #[default]
+    Sfx,
+    Music,
+    Ui,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct PlaySound {
+    pub sound: PathBuf,
+    #[serde(default)]
+    pub bus: AudioBus,
+    pub volume: f32,
+    #[serde(default)]
+    pub pitch_variance: f32,
 }
@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use anyhow::Context;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Senses, Symbol, animation_manifest::AnimationId};
+
+pub async fn load_enemy_kind(path: &Path) -> anyhow::Result<EnemyKind> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let kind: EnemyKind = serde_json::from_str(&json).context("decoding")?;
+    kind.behaviour.validate()?;
+    Ok(kind)
+}
+
+/// An enemy, that is fully described in data: its stats
+/// and a behaviour tree driving it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyKind {
+    pub faction: String,
+    pub max_hp: i32,
+    pub hit_cooldown: f32,
+    pub shape: lib_col::Shape,
+    pub senses: Senses,
+    pub idle_animation: AnimationId,
+    pub walk_animation: AnimationId,
+    /// The bullet pattern the enemy fires while it is aggroed
+    #[serde(default)]
+    pub emitter: Option<Symbol>,
+    pub behaviour: BtNode,
+}
+
+/// A behaviour tree node. Each tick the tree is run from the root.
+/// Every node either succeeds, fails or keeps running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BtNode {
+    /// Runs the children in order, until one of them fails or keeps running.
+    /// The sequence continues from the running child on the next tick,
+    /// so the conditions before it are not checked again.
+    Sequence {
+        children: Vec<BtNode>,
+    },
+    /// Runs the children in order, until one of them succeeds or keeps
+    /// running. The children are always tried from the first one, so
+    /// the earlier children take priority and interrupt the later ones.
+    Selector {
+        children: Vec<BtNode>,
+    },
+    /// Turns success into failure and vice versa
+    Invert {
+        child: Box<BtNode>,
+    },
+    /// Always succeeds
+    Succeed {
+        child: Box<BtNode>,
+    },
+    Condition {
+        condition: BtCondition,
+    },
+    /// Walk to the goal around the walls
+    MoveTo {
+        goal: BtGoal,
+        speed: f32,
+        /// Succeed once this close to the goal
+        stop_distance: f32,
+    },
+    /// Walk away from the target
+    Flee {
+        speed: f32,
+        /// Succeed once this far from the target
+        distance: f32,
+    },
+    /// Turn towards the goal
+    LookAt {
+        goal: BtGoal,
+    },
+    /// Play the animation till the end. The tree is paused meanwhile.
+    Attack {
+        animation: AnimationId,
+    },
+    Wait {
+        duration: f32,
+    },
+    /// Stop being interested in the last noise
+    ForgetNoise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BtCondition {
+    SeesTarget,
+    IsAggro,
+    /// Aggroed, but the target is out of sight
+    LostTarget,
+    HeardNoise,
+    /// The last known target position is closer than `distance`
+    TargetCloser {
+        distance: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BtGoal {
+    /// The last known target position
+    Target,
+    /// Where the last noise came from
+    Noise,
+}
+
+impl BtNode {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            BtNode::Sequence { children } | BtNode::Selector { children } => {
+                anyhow::ensure!(!children.is_empty(), "Composite node has no children");
+                children.iter().try_for_each(BtNode::validate)
+            }
+            BtNode::Invert { child } | BtNode::Succeed { child } => child.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// The amount of nodes in the subtree
+    pub fn size(&self) -> usize {
+        1 + self.children().iter().map(BtNode::size).sum::<usize>()
+    }
+
+    pub fn children(&self) -> &[BtNode] {
+        match self {
+            BtNode::Sequence { children } | BtNode::Selector { children } => children,
+            BtNode::Invert { child } | BtNode::Succeed { child } => {
+                std::slice::from_ref(child.as_ref())
+            }
+            _ => &[],
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use anyhow::Context;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Symbol, animation_manifest::AnimationId};
+
+pub async fn load_boss(path: &Path) -> anyhow::Result<BossDef> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let boss: BossDef = serde_json::from_str(&json).context("decoding")?;
+    boss.validate()?;
+    Ok(boss)
+}
+
+/// A boss fight. The boss goes through its phases as it loses health.
+/// The stats come from the [crate::EnemyKind] of the first phase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BossDef {
+    /// Shown over the health bar
+    pub name: String,
+    /// Overrides the health of the enemy kind
+    pub max_hp: i32,
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BossPhase {
+    /// The phase starts once the health drops to this
+    /// fraction of the max health. The first phase must
+    /// start at `1.0`.
+    pub health_fraction: f32,
+    /// The asset path of the [crate::EnemyKind], whose
+    /// behaviour and animations the boss uses in the phase
+    pub kind: Symbol,
+    /// Played before the phase starts. The boss
+    /// can't be damaged meanwhile.
+    #[serde(default)]
+    pub transition: Option<AnimationId>,
+}
+
+impl BossDef {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.max_hp > 0, "Max health must be positive");
+        let Some(first) = self.phases.first() else {
+            anyhow::bail!("The boss has no phases");
+        };
+        anyhow::ensure!(
+            first.health_fraction == 1.0,
+            "The first phase must start at full health",
+        );
+        for (idx, pair) in self.phases.windows(2).enumerate() {
+            anyhow::ensure!(
+                pair[1].health_fraction < pair[0].health_fraction && pair[1].health_fraction > 0.0,
+                "Phase {}: the health fractions must go down and stay above zero",
+                idx + 1,
+            );
+        }
+        Ok(())
+    }
+
+    /// The phase for the health fraction
+    pub fn phase_at(&self, health_fraction: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| health_fraction <= phase.health_fraction)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,136 @@
+use std::f32::consts::TAU;
+use std::path::Path;
+
+use anyhow::Context;
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Symbol, level::CharacterInfo};
+
+pub async fn load_bullet_patterns(path: &Path) -> anyhow::Result<HashMap<Symbol, BulletPattern>> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let patterns: HashMap<Symbol, BulletPattern> =
+        serde_json::from_str(&json).context("decoding")?;
+    for (name, pattern) in patterns.iter() {
+        pattern
+            .validate()
+            .with_context(|| format!("Pattern {name:?}"))?;
+    }
+    Ok(patterns)
+}
+
+/// A bullet pattern is a list of volleys, that are fired
+/// one after another. Each volley is fired `repeat` times.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulletPattern {
+    pub volleys: Vec<Volley>,
+    /// Go back to the first volley after the last one
+    #[serde(default)]
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Volley {
+    pub shape: VolleyShape,
+    pub aim: Aim,
+    /// The character to spawn for each bullet
+    pub projectile: CharacterInfo,
+    /// How many times the volley is fired
+    pub repeat: u32,
+    /// Seconds between the repeats
+    pub interval: f32,
+    /// Seconds between the last repeat and the next volley
+    pub cooldown: f32,
+}
+
+/// The direction the volley angles are measured from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aim {
+    /// Where the emitter is looking
+    Forward,
+    /// At the closest hostile character. Falls back to [Aim::Forward]
+    /// if there is none.
+    Target,
+}
+
+/// All angles are in radians.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum VolleyShape {
+    /// `count` bullets spread evenly around the emitter
+    Ring { count: u32 },
+    /// A ring of `arms` bullets, that turns by `turn` each repeat
+    Spiral { arms: u32, turn: f32 },
+    /// `count` bullets spread evenly over the `spread` angle
+    Fan { count: u32, spread: f32 },
+    /// A single bullet, that swings `amplitude` to the sides.
+    /// The swing takes `period` repeats.
+    Wave { amplitude: f32, period: u32 },
+}
+
+impl BulletPattern {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.volleys.is_empty(), "The pattern has no volleys");
+        for (idx, volley) in self.volleys.iter().enumerate() {
+            anyhow::ensure!(volley.repeat > 0, "Volley {idx}: repeat must be positive");
+            anyhow::ensure!(
+                volley.interval >= 0.0 && volley.cooldown >= 0.0,
+                "Volley {idx}: negative timings"
+            );
+            match volley.shape {
+                VolleyShape::Ring { count: 0 }
+                | VolleyShape::Spiral { arms: 0, .. }
+                | VolleyShape::Fan { count: 0, .. } => {
+                    anyhow::bail!("Volley {idx}: the volley has no bullets")
+                }
+                VolleyShape::Wave { period: 0, .. } => {
+                    anyhow::bail!("Volley {idx}: the wave period must be positive")
+                }
+                _ => (),
+            }
+        }
+        anyhow::ensure!(
+            !self.looping || self.duration() > 0.0,
+            "A looping pattern must take some time"
+        );
+        Ok(())
+    }
+
+    /// How long (in seconds) it takes to fire all volleys
+    pub fn duration(&self) -> f32 {
+        self.volleys
+            .iter()
+            .map(|x| x.interval * (x.repeat - 1) as f32 + x.cooldown)
+            .sum()
+    }
+}
+
+impl VolleyShape {
+    /// The angles of the bullets fired on the `repeat`-th
+    /// repeat of the volley, relative to the aim direction.
+    pub fn angles(self, repeat: u32) -> Vec<f32> {
+        match self {
+            VolleyShape::Ring { count } => ring(count, 0.0),
+            VolleyShape::Spiral { arms, turn } => ring(arms, turn * repeat as f32),
+            VolleyShape::Fan { count: 1, .. } => vec![0.0],
+            VolleyShape::Fan { count, spread } => {
+                let step = spread / (count - 1) as f32;
+                (0..count)
+                    .map(|idx| -spread / 2.0 + step * idx as f32)
+                    .collect()
+            }
+            VolleyShape::Wave { amplitude, period } => {
+                let phase = (repeat % period) as f32 / period as f32;
+                vec![amplitude * (TAU * phase).sin()]
+            }
+        }
+    }
+}
+
+fn ring(count: u32, offset: f32) -> Vec<f32> {
+    let step = TAU / count as f32;
+    (0..count).map(|idx| offset + step * idx as f32).collect()
+}
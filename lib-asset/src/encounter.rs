@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use anyhow::Context;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Symbol, level::CharacterInfo};
+
+pub async fn load_wave_script(path: &Path) -> anyhow::Result<WaveScript> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let script: WaveScript = serde_json::from_str(&json).context("decoding")?;
+    script.validate()?;
+    Ok(script)
+}
+
+/// The waves of an arena encounter. The waves go one after another
+/// and the encounter is over once the last one is complete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveScript {
+    pub waves: Vec<WaveDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveDef {
+    /// Seconds to wait before the wave starts spawning
+    #[serde(default)]
+    pub delay: f32,
+    pub groups: Vec<SpawnGroup>,
+    #[serde(default)]
+    pub complete: WaveComplete,
+}
+
+/// A bunch of the same enemies. The groups of a wave spawn in parallel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SpawnGroup {
+    pub enemy: CharacterInfo,
+    pub count: u32,
+    /// Seconds between two spawns
+    #[serde(default)]
+    pub interval: f32,
+    /// The spawn zone to use. Spawns in all zones of the arena if missing.
+    #[serde(default)]
+    pub zone: Option<Symbol>,
+}
+
+/// When the wave counts as complete
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum WaveComplete {
+    /// Everything has been spawned and killed
+    #[default]
+    AllDead,
+    /// Everything has been spawned and this many seconds passed
+    /// since the wave start, even if some enemies are still alive
+    After { seconds: f32 },
+}
+
+impl WaveScript {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.waves.is_empty(), "The script has no waves");
+        for (idx, wave) in self.waves.iter().enumerate() {
+            wave.validate().with_context(|| format!("Wave {idx}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl WaveDef {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.delay >= 0.0, "Negative delay");
+        anyhow::ensure!(!self.groups.is_empty(), "The wave has no groups");
+        for group in &self.groups {
+            anyhow::ensure!(group.count > 0, "Empty spawn group");
+            anyhow::ensure!(group.interval >= 0.0, "Negative spawn interval");
+        }
+        Ok(())
+    }
+}
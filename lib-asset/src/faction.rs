@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use anyhow::Context;
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub async fn load_faction_table(path: &Path) -> anyhow::Result<FactionTable> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let manifest: FactionManifest = serde_json::from_str(&json).context("decoding")?;
+    FactionTable::from_manifest(&manifest)
+}
+
+/// A faction id. Factions are defined in data, so the ids are only
+/// meaningful for the [FactionTable] they were resolved from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Faction(u32);
+
+/// How one faction treats another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Attitude {
+    /// Attacks land and the AI goes after the members of the other faction.
+    Hostile,
+    /// Attacks land, but the AI leaves the members of the other faction alone.
+    Neutral,
+    /// Attacks only land when friendly fire is on.
+    Friendly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Relation {
+    pub attitude: Attitude,
+    #[serde(default)]
+    pub friendly_fire: bool,
+}
+
+impl Relation {
+    pub const SAME_FACTION: Relation = Relation {
+        attitude: Attitude::Friendly,
+        friendly_fire: false,
+    };
+    pub const UNRELATED: Relation = Relation {
+        attitude: Attitude::Neutral,
+        friendly_fire: false,
+    };
+
+    pub fn can_damage(self) -> bool {
+        match self.attitude {
+            Attitude::Hostile | Attitude::Neutral => true,
+            Attitude::Friendly => self.friendly_fire,
+        }
+    }
+
+    pub fn is_hostile(self) -> bool {
+        self.attitude == Attitude::Hostile
+    }
+}
+
+/// The faction file. Relations are directed: an entry describes how
+/// the members of `from` treat the members of `to`.
+/// Pairs without an entry fall back to [Relation::SAME_FACTION] for
+/// a faction and itself and to [Relation::UNRELATED] otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactionManifest {
+    pub factions: Vec<String>,
+    pub relations: Vec<RelationDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationDef {
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub relation: Relation,
+}
+
+/// The relationship matrix between all factions.
+#[derive(Debug, Clone, Default)]
+pub struct FactionTable {
+    names: Vec<String>,
+    lookup: HashMap<String, Faction>,
+    relations: Vec<Relation>,
+}
+
+impl FactionTable {
+    pub fn from_manifest(manifest: &FactionManifest) -> anyhow::Result<Self> {
+        let mut lookup = HashMap::new();
+        for (idx, name) in manifest.factions.iter().enumerate() {
+            let old = lookup.insert(name.clone(), Faction(idx as u32));
+            anyhow::ensure!(old.is_none(), "Duplicate faction: {name:?}");
+        }
+
+        let count = manifest.factions.len();
+        let mut table = FactionTable {
+            names: manifest.factions.clone(),
+            lookup,
+            relations: vec![Relation::UNRELATED; count * count],
+        };
+        for idx in 0..count {
+            let faction = Faction(idx as u32);
+            table.set_relation(faction, faction, Relation::SAME_FACTION);
+        }
+
+        for def in &manifest.relations {
+            let Some(from) = table.resolve(&def.from) else {
+                anyhow::bail!("Unknown faction: {:?}", def.from);
+            };
+            let Some(to) = table.resolve(&def.to) else {
+                anyhow::bail!("Unknown faction: {:?}", def.to);
+            };
+            table.set_relation(from, to, def.relation);
+        }
+
+        Ok(table)
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<Faction> {
+        self.lookup.get(name).copied()
+    }
+
+    pub fn name(&self, faction: Faction) -> &str {
+        &self.names[faction.0 as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Faction)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.as_str(), Faction(idx as u32)))
+    }
+
+    /// How the members of `from` treat the members of `to`.
+    pub fn relation(&self, from: Faction, to: Faction) -> Relation {
+        self.relations[self.relation_idx(from, to)]
+    }
+
+    pub fn set_relation(&mut self, from: Faction, to: Faction, relation: Relation) {
+        let idx = self.relation_idx(from, to);
+        self.relations[idx] = relation;
+    }
+
+    fn relation_idx(&self, from: Faction, to: Faction) -> usize {
+        let count = self.names.len();
+        debug_assert!((from.0 as usize) < count && (to.0 as usize) < count);
+        from.0 as usize * count + to.0 as usize
+    }
+}
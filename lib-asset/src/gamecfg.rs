@@ -58,24 +58,25 @@ macro_rules! game_cfg {
 game_cfg! {
     player: Player {
         speed: f32,
-        dash_speed: f32,
         max_hp: i32,
         hit_cooldown: f32,
         shape: lib_col::Shape,
         max_stamina: f32,
-        attack_cost: f32,
         dash_cost: f32,
         graze_shape: lib_col::Shape,
+        noise_radius: f32,
     },
     basic_bullet: BasicBullet {
         speed: f32,
         graze_value: f32,
         shape: lib_col::Shape,
+        behaviour: crate::ProjectileBehaviour,
     },
     shooter: Shooter {
         max_hp: i32,
         hit_cooldown: f32,
         shape: lib_col::Shape,
+        senses: crate::Senses,
     },
     stabber: Stabber {
         max_hp: i32,
@@ -83,5 +84,26 @@ game_cfg! {
         shape: lib_col::Shape,
         speed: f32,
         attack_range: f32,
+        separation_radius: f32,
+        separation_weight: f32,
+        senses: crate::Senses,
+    },
+    squad: Squad {
+        max_attackers: u32,
+        token_timeout: f32,
+        rest_time: f32,
+        wait_radius: f32,
+        flank_spread: f32,
+    },
+    audio: Audio {
+        sfx_volume: f32,
+        music_volume: f32,
+        ui_volume: f32,
+        max_instances: u32,
+    },
+    sprite_flash: SpriteFlash {
+        color: [f32; 4],
+        duration: f32,
+        blink_rate: f32,
     },
 }
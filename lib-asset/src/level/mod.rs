@@ -42,6 +42,43 @@ pub struct LevelDef {
     pub map: MapDef,
     /// The characters placed on the map.
     pub characters: Vec<CharacterDef>,
+    /// The arena fights of the level.
+    #[serde(default)]
+    pub encounters: Vec<EncounterDef>,
+}
+
+/// An arena fight. Once the player steps into the trigger area,
+/// the doors get locked and the waves start spawning. The doors
+/// open again once all waves are complete.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EncounterDef {
+    pub name: crate::Symbol,
+    /// The asset path of the [crate::WaveScript]
+    pub script: crate::Symbol,
+    pub trigger: AreaDef,
+    pub spawn_zones: Vec<SpawnZoneDef>,
+    pub doors: Vec<AreaDef>,
+}
+
+/// An area, where the enemies of an encounter appear
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SpawnZoneDef {
+    pub name: crate::Symbol,
+    pub area: AreaDef,
+}
+
+/// An axis aligned rectangle in level units
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AreaDef {
+    /// The top left corner
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl AreaDef {
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size / 2.0
+    }
 }
 
 /// Entity data. Currently, all entities are represented as squares.
@@ -68,13 +105,31 @@ pub enum CharacterInfo {
     Goal {},
     Damager {},
     Stabber {},
-    BasicBullet {},
+    BasicBullet {
+        /// Overrides the behaviour from the game config
+        #[serde(default)]
+        behaviour: Option<crate::ProjectileBehaviour>,
+    },
     Shooter {},
+    /// A static object firing a bullet pattern
+    Emitter {
+        pattern: crate::Symbol,
+    },
+    /// An enemy driven by an [crate::EnemyKind] asset
+    Scripted {
+        /// Path to the enemy kind, e.g. `ai/brute.json`
+        kind: crate::Symbol,
+    },
+    /// A boss described by a [crate::BossDef] asset
+    Boss {
+        /// Path to the boss, e.g. `boss/warden.json`
+        boss: crate::Symbol,
+    },
 }
 
 impl Default for CharacterInfo {
     fn default() -> Self {
-        CharacterInfo::BasicBullet {}
+        CharacterInfo::BasicBullet { behaviour: None }
     }
 }
 
@@ -107,6 +162,9 @@ pub struct Tile {
     pub ty: TileTy,
     #[serde(default = "description_unused")]
     pub description: String,
+    /// The locked arena doors are drawn with this tile
+    #[serde(default)]
+    pub door: bool,
 }
 
 fn description_unused() -> String {
@@ -6,6 +6,7 @@ use std::path::Path;
 use anyhow::Context;
 use glam::vec2;
 use hashbrown::HashMap;
+use serde::Deserialize;
 
 use super::tiled_props_des::from_properties;
 use crate::{AssetRoot, FsResolver, level::*};
@@ -52,7 +53,7 @@ fn load_level_from_map(
     for layer in map.layers() {
         let name = layer.name.clone();
         anyhow::ensure!(
-            name == OBJECT_LAYER || name == WORLD_LAYER,
+            name == OBJECT_LAYER || name == WORLD_LAYER || name == ENCOUNTER_LAYER,
             "Unknown layer: {name:?}",
         );
 
@@ -70,9 +71,16 @@ fn load_level_from_map(
     };
     let entities = load_entity_defs_from_object_layer(map_path, resolver, entitydefs_layer)?;
 
+    // Encounters are optional
+    let encounters = match layers_by_name.get(ENCOUNTER_LAYER) {
+        Some(layer) => load_encounter_defs_from_object_layer(map_path, resolver, layer)?,
+        None => Vec::new(),
+    };
+
     Ok(LevelDef {
         map,
         characters: entities,
+        encounters,
     })
 }
 
@@ -168,7 +176,96 @@ fn load_entity_defs_from_object_layer(
     Ok(entities)
 }
 
+/// The objects of the encounter layer. The spawn zones and
+/// the doors refer to their arena by its object name.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EncounterObject {
+    Arena { script: crate::Symbol },
+    SpawnZone { arena: crate::Symbol },
+    Door { arena: crate::Symbol },
+}
+
+fn load_encounter_defs_from_object_layer(
+    map_path: &Path,
+    resolver: &FsResolver,
+    layer: &tiled::Layer,
+) -> anyhow::Result<Vec<EncounterDef>> {
+    let Some(object_layer) = layer.as_object_layer() else {
+        anyhow::bail!("Expected layer {ENCOUNTER_LAYER:?} to be an object layer")
+    };
+
+    let mut encounters = Vec::<EncounterDef>::new();
+    let mut zones = Vec::new();
+    let mut doors = Vec::new();
+    for object in object_layer.objects() {
+        anyhow::ensure!(
+            !object.user_type.is_empty(),
+            "Layer {ENCOUNTER_LAYER:?}, object {}: no class",
+            object.id(),
+        );
+        let info: EncounterObject =
+            from_properties(map_path, resolver, &object.user_type, &object.properties)
+                .with_context(|| format!("Layer {ENCOUNTER_LAYER:?}, object {}", object.id()))?;
+        let area = match object.shape {
+            tiled::ObjectShape::Rect { width, height } => AreaDef {
+                pos: vec2(object.x, object.y),
+                size: vec2(width, height),
+            },
+            _ => anyhow::bail!(
+                "Layer {ENCOUNTER_LAYER:?}, object {}: non rectangle object",
+                object.id(),
+            ),
+        };
+        let name = crate::Symbol::new(&object.name);
+
+        match info {
+            EncounterObject::Arena { script } => {
+                anyhow::ensure!(
+                    encounters.iter().all(|x| x.name != name),
+                    "Layer {ENCOUNTER_LAYER:?}, object {}: duplicate arena {name:?}",
+                    object.id(),
+                );
+                encounters.push(EncounterDef {
+                    name,
+                    script,
+                    trigger: area,
+                    spawn_zones: Vec::new(),
+                    doors: Vec::new(),
+                });
+            }
+            EncounterObject::SpawnZone { arena } => {
+                zones.push((object.id(), arena, SpawnZoneDef { name, area }))
+            }
+            EncounterObject::Door { arena } => doors.push((object.id(), arena, area)),
+        }
+    }
+
+    for (id, arena, zone) in zones {
+        let Some(encounter) = encounters.iter_mut().find(|x| x.name == arena) else {
+            anyhow::bail!("Layer {ENCOUNTER_LAYER:?}, object {id}: unknown arena {arena:?}");
+        };
+        encounter.spawn_zones.push(zone);
+    }
+    for (id, arena, door) in doors {
+        let Some(encounter) = encounters.iter_mut().find(|x| x.name == arena) else {
+            anyhow::bail!("Layer {ENCOUNTER_LAYER:?}, object {id}: unknown arena {arena:?}");
+        };
+        encounter.doors.push(door);
+    }
+    for encounter in &encounters {
+        anyhow::ensure!(
+            !encounter.spawn_zones.is_empty(),
+            "Arena {:?} has no spawn zones",
+            encounter.name,
+        );
+    }
+
+    Ok(encounters)
+}
+
 static REQUIRED_TILED_VERSION: &str = "1.10";
 static OBJECT_LAYER: &str = "Characters";
+static ENCOUNTER_LAYER: &str = "Encounters";
 static WORLD_LAYER: &str = "World";
 static TILE_CLASS: &str = "Tile";
@@ -1,13 +1,33 @@
 mod asset_roots;
 mod containers;
+mod symbol;
 
 pub mod animation_manifest;
+pub mod behaviour;
+pub mod boss;
+pub mod bullet_pattern;
+pub mod encounter;
+pub mod faction;
 pub mod gamecfg;
 pub mod level;
+pub mod moveset;
+pub mod perception;
+pub mod projectile;
+pub mod state_machine;
 
 pub use asset_roots::*;
+pub use behaviour::*;
+pub use boss::*;
+pub use bullet_pattern::*;
 pub use containers::*;
+pub use encounter::*;
+pub use faction::*;
 pub use gamecfg::*;
+pub use moveset::*;
+pub use perception::*;
+pub use projectile::*;
+pub use state_machine::*;
+pub use symbol::*;
 
 use hashbrown::HashMap;
 
@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use anyhow::Context;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animation_manifest::AnimationId;
+
+pub async fn load_moveset(path: &Path) -> anyhow::Result<Moveset> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let moveset: Moveset = serde_json::from_str(&json).context("decoding")?;
+    moveset.validate()?;
+    Ok(moveset)
+}
+
+/// The attacks a character can do. The combo is a chain of
+/// animations: pressing attack while a step's animation has a
+/// `cancel_window` clip into `attack` goes to the next step.
+/// Holding attack after starting the combo turns into the charge
+/// attack once the `hold_time` of the first charge level is
+/// reached. Letting go does the highest level reached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Moveset {
+    pub combo: Vec<ComboStep>,
+    #[serde(default)]
+    pub charge: Option<ChargeAttack>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ComboStep {
+    pub animation: AnimationId,
+    pub stamina_cost: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeAttack {
+    /// The animation played while attack is held
+    pub charge_animation: AnimationId,
+    /// Must be sorted by `hold_time`
+    pub levels: Vec<ChargeLevel>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ChargeLevel {
+    /// For how long (in seconds) attack needs to be held
+    pub hold_time: f32,
+    pub animation: AnimationId,
+    pub stamina_cost: f32,
+}
+
+impl Moveset {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.combo.is_empty(), "The combo has no steps");
+        if let Some(charge) = &self.charge {
+            anyhow::ensure!(
+                charge
+                    .levels
+                    .windows(2)
+                    .all(|x| x[0].hold_time < x[1].hold_time),
+                "Charge levels are not sorted by hold time"
+            );
+        }
+        Ok(())
+    }
+
+    /// The step coming after `step`, unless it ends the combo
+    pub fn next_step(&self, step: usize) -> Option<usize> {
+        let next = step + 1;
+        (next < self.combo.len()).then_some(next)
+    }
+
+    /// The highest charge level reached after holding attack for `hold_time`.
+    pub fn charge_level(&self, hold_time: f32) -> Option<usize> {
+        let charge = self.charge.as_ref()?;
+        charge
+            .levels
+            .iter()
+            .rposition(|level| level.hold_time <= hold_time)
+    }
+}
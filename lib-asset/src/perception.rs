@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// How well a character perceives the world. Distances are
+/// in level units, angles in radians and timers in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Senses {
+    pub sight_range: f32,
+    /// The full angle of the view cone
+    pub fov: f32,
+    pub hearing_radius: f32,
+    /// For how long the last seen position is remembered
+    pub memory: f32,
+    /// For how long a target must be seen to get aggroed
+    pub aggro_time: f32,
+    /// For how long the target must be out of sight to lose the aggro
+    pub deaggro_time: f32,
+}
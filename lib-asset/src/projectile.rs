@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Describes how a projectile flies. All behaviours are optional
+/// and can be combined: for example, a bullet can both home in
+/// on its target and wiggle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectileBehaviour {
+    /// Despawn the projectile after this many seconds
+    pub lifetime: Option<f32>,
+    /// Despawn the projectile after it flies this far
+    pub max_range: Option<f32>,
+    /// How many times the projectile bounces off walls before breaking
+    pub bounces: u32,
+    pub homing: Option<Homing>,
+    pub acceleration: Option<Acceleration>,
+    pub wave: Option<Wave>,
+}
+
+/// Steer towards the closest hostile character
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Homing {
+    /// Radians per second
+    pub turn_rate: f32,
+    /// Characters further than this are ignored
+    pub range: f32,
+}
+
+/// Change the speed over time. Use a negative `accel` to slow down.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Acceleration {
+    pub accel: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+}
+
+/// Sway to the sides while flying forward
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Wave {
+    pub amplitude: f32,
+    /// Oscillations per second
+    pub frequency: f32,
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use anyhow::Context;
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Symbol, animation_manifest::AnimationId};
+
+pub async fn load_state_machine(path: &Path) -> anyhow::Result<StateMachine> {
+    let json = load_string(path.to_str().unwrap())
+        .await
+        .context("loading JSON")?;
+    let machine: StateMachine = serde_json::from_str(&json).context("decoding")?;
+    machine.validate()?;
+    Ok(machine)
+}
+
+/// A character state machine. Each state plays an animation and
+/// lists the transitions out of it. The transitions are tried
+/// in order and the first one that fires wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateMachine {
+    pub initial: Symbol,
+    pub states: HashMap<Symbol, StateDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDef {
+    pub animation: StateAnimation,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/// Either one animation, or an animation per look direction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StateAnimation {
+    Single(AnimationId),
+    /// Any number of directions, evenly spaced clockwise starting
+    /// from the right. The missing ones fall back to the mirrored
+    /// direction, then to the nearest one.
+    Directions {
+        directions: Vec<Option<AnimationId>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Transition {
+    pub to: Symbol,
+    pub on: Trigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Trigger {
+    /// The state's animation has finished
+    AnimEnd,
+    /// The code has sent the signal this tick
+    Signal { signal: Symbol },
+    /// The code has raised the flag
+    Flag { flag: Symbol },
+    /// The code has not raised the flag
+    NotFlag { flag: Symbol },
+    /// Spent at least this many seconds in the state
+    After { seconds: f32 },
+}
+
+impl StateMachine {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.states.contains_key(&self.initial),
+            "Initial state {:?} does not exist",
+            self.initial,
+        );
+        for (name, state) in &self.states {
+            if let StateAnimation::Directions { directions } = &state.animation {
+                anyhow::ensure!(
+                    directions.iter().any(Option::is_some),
+                    "State {name:?} has no animation for any direction",
+                );
+            }
+            for transition in &state.transitions {
+                anyhow::ensure!(
+                    self.states.contains_key(&transition.to),
+                    "State {name:?} has a transition to a missing state {:?}",
+                    transition.to,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// `None` for the names, that are not states of the machine
+    pub fn state(&self, state: Symbol) -> Option<&StateDef> {
+        self.states.get(&state)
+    }
+
+    /// Find the first transition out of `from`, whose trigger fires
+    pub fn next_state(
+        &self,
+        from: Symbol,
+        mut fires: impl FnMut(&Trigger) -> bool,
+    ) -> Option<Symbol> {
+        self.state(from)?
+            .transitions
+            .iter()
+            .find(|transition| fires(&transition.on))
+            .map(|transition| transition.to)
+    }
+}
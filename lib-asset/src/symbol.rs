@@ -0,0 +1,94 @@
+use std::sync::{LazyLock, Mutex};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| {
+    let mut interner = Interner::default();
+    for s in crate::animation_manifest::PRESET_IDS {
+        interner.intern(s);
+    }
+    Mutex::new(interner)
+});
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, u32>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(id) = self.ids.get(s) {
+            return *id;
+        }
+
+        let s: &'static str = Box::leak(s.to_string().into_boxed_str());
+        let id = self.strings.len() as u32;
+        self.strings.push(s);
+        self.ids.insert(s, id);
+        id
+    }
+}
+
+/// An interned string. Symbols are cheap to copy and compare, so
+/// they can be used to refer to assets by name from `Copy` data,
+/// like the [crate::level::CharacterInfo].
+///
+/// Interned strings are never freed, so only use symbols for names
+/// coming from the assets.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn new(s: &str) -> Self {
+        Symbol(INTERNER.lock().unwrap().intern(s))
+    }
+
+    /// The symbols for the build time constants. The interner
+    /// starts with these, so their ids are known in advance.
+    pub(crate) const fn preset(id: u32) -> Self {
+        Symbol(id)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.lock().unwrap().strings[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Symbol::new(value)
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Symbol::new(&s))
+    }
+}
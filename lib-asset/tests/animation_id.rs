@@ -0,0 +1,16 @@
+use lib_asset::animation_manifest::AnimationId;
+
+#[test]
+fn test_constants_match_names() {
+    assert_eq!(AnimationId::new("BnuuyIdleR"), AnimationId::BnuuyIdleR);
+    assert_eq!(AnimationId::StabberAttack.as_str(), "StabberAttack");
+    assert_ne!(AnimationId::StabberAttack, AnimationId::StabberIdle);
+}
+
+#[test]
+fn test_new_ids() {
+    let id = AnimationId::new("SomeNewAnimation");
+    assert_eq!(id, AnimationId::new("SomeNewAnimation"));
+    assert_eq!(id.as_str(), "SomeNewAnimation");
+    assert_ne!(id, AnimationId::BnuuyIdleR);
+}
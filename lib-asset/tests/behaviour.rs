@@ -0,0 +1,44 @@
+use lib_asset::{BtCondition, BtGoal, BtNode};
+
+fn condition(condition: BtCondition) -> BtNode {
+    BtNode::Condition { condition }
+}
+
+#[test]
+fn test_size() {
+    let tree = BtNode::Selector {
+        children: vec![
+            BtNode::Sequence {
+                children: vec![
+                    condition(BtCondition::SeesTarget),
+                    BtNode::LookAt {
+                        goal: BtGoal::Target,
+                    },
+                ],
+            },
+            BtNode::Invert {
+                child: Box::new(condition(BtCondition::IsAggro)),
+            },
+            BtNode::Wait { duration: 1.0 },
+        ],
+    };
+    assert_eq!(tree.size(), 7);
+    assert_eq!(tree.children().len(), 3);
+    assert_eq!(tree.children()[1].children().len(), 1);
+}
+
+#[test]
+fn test_validate() {
+    let empty = BtNode::Sequence { children: vec![] };
+    assert!(empty.validate().is_err());
+
+    let nested = BtNode::Succeed {
+        child: Box::new(BtNode::Selector { children: vec![] }),
+    };
+    assert!(nested.validate().is_err());
+
+    let ok = BtNode::Selector {
+        children: vec![BtNode::ForgetNoise],
+    };
+    assert!(ok.validate().is_ok());
+}
@@ -0,0 +1,35 @@
+use lib_asset::{BossDef, BossPhase, Symbol};
+
+fn boss(fractions: &[f32]) -> BossDef {
+    BossDef {
+        name: "Test".to_string(),
+        max_hp: 10,
+        phases: fractions
+            .iter()
+            .map(|&health_fraction| BossPhase {
+                health_fraction,
+                kind: Symbol::new("ai/brute.json"),
+                transition: None,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_validate() {
+    assert!(boss(&[]).validate().is_err());
+    assert!(boss(&[0.5]).validate().is_err());
+    assert!(boss(&[1.0, 0.5, 0.5]).validate().is_err());
+    assert!(boss(&[1.0, 0.0]).validate().is_err());
+    assert!(boss(&[1.0, 0.5, 0.2]).validate().is_ok());
+}
+
+#[test]
+fn test_phase_at() {
+    let boss = boss(&[1.0, 0.5, 0.2]);
+    assert_eq!(boss.phase_at(1.0), 0);
+    assert_eq!(boss.phase_at(0.6), 0);
+    assert_eq!(boss.phase_at(0.5), 1);
+    assert_eq!(boss.phase_at(0.2), 2);
+    assert_eq!(boss.phase_at(0.0), 2);
+}
@@ -0,0 +1,113 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use lib_asset::{Aim, BulletPattern, Symbol, Volley, VolleyShape, level::CharacterInfo};
+
+fn assert_angles(actual: Vec<f32>, expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+    for (a, b) in actual.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-5, "{actual:?} != {expected:?}");
+    }
+}
+
+fn pattern(shape: VolleyShape, looping: bool, cooldown: f32) -> BulletPattern {
+    BulletPattern {
+        volleys: vec![Volley {
+            shape,
+            aim: Aim::Forward,
+            projectile: CharacterInfo::BasicBullet { behaviour: None },
+            repeat: 1,
+            interval: 0.0,
+            cooldown,
+        }],
+        looping,
+    }
+}
+
+#[test]
+fn test_ring() {
+    let shape = VolleyShape::Ring { count: 4 };
+    assert_angles(shape.angles(0), &[0.0, FRAC_PI_2, PI, 3.0 * FRAC_PI_2]);
+    assert_angles(shape.angles(5), &[0.0, FRAC_PI_2, PI, 3.0 * FRAC_PI_2]);
+}
+
+#[test]
+fn test_spiral() {
+    let shape = VolleyShape::Spiral { arms: 2, turn: 0.1 };
+    assert_angles(shape.angles(0), &[0.0, PI]);
+    assert_angles(shape.angles(3), &[0.3, PI + 0.3]);
+}
+
+#[test]
+fn test_fan() {
+    let shape = VolleyShape::Fan {
+        count: 3,
+        spread: 1.0,
+    };
+    assert_angles(shape.angles(0), &[-0.5, 0.0, 0.5]);
+
+    let shape = VolleyShape::Fan {
+        count: 1,
+        spread: 1.0,
+    };
+    assert_angles(shape.angles(0), &[0.0]);
+}
+
+#[test]
+fn test_wave() {
+    let shape = VolleyShape::Wave {
+        amplitude: 0.5,
+        period: 4,
+    };
+    assert_angles(shape.angles(0), &[0.0]);
+    assert_angles(shape.angles(1), &[0.5]);
+    assert_angles(shape.angles(3), &[-0.5]);
+    assert_angles(shape.angles(5), &[0.5]);
+}
+
+#[test]
+fn test_validate() {
+    assert!(
+        pattern(VolleyShape::Ring { count: 3 }, true, 1.0)
+            .validate()
+            .is_ok()
+    );
+    assert!(
+        pattern(VolleyShape::Ring { count: 0 }, true, 1.0)
+            .validate()
+            .is_err()
+    );
+    assert!(
+        pattern(VolleyShape::Ring { count: 3 }, true, 0.0)
+            .validate()
+            .is_err()
+    );
+    assert!(
+        pattern(VolleyShape::Ring { count: 3 }, false, 0.0)
+            .validate()
+            .is_ok()
+    );
+    assert!(
+        pattern(
+            VolleyShape::Wave {
+                amplitude: 1.0,
+                period: 0
+            },
+            false,
+            1.0
+        )
+        .validate()
+        .is_err()
+    );
+}
+
+#[test]
+fn test_symbol() {
+    let a = Symbol::new("ring");
+    assert_eq!(a, Symbol::new("ring"));
+    assert_ne!(a, Symbol::new("spiral"));
+    assert_eq!(a.as_str(), "ring");
+
+    let json = serde_json::to_string(&a).unwrap();
+    assert_eq!(json, "\"ring\"");
+    assert_eq!(serde_json::from_str::<Symbol>(&json).unwrap(), a);
+}
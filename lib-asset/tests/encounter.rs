@@ -0,0 +1,51 @@
+use lib_asset::{SpawnGroup, WaveComplete, WaveDef, WaveScript, level::CharacterInfo};
+
+fn wave(count: u32, interval: f32) -> WaveDef {
+    WaveDef {
+        delay: 0.0,
+        groups: vec![SpawnGroup {
+            enemy: CharacterInfo::Stabber {},
+            count,
+            interval,
+            zone: None,
+        }],
+        complete: WaveComplete::AllDead,
+    }
+}
+
+#[test]
+fn test_validate() {
+    assert!(WaveScript { waves: vec![] }.validate().is_err());
+    assert!(
+        WaveScript {
+            waves: vec![wave(0, 0.0)]
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(
+        WaveScript {
+            waves: vec![wave(2, -1.0)]
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(
+        WaveScript {
+            waves: vec![wave(2, 0.5), wave(1, 0.0)]
+        }
+        .validate()
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_decode_defaults() {
+    let json = r#"{ "waves": [ { "groups": [ { "enemy": { "stabber": {} }, "count": 1 } ] } ] }"#;
+    let script: WaveScript = serde_json::from_str(json).unwrap();
+    let wave = &script.waves[0];
+    assert_eq!(wave.delay, 0.0);
+    assert_eq!(wave.complete, WaveComplete::AllDead);
+    assert_eq!(wave.groups[0].interval, 0.0);
+    assert_eq!(wave.groups[0].zone, None);
+}
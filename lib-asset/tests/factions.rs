@@ -0,0 +1,67 @@
+use lib_asset::{Attitude, FactionManifest, FactionTable, Relation, RelationDef};
+
+fn manifest(factions: &[&str], relations: Vec<RelationDef>) -> FactionManifest {
+    FactionManifest {
+        factions: factions.iter().map(|x| x.to_string()).collect(),
+        relations,
+    }
+}
+
+fn relation_def(from: &str, to: &str, attitude: Attitude, friendly_fire: bool) -> RelationDef {
+    RelationDef {
+        from: from.to_string(),
+        to: to.to_string(),
+        relation: Relation {
+            attitude,
+            friendly_fire,
+        },
+    }
+}
+
+#[test]
+fn test_defaults() {
+    let table = FactionTable::from_manifest(&manifest(&["a", "b"], vec![])).unwrap();
+    let a = table.resolve("a").unwrap();
+    let b = table.resolve("b").unwrap();
+
+    assert_eq!(table.relation(a, a), Relation::SAME_FACTION);
+    assert_eq!(table.relation(a, b), Relation::UNRELATED);
+    assert!(!table.relation(a, a).can_damage());
+    assert!(table.relation(a, b).can_damage());
+    assert!(!table.relation(a, b).is_hostile());
+}
+
+#[test]
+fn test_directed_relations() {
+    let table = FactionTable::from_manifest(&manifest(
+        &["player", "critter", "monster"],
+        vec![
+            relation_def("monster", "player", Attitude::Hostile, false),
+            relation_def("monster", "monster", Attitude::Friendly, true),
+            relation_def("player", "critter", Attitude::Friendly, false),
+        ],
+    ))
+    .unwrap();
+    let player = table.resolve("player").unwrap();
+    let critter = table.resolve("critter").unwrap();
+    let monster = table.resolve("monster").unwrap();
+
+    assert!(table.relation(monster, player).is_hostile());
+    assert!(!table.relation(player, monster).is_hostile());
+    assert!(table.relation(monster, monster).can_damage());
+    assert!(!table.relation(player, critter).can_damage());
+    assert!(table.relation(critter, player).can_damage());
+    assert_eq!(table.name(critter), "critter");
+}
+
+#[test]
+fn test_bad_manifests() {
+    assert!(FactionTable::from_manifest(&manifest(&["a", "a"], vec![])).is_err());
+    assert!(
+        FactionTable::from_manifest(&manifest(
+            &["a"],
+            vec![relation_def("a", "b", Attitude::Hostile, false)],
+        ))
+        .is_err()
+    );
+}
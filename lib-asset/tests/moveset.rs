@@ -0,0 +1,60 @@
+use lib_asset::{ChargeAttack, ChargeLevel, ComboStep, Moveset, animation_manifest::AnimationId};
+
+fn step() -> ComboStep {
+    ComboStep {
+        animation: AnimationId::BnuuyAttackD,
+        stamina_cost: 0.0,
+    }
+}
+
+fn level(hold_time: f32) -> ChargeLevel {
+    ChargeLevel {
+        hold_time,
+        animation: AnimationId::BnuuyChargedAttackD,
+        stamina_cost: 0.0,
+    }
+}
+
+fn moveset(levels: Vec<ChargeLevel>) -> Moveset {
+    Moveset {
+        combo: vec![step(), step(), step()],
+        charge: Some(ChargeAttack {
+            charge_animation: AnimationId::BnuuyCharge,
+            levels,
+        }),
+    }
+}
+
+#[test]
+fn test_next_step() {
+    let moveset = moveset(vec![]);
+
+    assert_eq!(moveset.next_step(0), Some(1));
+    assert_eq!(moveset.next_step(1), Some(2));
+    assert_eq!(moveset.next_step(2), None);
+    assert_eq!(moveset.next_step(3), None);
+}
+
+#[test]
+fn test_charge_level() {
+    let moveset = moveset(vec![level(0.5), level(1.0)]);
+
+    assert_eq!(moveset.charge_level(0.0), None);
+    assert_eq!(moveset.charge_level(0.5), Some(0));
+    assert_eq!(moveset.charge_level(0.9), Some(0));
+    assert_eq!(moveset.charge_level(3.0), Some(1));
+}
+
+#[test]
+fn test_validate() {
+    assert!(moveset(vec![level(0.5), level(1.0)]).validate().is_ok());
+    assert!(moveset(vec![level(1.0), level(0.5)]).validate().is_err());
+    assert!(
+        Moveset {
+            combo: vec![],
+            charge: None,
+        }
+        .validate()
+        .is_err()
+    );
+}
@@ -0,0 +1,72 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashMap;
+use lib_asset::{
+    BossDef, BulletPattern, EnemyKind, FactionManifest, FactionTable, Moveset, StateMachine,
+    Symbol, WaveScript, animation_manifest::AnimationPack,
+};
+use serde::de::DeserializeOwned;
+
+const ASSETS: &str = "../assets";
+
+type Check = Box<dyn Fn(&str) -> anyhow::Result<()>>;
+
+fn check<T: DeserializeOwned>(validate: fn(&T) -> anyhow::Result<()>) -> Check {
+    Box::new(move |json: &str| validate(&serde_json::from_str(json)?))
+}
+
+/// The file itself or the JSON files in the directory
+fn json_files(path: PathBuf) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path];
+    }
+    fs::read_dir(&path)
+        .unwrap_or_else(|e| panic!("{path:?}: {e}"))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect()
+}
+
+#[test]
+fn test_shipped_assets() {
+    let table = [
+        ("anim", check(|_: &AnimationPack| Ok(()))),
+        ("ai", check(|kind: &EnemyKind| kind.behaviour.validate())),
+        (
+            "boss",
+            check(|boss: &BossDef| {
+                boss.validate()?;
+                for phase in &boss.phases {
+                    let kind = Path::new(ASSETS).join(phase.kind.as_str());
+                    anyhow::ensure!(kind.is_file(), "No enemy kind {kind:?}");
+                }
+                Ok(())
+            }),
+        ),
+        ("fsm", check(StateMachine::validate)),
+        ("moveset", check(Moveset::validate)),
+        ("waves", check(WaveScript::validate)),
+        (
+            "bullet_patterns.json",
+            check(|patterns: &HashMap<Symbol, BulletPattern>| {
+                patterns.values().try_for_each(BulletPattern::validate)
+            }),
+        ),
+        (
+            "factions.json",
+            check(|manifest: &FactionManifest| FactionTable::from_manifest(manifest).map(drop)),
+        ),
+    ];
+
+    for (path, check) in &table {
+        let files = json_files(Path::new(ASSETS).join(path));
+        assert!(!files.is_empty(), "No assets in {path:?}");
+        for file in files {
+            let json = fs::read_to_string(&file).unwrap();
+            check(&json).unwrap_or_else(|e| panic!("{file:?}: {e:#}"));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use lib_asset::{StateAnimation, StateMachine, Symbol, Trigger, animation_manifest::AnimationId};
+
+const MACHINE: &str = r#"{
+    "initial": "idle",
+    "states": {
+        "idle": {
+            "animation": {
+                "directions": ["BnuuyIdleR", "BnuuyIdleD", "BnuuyIdleL", "BnuuyIdleU"]
+            },
+            "transitions": [
+                { "to": "dash", "on": { "type": "Signal", "signal": "dash" } }
+            ]
+        },
+        "dash": {
+            "animation": "BnuuyDash",
+            "transitions": [
+                { "to": "idle", "on": { "type": "AnimEnd" } }
+            ]
+        }
+    }
+}"#;
+
+#[test]
+fn test_decode() {
+    let machine: StateMachine = serde_json::from_str(MACHINE).unwrap();
+    machine.validate().unwrap();
+
+    let idle = machine.state(Symbol::new("idle")).unwrap();
+    assert!(matches!(
+        &idle.animation,
+        StateAnimation::Directions { directions }
+            if directions[3] == Some(AnimationId::BnuuyIdleU)
+    ));
+    let dash = machine.state(Symbol::new("dash")).unwrap();
+    assert!(matches!(
+        dash.animation,
+        StateAnimation::Single(AnimationId::BnuuyDash)
+    ));
+
+    let next = machine.next_state(Symbol::new("dash"), |x| *x == Trigger::AnimEnd);
+    assert_eq!(next, Some(Symbol::new("idle")));
+    let next = machine.next_state(Symbol::new("idle"), |x| *x == Trigger::AnimEnd);
+    assert_eq!(next, None);
+
+    assert!(machine.state(Symbol::new("missing")).is_none());
+    let next = machine.next_state(Symbol::new("missing"), |_| true);
+    assert_eq!(next, None);
+}
+
+#[test]
+fn test_validate() {
+    let mut machine: StateMachine = serde_json::from_str(MACHINE).unwrap();
+    machine.initial = Symbol::new("missing");
+    assert!(machine.validate().is_err());
+
+    let mut machine: StateMachine = serde_json::from_str(MACHINE).unwrap();
+    machine.states.remove(&Symbol::new("dash"));
+    assert!(machine.validate().is_err());
+}
@@ -1,18 +1,32 @@
 use anyhow::bail;
 #[cfg(feature = "dev-env")]
 use lib_asset::AssetContainer;
-use lib_asset::{AssetKey, level::CharacterInfo};
+use lib_asset::{AssetKey, Symbol, level::CharacterInfo};
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::any::TypeId;
 
-use crate::Resources;
+use crate::{AudioBus, CharacterLook, Resources};
 
 pub trait ClipAction: std::fmt::Debug + Default + Copy + 'static {
+    /// Whether the action reacts to [Tween]s. The tweens are only
+    /// looked up for the actions that override [ClipAction::tween].
+    const TWEENABLE: bool = false;
+
     fn manifest_key() -> &'static str;
 
     fn global_offset(&mut self, _off: Vec2) {}
 
+    /// Applies a [Tween] sample on top of the authored values
+    fn tween(&mut self, _frame: &TweenFrame) {}
+
+    /// Flips the action left to right, for the mirrored animations
+    fn mirror(&mut self) {}
+
+    /// Moves the action from the pose of `from` towards its own during a
+    /// crossfade. `weight` goes from 0 (all `from`) to 1.
+    fn blend_from(&mut self, _from: &Self, _weight: f32) {}
+
     #[cfg(feature = "dev-env")]
     fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
         ui.label("No data");
@@ -23,13 +37,21 @@ pub trait ClipAction: std::fmt::Debug + Default + Copy + 'static {
     fn to_manifest(&self, resources: &Resources) -> serde_json::Value;
 }
 
-pub const CLIP_TYPES: [TypeId; 6] = [
+pub const CLIP_TYPES: [TypeId; 14] = [
     TypeId::of::<Invulnerability>(),
     TypeId::of::<Move>(),
     TypeId::of::<DrawSprite>(),
     TypeId::of::<AttackBox>(),
     TypeId::of::<LockInput>(),
     TypeId::of::<Spawn>(),
+    TypeId::of::<ParryWindow>(),
+    TypeId::of::<ShieldArc>(),
+    TypeId::of::<PlaySound>(),
+    TypeId::of::<RootMotion>(),
+    TypeId::of::<AnimEvent>(),
+    TypeId::of::<CancelWindow>(),
+    TypeId::of::<Hurtbox>(),
+    TypeId::of::<Tween>(),
 ];
 
 #[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
@@ -66,7 +88,7 @@ impl ClipAction for Move {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct DrawSprite {
     pub layer: u32,
     pub texture_id: AssetKey,
@@ -76,13 +98,60 @@ pub struct DrawSprite {
     pub rect_size: UVec2,
     pub sort_offset: f32,
     pub rotate_with_parent: bool,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Only set by the [Tween]s
+    pub scale: Vec2,
+    /// The tint, the [Tween]s multiply it further
+    pub color: Color,
+}
+
+impl Default for DrawSprite {
+    fn default() -> Self {
+        Self {
+            layer: 0,
+            texture_id: AssetKey::default(),
+            local_pos: Vec2::ZERO,
+            local_rotation: 0.0,
+            rect_pos: UVec2::ZERO,
+            rect_size: UVec2::ZERO,
+            sort_offset: 0.0,
+            rotate_with_parent: false,
+            flip_x: false,
+            flip_y: false,
+            scale: Vec2::ONE,
+            color: WHITE,
+        }
+    }
 }
 
 impl ClipAction for DrawSprite {
+    const TWEENABLE: bool = true;
+
     fn global_offset(&mut self, off: Vec2) {
         self.local_pos += off;
     }
 
+    fn tween(&mut self, frame: &TweenFrame) {
+        self.local_pos += frame.offset;
+        self.local_rotation += frame.rotation;
+        self.scale *= frame.scale;
+        self.color = frame.tint(self.color);
+    }
+
+    fn mirror(&mut self) {
+        // The sprites hang from their top left corner
+        let size = self.rect_size.as_vec2() * self.scale;
+        if self.rotate_with_parent {
+            self.local_pos.y = -self.local_pos.y - size.y;
+            self.flip_y = !self.flip_y;
+        } else {
+            self.local_pos.x = -self.local_pos.x - size.x;
+            self.flip_x = !self.flip_x;
+        }
+        self.local_rotation = -self.local_rotation;
+    }
+
     #[cfg(feature = "dev-env")]
     fn editor_ui(&mut self, textures: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
         use egui::*;
@@ -124,6 +193,16 @@ impl ClipAction for DrawSprite {
             ui.label("sort offset");
         });
         ui.checkbox(&mut self.rotate_with_parent, "rotate with parent");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.flip_x, "flip x");
+            ui.checkbox(&mut self.flip_y, "flip y");
+        });
+        ui.horizontal(|ui| {
+            let mut tint = self.color.to_vec().to_array();
+            ui.color_edit_button_rgba_unmultiplied(&mut tint);
+            self.color = Color::from_vec(tint.into());
+            ui.label("tint");
+        });
     }
 
     fn manifest_key() -> &'static str {
@@ -145,6 +224,10 @@ impl ClipAction for DrawSprite {
             rect_size: raw_manifest.rect_size,
             sort_offset: raw_manifest.sort_offset,
             rotate_with_parent: raw_manifest.rotate_with_parent,
+            flip_x: raw_manifest.flip_x,
+            flip_y: raw_manifest.flip_y,
+            scale: Vec2::ONE,
+            color: Color::from_vec(raw_manifest.tint.into()),
         })
     }
 
@@ -159,6 +242,9 @@ impl ClipAction for DrawSprite {
             rect_size: self.rect_size,
             sort_offset: self.sort_offset,
             rotate_with_parent: self.rotate_with_parent,
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            tint: self.color.to_vec().to_array(),
         };
         serde_json::to_value(raw_manifest).unwrap()
     }
@@ -172,13 +258,41 @@ pub struct AttackBox {
     pub shape: lib_col::Shape,
     pub rotate_with_parent: bool,
     pub graze_value: f32,
+    /// For how long the game freezes when the attack lands
+    #[serde(default)]
+    pub hitstop: f32,
 }
 
 impl ClipAction for AttackBox {
+    const TWEENABLE: bool = true;
+
     fn global_offset(&mut self, off: Vec2) {
         self.local_pos += off;
     }
 
+    fn tween(&mut self, frame: &TweenFrame) {
+        self.local_pos += frame.offset;
+        self.local_rotation += frame.rotation;
+        self.shape = frame.scale_shape(self.shape);
+    }
+
+    fn mirror(&mut self) {
+        mirror_local(
+            &mut self.local_pos,
+            &mut self.local_rotation,
+            self.rotate_with_parent,
+        );
+    }
+
+    fn blend_from(&mut self, from: &Self, weight: f32) {
+        blend_local(
+            &mut self.local_pos,
+            &mut self.local_rotation,
+            (from.local_pos, from.local_rotation),
+            weight,
+        );
+    }
+
     #[cfg(feature = "dev-env")]
     fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
         use egui::*;
@@ -201,6 +315,14 @@ impl ClipAction for AttackBox {
             ui.add(DragValue::new(&mut self.graze_value).range(0.0..=30.0));
             ui.label("graze value");
         });
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.hitstop)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+            ui.label("hitstop");
+        });
         shape_ui(ui, &mut self.shape);
     }
 
@@ -257,6 +379,19 @@ impl ClipAction for Spawn {
         self.local_pos += off;
     }
 
+    fn mirror(&mut self) {
+        mirror_local(
+            &mut self.local_pos,
+            &mut self.local_look,
+            self.rotate_with_parent,
+        );
+        // The look is a direction, not a shape's rotation, so it has to
+        // point the other way too when it is flipped left to right
+        if !self.rotate_with_parent {
+            self.local_look += std::f32::consts::PI;
+        }
+    }
+
     #[cfg(feature = "dev-env")]
     fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
         use egui::*;
@@ -287,6 +422,622 @@ impl ClipAction for Spawn {
     }
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParryKind {
+    /// A timed block. Works against attacks that touch the character.
+    #[default]
+    Parry,
+    /// A timed evasion. Works the same way, but is meant to be put
+    /// into dashes and rolls, so the attack just passes through.
+    PerfectDodge,
+}
+
+/// While this clip is active, attacks that touch the character
+/// are negated instead of dealing damage. The character gets
+/// `stamina_bonus` added to its [crate::GrazeGain].
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ParryWindow {
+    pub kind: ParryKind,
+    /// Send the projectiles back at their owners.
+    /// Has no effect on melee attacks.
+    pub reflect: bool,
+    pub stamina_bonus: f32,
+}
+
+impl ClipAction for ParryWindow {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ComboBox::new("parry_kind", "kind")
+            .selected_text(format!("{:?}", self.kind))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.kind, ParryKind::Parry, "Parry");
+                ui.selectable_value(&mut self.kind, ParryKind::PerfectDodge, "PerfectDodge");
+            });
+        ui.checkbox(&mut self.reflect, "reflect");
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.stamina_bonus).range(0.0..=100.0));
+            ui.label("stamina bonus");
+        });
+    }
+
+    fn manifest_key() -> &'static str {
+        "parry_window"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// While this clip is active, the attacks coming from inside
+/// the arc are blocked. Each blocked attack costs `stamina_cost`
+/// from the character's [crate::GrazeGain] and deals `chip_damage`.
+/// When there is not enough stamina, the attack goes through.
+/// Characters without [crate::GrazeGain] (like most enemies) block for free.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ShieldArc {
+    /// The direction of the shield relative to the character's look
+    pub local_angle: f32,
+    /// Half of the arc's angular size
+    pub half_width: f32,
+    pub stamina_cost: f32,
+    pub chip_damage: i32,
+}
+
+impl ShieldArc {
+    /// Checks if an attack coming from `attack_off` (relative to the
+    /// character's position) hits the shield.
+    pub fn covers(&self, look: CharacterLook, attack_off: Vec2) -> bool {
+        let shield_dir = look
+            .to_direction()
+            .rotate(Vec2::from_angle(self.local_angle));
+        let attack_dir = attack_off.normalize_or(shield_dir);
+        shield_dir.angle_to(attack_dir).abs() <= self.half_width
+    }
+}
+
+impl ClipAction for ShieldArc {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ui.horizontal(|ui| {
+            ui.drag_angle(&mut self.local_angle);
+            ui.label("local angle");
+        });
+        ui.horizontal(|ui| {
+            ui.drag_angle(&mut self.half_width);
+            ui.label("half width");
+        });
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.stamina_cost).range(0.0..=100.0));
+            ui.label("stamina cost");
+        });
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.chip_damage).range(0..=10));
+            ui.label("chip damage");
+        });
+    }
+
+    fn manifest_key() -> &'static str {
+        "shield_arc"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// Plays a sound once, when the clip starts. The pitch gets
+/// randomly shifted by up to `pitch_variance` (`0.1` is 10%).
+#[derive(Default, Debug, Clone, Copy)]
+pub struct PlaySound {
+    pub sound: AssetKey,
+    pub bus: AudioBus,
+    pub volume: f32,
+    pub pitch_variance: f32,
+}
+
+impl ClipAction for PlaySound {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ui.label(format!("sound: {:?}", self.sound));
+        ComboBox::new("audio_bus", "bus")
+            .selected_text(format!("{:?}", self.bus))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.bus, AudioBus::Sfx, "Sfx");
+                ui.selectable_value(&mut self.bus, AudioBus::Music, "Music");
+                ui.selectable_value(&mut self.bus, AudioBus::Ui, "Ui");
+            });
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.volume)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+            ui.label("volume");
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.pitch_variance)
+                    .range(0.0..=0.5)
+                    .speed(0.01),
+            );
+            ui.label("pitch variance");
+        });
+    }
+
+    fn manifest_key() -> &'static str {
+        "play_sound"
+    }
+
+    fn from_manifest(resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        let raw_manifest: lib_asset::animation_manifest::PlaySound =
+            serde_json::from_value(manifest.clone())?;
+        let Some(sound) = resources.audio.sounds.resolve(&raw_manifest.sound) else {
+            bail!("Sound {:?} is not loaded", raw_manifest.sound);
+        };
+        Ok(PlaySound {
+            sound,
+            bus: raw_manifest.bus,
+            volume: raw_manifest.volume,
+            pitch_variance: raw_manifest.pitch_variance,
+        })
+    }
+
+    fn to_manifest(&self, resources: &Resources) -> serde_json::Value {
+        let raw_manifest = lib_asset::animation_manifest::PlaySound {
+            sound: resources
+                .audio
+                .sounds
+                .inverse_resolve(self.sound)
+                .to_path_buf(),
+            bus: self.bus,
+            volume: self.volume,
+            pitch_variance: self.pitch_variance,
+        };
+        serde_json::to_value(raw_manifest).unwrap()
+    }
+}
+
+/// Moves the character while the clip is active. The velocity goes
+/// from `start_velocity` to `end_velocity` over the clip, so equal
+/// values give a constant speed. It is in pixels per second and with
+/// `rotate_with_parent` the `x` axis points where the character looks.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RootMotion {
+    pub start_velocity: Vec2,
+    pub end_velocity: Vec2,
+    pub rotate_with_parent: bool,
+}
+
+impl RootMotion {
+    /// The velocity after `progress` (from `0.0` to `1.0`) of the clip
+    pub fn velocity(&self, look: CharacterLook, progress: f32) -> Vec2 {
+        let velocity = self
+            .start_velocity
+            .lerp(self.end_velocity, progress.clamp(0.0, 1.0));
+        if self.rotate_with_parent {
+            look.to_direction().rotate(velocity)
+        } else {
+            velocity
+        }
+    }
+}
+
+impl ClipAction for RootMotion {
+    fn mirror(&mut self) {
+        if self.rotate_with_parent {
+            self.start_velocity.y = -self.start_velocity.y;
+            self.end_velocity.y = -self.end_velocity.y;
+        } else {
+            self.start_velocity.x = -self.start_velocity.x;
+            self.end_velocity.x = -self.end_velocity.x;
+        }
+    }
+
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.start_velocity.x).range(-1024.0..=1024.0));
+            ui.add(DragValue::new(&mut self.start_velocity.y).range(-1024.0..=1024.0));
+            ui.label("start velocity");
+        });
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.end_velocity.x).range(-1024.0..=1024.0));
+            ui.add(DragValue::new(&mut self.end_velocity.y).range(-1024.0..=1024.0));
+            ui.label("end velocity");
+        });
+        if ui.button("constant").clicked() {
+            self.end_velocity = self.start_velocity;
+        }
+        ui.checkbox(&mut self.rotate_with_parent, "rotate with parent");
+    }
+
+    fn manifest_key() -> &'static str {
+        "root_motion"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// Sends an [crate::AnimationEvent] when the clip starts. The
+/// characters get it through [crate::CharacterData::on_anim_event].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AnimEvent {
+    pub name: Symbol,
+}
+
+impl Default for AnimEvent {
+    fn default() -> Self {
+        Self {
+            name: Symbol::new("event"),
+        }
+    }
+}
+
+impl ClipAction for AnimEvent {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        symbol_ui(ui, "name", &mut self.name);
+    }
+
+    fn manifest_key() -> &'static str {
+        "event"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// While this clip is active, the character may interrupt the
+/// animation with whatever `into` stands for. The names are up to
+/// the game, e.g. `dash` or `attack`. See [crate::Character::can_cancel_into].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CancelWindow {
+    pub into: Symbol,
+}
+
+impl Default for CancelWindow {
+    fn default() -> Self {
+        Self {
+            into: Symbol::new("any"),
+        }
+    }
+}
+
+impl ClipAction for CancelWindow {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        symbol_ui(ui, "into", &mut self.into);
+    }
+
+    fn manifest_key() -> &'static str {
+        "cancel_window"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// Gives the character an extra shape that can be hit while the clip
+/// is active. With `replace_body`, the character's own body can't be
+/// hit at all, so only its hurtboxes count (e.g. when ducking).
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Hurtbox {
+    pub local_pos: Vec2,
+    pub local_rotation: f32,
+    pub shape: lib_col::Shape,
+    pub rotate_with_parent: bool,
+    pub replace_body: bool,
+}
+
+impl ClipAction for Hurtbox {
+    const TWEENABLE: bool = true;
+
+    fn global_offset(&mut self, off: Vec2) {
+        self.local_pos += off;
+    }
+
+    fn tween(&mut self, frame: &TweenFrame) {
+        self.local_pos += frame.offset;
+        self.local_rotation += frame.rotation;
+        self.shape = frame.scale_shape(self.shape);
+    }
+
+    fn mirror(&mut self) {
+        mirror_local(
+            &mut self.local_pos,
+            &mut self.local_rotation,
+            self.rotate_with_parent,
+        );
+    }
+
+    fn blend_from(&mut self, from: &Self, weight: f32) {
+        blend_local(
+            &mut self.local_pos,
+            &mut self.local_rotation,
+            (from.local_pos, from.local_rotation),
+            weight,
+        );
+    }
+
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.local_pos.x).range(-256.0..=256.0));
+            ui.add(DragValue::new(&mut self.local_pos.y).range(-256.0..=256.0));
+            ui.label("local pos");
+        });
+        ui.horizontal(|ui| {
+            ui.drag_angle(&mut self.local_rotation);
+            ui.label("local rotation");
+        });
+        ui.checkbox(&mut self.rotate_with_parent, "rotate with parent");
+        ui.checkbox(&mut self.replace_body, "replace body");
+        shape_ui(ui, &mut self.shape);
+    }
+
+    fn manifest_key() -> &'static str {
+        "hurtbox"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// The curve a [Tween] follows from its start to its end
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    /// Overshoots the end a bit before settling
+    BackOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 9] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::SineInOut,
+        Easing::BackOut,
+    ];
+
+    /// Maps the progress `t` from `0.0` to `1.0` onto the curve
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut if t < 0.5 => 2.0 * t * t,
+            Easing::QuadInOut => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0,
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::CubicInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::SineInOut => -((std::f32::consts::PI * t).cos() - 1.0) / 2.0,
+            Easing::BackOut => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+/// The values a [Tween] puts on top of the clips it animates.
+/// The offsets are in the clips' local space.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TweenFrame {
+    pub offset: Vec2,
+    pub rotation: f32,
+    /// Scales the sprites and the shapes. Circles use the larger axis.
+    pub scale: Vec2,
+    /// Multiplies the sprite color
+    pub color: [f32; 4],
+}
+
+impl Default for TweenFrame {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            color: [1.0; 4],
+        }
+    }
+}
+
+impl TweenFrame {
+    pub fn lerp(&self, other: &TweenFrame, t: f32) -> TweenFrame {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        TweenFrame {
+            offset: self.offset.lerp(other.offset, t),
+            rotation: lerp(self.rotation, other.rotation),
+            scale: self.scale.lerp(other.scale, t),
+            color: std::array::from_fn(|i| lerp(self.color[i], other.color[i])),
+        }
+    }
+
+    pub fn tint(&self, color: Color) -> Color {
+        let [r, g, b, a] = self.color;
+        Color::new(color.r * r, color.g * g, color.b * b, color.a * a)
+    }
+
+    pub fn scale_shape(&self, shape: lib_col::Shape) -> lib_col::Shape {
+        match shape {
+            lib_col::Shape::Rect { width, height } => lib_col::Shape::Rect {
+                width: width * self.scale.x,
+                height: height * self.scale.y,
+            },
+            lib_col::Shape::Circle { radius } => lib_col::Shape::Circle {
+                radius: radius * self.scale.max_element(),
+            },
+        }
+    }
+}
+
+/// Animates the [DrawSprite], [AttackBox] and [Hurtbox] clips on the
+/// tracks with the same name as the tween's track. Goes from `from` to
+/// `to` along `easing` across the clip.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Tween {
+    pub easing: Easing,
+    pub from: TweenFrame,
+    pub to: TweenFrame,
+}
+
+impl Tween {
+    /// The frame at `progress` through the clip
+    pub fn sample(&self, progress: f32) -> TweenFrame {
+        self.from.lerp(&self.to, self.easing.apply(progress))
+    }
+}
+
+impl ClipAction for Tween {
+    #[cfg(feature = "dev-env")]
+    fn editor_ui(&mut self, _resources: &AssetContainer<Texture2D>, ui: &mut egui::Ui) {
+        use egui::*;
+
+        ComboBox::new("easing", "easing")
+            .selected_text(format!("{:?}", self.easing))
+            .show_ui(ui, |ui| {
+                for easing in Easing::ALL {
+                    ui.selectable_value(&mut self.easing, easing, format!("{easing:?}"));
+                }
+            });
+        ui.label("from");
+        tween_frame_ui(ui, &mut self.from);
+        ui.label("to");
+        tween_frame_ui(ui, &mut self.to);
+    }
+
+    fn manifest_key() -> &'static str {
+        "tween"
+    }
+
+    fn from_manifest(_resources: &Resources, manifest: &serde_json::Value) -> anyhow::Result<Self> {
+        serde_json::from_value(manifest.clone()).map_err(anyhow::Error::from)
+    }
+
+    fn to_manifest(&self, _resource: &Resources) -> serde_json::Value {
+        serde_json::to_value(&self).unwrap()
+    }
+}
+
+/// The characters mirror their look, when they play a mirrored
+/// animation. So the clips rotating with them only flip up and down.
+fn mirror_local(local_pos: &mut Vec2, local_rotation: &mut f32, rotate_with_parent: bool) {
+    if rotate_with_parent {
+        local_pos.y = -local_pos.y;
+    } else {
+        local_pos.x = -local_pos.x;
+    }
+    *local_rotation = -*local_rotation;
+}
+
+fn blend_local(local_pos: &mut Vec2, local_rotation: &mut f32, from: (Vec2, f32), weight: f32) {
+    *local_pos = from.0.lerp(*local_pos, weight);
+    *local_rotation = from.1 + (*local_rotation - from.1) * weight;
+}
+
+#[cfg(feature = "dev-env")]
+fn symbol_ui(ui: &mut egui::Ui, label: &str, symbol: &mut Symbol) {
+    // Keep the edited name around, so we only intern the final one
+    let id = ui.id().with(label);
+    let mut name = ui.data_mut(|x| x.get_temp::<String>(id).unwrap_or(symbol.to_string()));
+    ui.horizontal(|ui| {
+        ui.label(label);
+        if ui.text_edit_singleline(&mut name).lost_focus() {
+            *symbol = Symbol::new(&name);
+        }
+    });
+    ui.data_mut(|x| x.insert_temp(id, name));
+}
+
+#[cfg(feature = "dev-env")]
+fn tween_frame_ui(ui: &mut egui::Ui, frame: &mut TweenFrame) {
+    use egui::*;
+
+    ui.horizontal(|ui| {
+        ui.add(DragValue::new(&mut frame.offset.x).range(-256.0..=256.0));
+        ui.add(DragValue::new(&mut frame.offset.y).range(-256.0..=256.0));
+        ui.label("offset");
+    });
+    ui.horizontal(|ui| {
+        ui.drag_angle(&mut frame.rotation);
+        ui.label("rotation");
+    });
+    ui.horizontal(|ui| {
+        ui.add(
+            DragValue::new(&mut frame.scale.x)
+                .range(0.0..=8.0)
+                .speed(0.01),
+        );
+        ui.add(
+            DragValue::new(&mut frame.scale.y)
+                .range(0.0..=8.0)
+                .speed(0.01),
+        );
+        ui.label("scale");
+    });
+    ui.horizontal(|ui| {
+        ui.color_edit_button_rgba_unmultiplied(&mut frame.color);
+        ui.label("color");
+    });
+}
+
 #[cfg(feature = "dev-env")]
 fn group_ui(ui: &mut egui::Ui, group: &mut lib_col::Group) {
     let response = ui.button("Configure");
@@ -381,6 +1132,95 @@ fn shape_ui(ui: &mut egui::Ui, shape: &mut lib_col::Shape) {
     }
 }
 
+#[cfg(feature = "dev-env")]
+fn projectile_behaviour_ui(ui: &mut egui::Ui, behaviour: &mut lib_asset::ProjectileBehaviour) {
+    use egui::*;
+    use lib_asset::{Acceleration, Homing, Wave};
+
+    optional_ui(
+        ui,
+        "lifetime",
+        &mut behaviour.lifetime,
+        5.0,
+        |ui, lifetime| {
+            ui.add(DragValue::new(lifetime).range(0.0..=60.0));
+        },
+    );
+    optional_ui(
+        ui,
+        "max range",
+        &mut behaviour.max_range,
+        128.0,
+        |ui, range| {
+            ui.add(DragValue::new(range).range(0.0..=2048.0));
+        },
+    );
+    ui.horizontal(|ui| {
+        ui.label("bounces");
+        ui.add(DragValue::new(&mut behaviour.bounces));
+    });
+    let homing = Homing {
+        turn_rate: 1.0,
+        range: 96.0,
+    };
+    optional_ui(ui, "homing", &mut behaviour.homing, homing, |ui, homing| {
+        ui.drag_angle(&mut homing.turn_rate);
+        ui.label("turn rate");
+        ui.add(DragValue::new(&mut homing.range).range(0.0..=2048.0));
+        ui.label("range");
+    });
+    let acceleration = Acceleration {
+        accel: 0.0,
+        min_speed: 0.0,
+        max_speed: 64.0,
+    };
+    optional_ui(
+        ui,
+        "acceleration",
+        &mut behaviour.acceleration,
+        acceleration,
+        |ui, acceleration| {
+            ui.add(DragValue::new(&mut acceleration.accel).range(-512.0..=512.0));
+            ui.label("accel");
+            ui.add(DragValue::new(&mut acceleration.min_speed).range(0.0..=512.0));
+            ui.label("min speed");
+            ui.add(DragValue::new(&mut acceleration.max_speed).range(0.0..=512.0));
+            ui.label("max speed");
+        },
+    );
+    let wave = Wave {
+        amplitude: 4.0,
+        frequency: 1.0,
+    };
+    optional_ui(ui, "wave", &mut behaviour.wave, wave, |ui, wave| {
+        ui.add(DragValue::new(&mut wave.amplitude).range(0.0..=64.0));
+        ui.label("amplitude");
+        ui.add(DragValue::new(&mut wave.frequency).range(0.0..=16.0));
+        ui.label("frequency");
+    });
+}
+
+/// A checkbox to turn an optional setting on, followed by its editor
+#[cfg(feature = "dev-env")]
+fn optional_ui<T>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: T,
+    body: impl FnOnce(&mut egui::Ui, &mut T),
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        ui.checkbox(&mut enabled, label);
+        if enabled != value.is_some() {
+            *value = enabled.then_some(default);
+        }
+        if let Some(value) = value {
+            body(ui, value);
+        }
+    });
+}
+
 #[cfg(feature = "dev-env")]
 fn character_info_ui(ui: &mut egui::Ui, character_info: &mut CharacterInfo) {
     use egui::*;
@@ -392,13 +1232,26 @@ fn character_info_ui(ui: &mut egui::Ui, character_info: &mut CharacterInfo) {
         "Stabber",
         "BasicBullet",
         "Shooter",
+        "Emitter",
+        "Scripted",
+        "Boss",
     ];
     let defaults = [
         CharacterInfo::Player {},
         CharacterInfo::Goal {},
         CharacterInfo::Damager {},
         CharacterInfo::Stabber {},
-        CharacterInfo::BasicBullet {},
+        CharacterInfo::BasicBullet { behaviour: None },
+        CharacterInfo::Shooter {},
+        CharacterInfo::Emitter {
+            pattern: lib_asset::Symbol::new("ring"),
+        },
+        CharacterInfo::Scripted {
+            kind: lib_asset::Symbol::new("ai/brute.json"),
+        },
+        CharacterInfo::Boss {
+            boss: lib_asset::Symbol::new("boss/warden.json"),
+        },
     ];
     let curr_id = match character_info {
         CharacterInfo::Player { .. } => 0,
@@ -407,6 +1260,9 @@ fn character_info_ui(ui: &mut egui::Ui, character_info: &mut CharacterInfo) {
         CharacterInfo::Stabber { .. } => 3,
         CharacterInfo::BasicBullet { .. } => 4,
         CharacterInfo::Shooter {} => 5,
+        CharacterInfo::Emitter { .. } => 6,
+        CharacterInfo::Scripted { .. } => 7,
+        CharacterInfo::Boss { .. } => 8,
     };
     let mut new_id = curr_id;
     ComboBox::new("info", "CharacterInfo")
@@ -432,11 +1288,141 @@ fn character_info_ui(ui: &mut egui::Ui, character_info: &mut CharacterInfo) {
         CharacterInfo::Stabber {} => {
             ui.label("No data");
         }
-        CharacterInfo::BasicBullet {} => {
-            ui.label("No data");
+        CharacterInfo::BasicBullet { behaviour } => {
+            let mut custom = behaviour.is_some();
+            ui.checkbox(&mut custom, "custom behaviour");
+            if custom != behaviour.is_some() {
+                *behaviour = custom.then(lib_asset::ProjectileBehaviour::default);
+            }
+            if let Some(behaviour) = behaviour {
+                projectile_behaviour_ui(ui, behaviour);
+            }
         }
         CharacterInfo::Shooter {} => {
             ui.label("No data");
         }
+        CharacterInfo::Emitter { pattern } => symbol_ui(ui, "pattern", pattern),
+        CharacterInfo::Scripted { kind } => symbol_ui(ui, "kind", kind),
+        CharacterInfo::Boss { boss } => symbol_ui(ui, "boss", boss),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::{Vec2, vec2};
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use super::{ClipAction, Easing, RootMotion, ShieldArc, Spawn, Tween, TweenFrame};
+    use crate::CharacterLook;
+
+    #[test]
+    fn test_shield_arc_covers() {
+        let shield = ShieldArc {
+            local_angle: 0.0,
+            half_width: FRAC_PI_4,
+            ..Default::default()
+        };
+        let look_right = CharacterLook(0.0);
+        let look_down = CharacterLook(FRAC_PI_2);
+
+        assert!(shield.covers(look_right, vec2(10.0, 0.0)));
+        assert!(shield.covers(look_right, vec2(10.0, 5.0)));
+        assert!(!shield.covers(look_right, vec2(0.0, 10.0)));
+        assert!(!shield.covers(look_right, vec2(-10.0, 0.0)));
+        assert!(shield.covers(look_down, vec2(0.0, 10.0)));
+
+        let back_shield = ShieldArc {
+            local_angle: PI,
+            ..shield
+        };
+        assert!(back_shield.covers(look_right, vec2(-10.0, 0.0)));
+        assert!(!back_shield.covers(look_right, vec2(10.0, 0.0)));
+    }
+
+    #[test]
+    fn test_root_motion_velocity() {
+        let lunge = RootMotion {
+            start_velocity: vec2(100.0, 0.0),
+            end_velocity: vec2(0.0, 0.0),
+            rotate_with_parent: true,
+        };
+        let look_down = CharacterLook(FRAC_PI_2);
+
+        assert_eq!(lunge.velocity(CharacterLook(0.0), 0.0), vec2(100.0, 0.0));
+        assert_eq!(lunge.velocity(CharacterLook(0.0), 0.5), vec2(50.0, 0.0));
+        assert!(lunge.velocity(look_down, 0.0).distance(vec2(0.0, 100.0)) < 1e-3);
+        // Past the end the velocity stays put
+        assert_eq!(lunge.velocity(CharacterLook(0.0), 2.0), Vec2::ZERO);
+
+        let drift = RootMotion {
+            rotate_with_parent: false,
+            ..lunge
+        };
+        assert_eq!(drift.velocity(look_down, 0.0), vec2(100.0, 0.0));
+    }
+
+    #[test]
+    fn test_spawn_mirror() {
+        // Spawned to the right, looking up and to the right
+        let mut spawn = Spawn {
+            local_pos: vec2(10.0, -5.0),
+            local_look: -FRAC_PI_4,
+            ..Default::default()
+        };
+        spawn.mirror();
+        assert_eq!(spawn.local_pos, vec2(-10.0, -5.0));
+        let look = Vec2::from_angle(spawn.local_look);
+        assert!(look.distance(vec2(-1.0, -1.0).normalize()) < 1e-5);
+
+        // Relative to the mirrored look, only up and down swap
+        let mut spawn = Spawn {
+            local_pos: vec2(10.0, -5.0),
+            local_look: -FRAC_PI_4,
+            rotate_with_parent: true,
+            ..Default::default()
+        };
+        spawn.mirror();
+        assert_eq!(spawn.local_pos, vec2(10.0, 5.0));
+        assert_eq!(spawn.local_look, FRAC_PI_4);
+    }
+
+    #[test]
+    fn test_easing_ends() {
+        for easing in Easing::ALL {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{easing:?}");
+        }
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+        assert_eq!(Easing::Linear.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn test_tween_sample() {
+        let grow = Tween {
+            easing: Easing::Linear,
+            from: TweenFrame::default(),
+            to: TweenFrame {
+                offset: vec2(10.0, 0.0),
+                scale: vec2(2.0, 3.0),
+                color: [1.0, 1.0, 1.0, 0.0],
+                ..Default::default()
+            },
+        };
+
+        let frame = grow.sample(0.5);
+        assert_eq!(frame.offset, vec2(5.0, 0.0));
+        assert_eq!(frame.scale, vec2(1.5, 2.0));
+        assert_eq!(frame.color, [1.0, 1.0, 1.0, 0.5]);
+        let rect = frame.scale_shape(lib_col::Shape::Rect {
+            width: 2.0,
+            height: 2.0,
+        });
+        assert!(matches!(
+            rect,
+            lib_col::Shape::Rect { width, height } if width == 3.0 && height == 4.0
+        ));
+        let circle = frame.scale_shape(lib_col::Shape::Circle { radius: 2.0 });
+        assert!(matches!(circle, lib_col::Shape::Circle { radius } if radius == 4.0));
     }
 }
@@ -56,6 +56,8 @@ pub trait AnimContainer: std::fmt::Debug + Any {
 #[derive(Default, Debug)]
 pub struct Animation {
     pub is_looping: bool,
+    /// See [lib_asset::animation_manifest::Animation::crossfade]
+    pub crossfade: f32,
     pub action_tracks: HashMap<TypeId, Box<dyn AnimContainer>>,
 }
 
@@ -85,6 +87,13 @@ impl Clip {
     pub fn end(&self) -> u32 {
         self.start + self.len
     }
+
+    pub fn progress(&self, pos: u32) -> f32 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        pos.saturating_sub(self.start) as f32 / self.len as f32
+    }
 }
 
 impl Animation {
@@ -99,6 +108,7 @@ impl Animation {
     pub fn to_manifest(&self, resources: &Resources) -> lib_asset::animation_manifest::Animation {
         lib_asset::animation_manifest::Animation {
             is_looping: self.is_looping,
+            crossfade: self.crossfade,
             action_tracks: self
                 .action_tracks
                 .values()
@@ -124,6 +134,14 @@ impl Animation {
         Self::add_action_track::<AttackBox>(resources, &mut action_tracks, manifest)?;
         Self::add_action_track::<LockInput>(resources, &mut action_tracks, manifest)?;
         Self::add_action_track::<Spawn>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<ParryWindow>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<ShieldArc>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<PlaySound>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<RootMotion>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<AnimEvent>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<CancelWindow>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<Hurtbox>(resources, &mut action_tracks, manifest)?;
+        Self::add_action_track::<Tween>(resources, &mut action_tracks, manifest)?;
         debug_assert_eq!(
             action_tracks.len(),
             CLIP_TYPES.len(),
@@ -131,6 +149,7 @@ impl Animation {
         );
         Ok(Animation {
             is_looping: manifest.is_looping,
+            crossfade: manifest.crossfade,
             action_tracks,
         })
     }
@@ -149,14 +168,59 @@ impl Animation {
         Ok(())
     }
 
+    /// The clips that `pos` is in, with the [Tween]s applied
     pub fn active_clips<T: ClipAction>(&self, pos: u32) -> impl Iterator<Item = (u32, T)> {
+        self.active_clips_named::<T>(pos)
+            .map(|(idx, _, action)| (idx, action))
+    }
+
+    /// Same as [Animation::active_clips], but yields how far
+    /// into each clip `pos` is, from `0.0` to `1.0`
+    pub fn active_clips_progress<T: ClipAction>(&self, pos: u32) -> impl Iterator<Item = (f32, T)> {
         self.action_track::<T>()
+            .clips
+            .iter()
+            .copied()
+            .filter(move |(x, _)| x.contains_pos(pos))
+            .map(move |(clip, action)| (clip.progress(pos), action))
+    }
+
+    /// Same as [Animation::active_clips], but also yields
+    /// the names of the tracks the clips are on
+    pub fn active_clips_named<T: ClipAction>(
+        &self,
+        pos: u32,
+    ) -> impl Iterator<Item = (u32, &str, T)> {
+        let track = self.action_track::<T>();
+        track
             .clips
             .iter()
             .copied()
             .enumerate()
             .filter(move |(_, (x, _))| x.contains_pos(pos))
-            .map(|(idx, (_, action))| (idx as u32, action))
+            .map(move |(idx, (clip, mut action))| {
+                let name = track.tracks[clip.track_id as usize].name.as_str();
+                self.apply_tweens(name, pos, &mut action);
+                (idx as u32, name, action)
+            })
+    }
+
+    /// Applies the active [Tween]s on the tracks named `track` to `action`
+    fn apply_tweens<T: ClipAction>(&self, track: &str, pos: u32, action: &mut T) {
+        if !T::TWEENABLE {
+            return;
+        }
+        let Some(container) = self.action_tracks.get(&TypeId::of::<Tween>()) else {
+            return;
+        };
+        let Some(tweens) = (container.as_ref() as &dyn Any).downcast_ref::<Clips<Tween>>() else {
+            return;
+        };
+        for (clip, tween) in tweens.clips.iter() {
+            if clip.contains_pos(pos) && tweens.tracks[clip.track_id as usize].name == track {
+                action.tween(&tween.sample(clip.progress(pos)));
+            }
+        }
     }
 
     pub fn inactive_clips<T: ClipAction>(&self, pos: u32) -> impl Iterator<Item = (u32, T)> {
@@ -5,12 +5,13 @@ use std::any::TypeId;
 
 use hashbrown::HashMap;
 use hecs::{CommandBuffer, Entity, EntityBuilder, World};
-use lib_asset::level::CharacterDef;
+use lib_asset::{Symbol, animation_manifest::AnimationId, level::CharacterDef};
 use macroquad::prelude::*;
 
 use crate::{
-    AnimationPlay, ClipActionObject, Game, Resources, Sprite, Transform, build_attack, col_group,
-    col_query, for_each_character,
+    AnimationLayers, AnimationPlay, BodyTag, ClipActionObject, Defense, Game, HitStop, HurtboxOf,
+    Resources, SpawnedBy, Sprite, SpriteFlash, Transform, build_attack, col_group, col_query,
+    emit_event, for_each_character,
 };
 
 pub use actions::*;
@@ -18,29 +19,29 @@ pub use container::*;
 
 pub const ANIMATION_TIME_UNIT: f32 = 1.0 / 1000.0;
 
+/// Sent when a character's animation reaches an [AnimEvent] clip
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub animation: AnimationId,
+    pub name: Symbol,
+}
+
 pub(crate) fn update(dt: f32, world: &mut World, resources: &Resources) {
     for_each_character::<()>(world, resources, |_, character| {
-        let play = character.character_q.play;
-        let anim = character.animation;
+        let dt = character.local_dt(dt);
+        character.character_q.play.advance(dt, character.animation);
 
-        let max_pos = anim.max_pos();
-        if max_pos == 0 || play.pause {
+        let Some(layers) = character.character_q.layers else {
             return;
-        }
-
-        play.total_dt += dt;
-        if play.total_dt < ANIMATION_TIME_UNIT {
-            return;
-        }
-
-        let cursor_delta = play.total_dt.div_euclid(ANIMATION_TIME_UNIT);
-        play.total_dt -= cursor_delta * ANIMATION_TIME_UNIT;
-        play.cursor += cursor_delta as u32;
-        if anim.is_looping {
-            play.cursor %= max_pos;
-        } else {
-            play.cursor = play.cursor.min(max_pos);
-        }
+        };
+        layers.layers.retain_mut(|layer| {
+            let Some(animation) = resources.animations.get(&layer.animation) else {
+                return false;
+            };
+            layer.advance(dt, animation);
+            animation.is_looping || !layer.is_done(animation)
+        });
     });
 }
 
@@ -66,25 +67,34 @@ pub(crate) fn delete_clip_action_objects(
             cmds.despawn(*entity);
             continue;
         };
-        if play.animation != event.animation {
+        // Only the sprites of the animation, that is faded out, stay around
+        let is_fading = event.kind == TypeId::of::<DrawSprite>()
+            && play.fade.is_some_and(|fade| fade.from == event.animation);
+        let is_layer = world
+            .get::<&AnimationLayers>(event.parent)
+            .is_ok_and(|layers| layers.is_playing(event.animation));
+        if play.animation != event.animation && !is_fading && !is_layer {
             cmds.despawn(*entity);
         }
     }
 
     for_each_character::<()>(world, resources, |parent, character| {
-        for (kind, clip_id) in character
-            .animation
-            .all_inactive_clips(character.anim_cursor())
-        {
-            let Some(entity) = clip_action_objects.get(&ClipActionObject {
-                parent,
-                kind,
-                clip_id,
-                animation: character.animation_id(),
-            }) else {
-                continue;
-            };
-            cmds.despawn(*entity);
+        let fading = character.character_q.play.fade.and_then(|fade| {
+            let animation = resources.animations.get(&fade.from)?;
+            Some((fade.from, animation, fade.position))
+        });
+        for (id, animation, pos) in character.playing().chain(fading) {
+            for (kind, clip_id) in animation.all_inactive_clips(pos) {
+                let Some(entity) = clip_action_objects.get(&ClipActionObject {
+                    parent,
+                    kind,
+                    clip_id,
+                    animation: id,
+                }) else {
+                    continue;
+                };
+                cmds.despawn(*entity);
+            }
         }
     });
 }
@@ -96,13 +106,10 @@ pub(crate) fn update_attack_boxes(
     active_events: &HashMap<ClipActionObject, Entity>,
 ) {
     for_each_character::<()>(world, resources, |parent, character| {
-        for (clip_id, attack) in character
-            .animation
-            .active_clips::<AttackBox>(character.anim_cursor())
-        {
+        for (animation, clip_id, attack) in character.blended_clips::<AttackBox>() {
             let event = ClipActionObject {
                 parent,
-                animation: character.animation_id(),
+                animation,
                 clip_id,
                 kind: TypeId::of::<AttackBox>(),
             };
@@ -127,11 +134,14 @@ pub(crate) fn update_attack_boxes(
                     build_attack(
                         &mut builder,
                         new_col_tf,
-                        *character.character_q.team,
+                        *character.character_q.faction,
                         attack.shape,
                         attack.graze_value,
                         col_group::NONE,
                     );
+                    if attack.hitstop > 0.0 {
+                        builder.add(HitStop(attack.hitstop));
+                    }
                     builder.add(event);
                     cmds.spawn(builder.build());
                 }
@@ -140,6 +150,52 @@ pub(crate) fn update_attack_boxes(
     });
 }
 
+pub(crate) fn update_hurtboxes(
+    world: &mut World,
+    resources: &Resources,
+    cmds: &mut CommandBuffer,
+    active_events: &HashMap<ClipActionObject, Entity>,
+) {
+    for_each_character::<()>(world, resources, |parent, character| {
+        for (animation, clip_id, hurtbox) in character.blended_clips::<Hurtbox>() {
+            let event = ClipActionObject {
+                parent,
+                animation,
+                clip_id,
+                kind: TypeId::of::<Hurtbox>(),
+            };
+            let new_col_tf = character.transform_child(
+                hurtbox.rotate_with_parent,
+                hurtbox.local_pos,
+                hurtbox.local_rotation,
+            );
+
+            match active_events.get(&event).copied() {
+                Some(ent) => {
+                    let mut query = world
+                        .query_one::<(&mut Transform, &mut BodyTag)>(ent)
+                        .expect("incomplete hurtbox components");
+                    let (col_tf, body) = query.get().unwrap();
+                    *col_tf = new_col_tf;
+                    body.shape = hurtbox.shape;
+                }
+                None => {
+                    // The attacks aimed at the owner must reach its hurtboxes too
+                    cmds.spawn((
+                        new_col_tf,
+                        BodyTag {
+                            groups: character.character_q.body.groups,
+                            shape: hurtbox.shape,
+                        },
+                        HurtboxOf { owner: parent },
+                        event,
+                    ));
+                }
+            }
+        }
+    });
+}
+
 pub(crate) fn update_spawned<G: Game>(
     world: &mut World,
     resources: &Resources,
@@ -148,13 +204,10 @@ pub(crate) fn update_spawned<G: Game>(
     active_events: &HashMap<ClipActionObject, Entity>,
 ) {
     for_each_character::<()>(world, resources, |parent, character| {
-        for (clip_id, spawn) in character
-            .animation
-            .active_clips::<Spawn>(character.anim_cursor())
-        {
+        for (animation, clip_id, spawn) in character.active_clips::<Spawn>() {
             let event = ClipActionObject {
                 parent,
-                animation: character.animation_id(),
+                animation,
                 clip_id,
                 kind: TypeId::of::<Spawn>(),
             };
@@ -175,6 +228,7 @@ pub(crate) fn update_spawned<G: Game>(
             };
             let mut builder = EntityBuilder::new();
             game.init_character(resources, &mut builder, def);
+            builder.add(SpawnedBy { owner: parent });
             cmds.spawn(builder.build());
 
             // Spawn a dummy entity.
@@ -185,67 +239,602 @@ pub(crate) fn update_spawned<G: Game>(
     });
 }
 
+pub(crate) fn update_sounds(
+    world: &mut World,
+    resources: &Resources,
+    cmds: &mut CommandBuffer,
+    active_events: &HashMap<ClipActionObject, Entity>,
+) {
+    for_each_character::<()>(world, resources, |parent, character| {
+        for (animation, clip_id, sound) in character.active_clips::<PlaySound>() {
+            let event = ClipActionObject {
+                parent,
+                animation,
+                clip_id,
+                kind: TypeId::of::<PlaySound>(),
+            };
+            if active_events.contains_key(&event) {
+                continue;
+            }
+
+            resources
+                .audio
+                .play(sound.sound, sound.bus, sound.volume, sound.pitch_variance);
+            // Same as with the spawns, the dummy keeps the sound
+            // from playing again till the clip is over
+            cmds.spawn((event,));
+        }
+    });
+}
+
+/// Adds the [RootMotion] clips on top of the walk step. Must run
+/// after the characters have set their walk steps for the frame.
+pub(crate) fn update_root_motion(dt: f32, world: &mut World, resources: &Resources) {
+    for_each_character::<()>(world, resources, |_, character| {
+        let dt = character.local_dt(dt);
+        let look = *character.character_q.look;
+        let step = character
+            .playing()
+            .flat_map(|(id, animation, pos)| {
+                let mirrored = character.is_mirrored(id);
+                animation.active_clips_progress::<RootMotion>(pos).map(
+                    move |(progress, mut motion)| {
+                        if mirrored {
+                            motion.mirror();
+                        }
+                        (progress, motion)
+                    },
+                )
+            })
+            .map(|(progress, motion)| motion.velocity(look, progress) * dt)
+            .sum::<Vec2>();
+        character.character_q.kinematic.dr += step;
+    });
+}
+
+pub(crate) fn update_anim_events(
+    world: &mut World,
+    resources: &Resources,
+    cmds: &mut CommandBuffer,
+    active_events: &HashMap<ClipActionObject, Entity>,
+) {
+    for_each_character::<()>(world, resources, |parent, character| {
+        for (animation, clip_id, event) in character.active_clips::<AnimEvent>() {
+            let lock = ClipActionObject {
+                parent,
+                animation,
+                clip_id,
+                kind: TypeId::of::<AnimEvent>(),
+            };
+            if active_events.contains_key(&lock) {
+                continue;
+            }
+
+            emit_event(
+                cmds,
+                AnimationEvent {
+                    entity: parent,
+                    animation,
+                    name: event.name,
+                },
+            );
+            cmds.spawn((lock,));
+        }
+    });
+}
+
 pub(crate) fn update_invulnerability(world: &mut World, resources: &Resources) {
     for_each_character::<()>(world, resources, |_, character| {
-        let is_invulnerable = character
-            .animation
-            .active_clips::<Invulnerability>(character.anim_cursor())
-            .next()
-            .is_some();
+        let is_invulnerable = character.active_clips::<Invulnerability>().next().is_some();
         character.character_q.hp.is_invulnerable = is_invulnerable;
     });
 }
 
+pub(crate) fn update_defense(world: &mut World, resources: &Resources) {
+    for_each_character::<&mut Defense>(world, resources, |_, character| {
+        character.data.parry = character
+            .active_clips::<ParryWindow>()
+            .map(|(_, _, parry)| parry)
+            .next();
+        character.data.shield = character
+            .active_clips::<ShieldArc>()
+            .map(|(_, _, shield)| shield)
+            .next();
+        character.data.body_replaced = character
+            .active_clips::<Hurtbox>()
+            .any(|(_, _, hurtbox)| hurtbox.replace_body);
+    });
+}
+
 pub(crate) fn update_draw_sprites(
     world: &mut World,
     resources: &Resources,
     cmds: &mut CommandBuffer,
     active_events: &HashMap<ClipActionObject, Entity>,
 ) {
-    for_each_character::<()>(world, resources, |parent, character| {
-        for (clip_id, draw_sprite) in character
+    for_each_character::<Option<&SpriteFlash>>(world, resources, |parent, character| {
+        let is_invulnerable = character.character_q.hp.is_invulnerable;
+        let sprite_color = |draw_sprite: &DrawSprite, alpha: f32| {
+            let color = Color {
+                a: draw_sprite.color.a * alpha,
+                ..draw_sprite.color
+            };
+            match character.data {
+                Some(flash) => flash.apply(color, is_invulnerable),
+                None => color,
+            }
+        };
+        let fade = character
+            .character_q
+            .play
+            .fade
+            .and_then(|fade| Some((fade, resources.animations.get(&fade.from)?)));
+        let weight = fade.map_or(1.0, |(fade, _)| fade.weight());
+        let fading = fade
+            .into_iter()
+            .flat_map(|(fade, animation)| {
+                animation
+                    .active_clips_named::<DrawSprite>(fade.position)
+                    .map(move |(clip_id, track, mut draw_sprite)| {
+                        if fade.mirrored {
+                            draw_sprite.mirror();
+                        }
+                        (clip_id, track, draw_sprite)
+                    })
+            })
+            .collect::<Vec<_>>();
+        let sprite_tf = |draw_sprite: &DrawSprite| {
+            character.transform_child(
+                draw_sprite.rotate_with_parent,
+                draw_sprite.local_pos,
+                draw_sprite.local_rotation,
+            )
+        };
+
+        // The sprites on the tracks with the same name move from the
+        // old pose to the new one. The rest of the old sprites fade out.
+        let mut morphed = Vec::new();
+        for (clip_id, track, mut draw_sprite) in character
             .animation
-            .active_clips::<DrawSprite>(character.anim_cursor())
+            .active_clips_named::<DrawSprite>(character.anim_cursor())
         {
+            if character.character_q.play.mirrored {
+                draw_sprite.mirror();
+            }
+            let mut new_sprite_tf = sprite_tf(&draw_sprite);
+            if let Some((_, _, old)) = fading.iter().find(|(_, name, _)| *name == track) {
+                let old_tf = sprite_tf(old);
+                new_sprite_tf.pos = old_tf.pos.lerp(new_sprite_tf.pos, weight);
+                new_sprite_tf.angle = old_tf.angle + (new_sprite_tf.angle - old_tf.angle) * weight;
+                morphed.push(track);
+            }
             let event = ClipActionObject {
                 parent,
                 animation: character.animation_id(),
                 clip_id,
                 kind: TypeId::of::<DrawSprite>(),
             };
-            let new_sprite_tf = character.transform_child(
-                draw_sprite.rotate_with_parent,
-                draw_sprite.local_pos,
-                draw_sprite.local_rotation,
+            put_sprite(
+                world,
+                cmds,
+                active_events,
+                event,
+                new_sprite_tf,
+                &draw_sprite,
+                sprite_color(&draw_sprite, 1.0),
             );
-            match active_events.get(&event).copied() {
-                Some(ent) => {
-                    let mut query = world
-                        .query_one::<(&mut Transform, &mut Sprite)>(ent)
-                        .expect("incomplete attach box components");
-                    let (col_tf, _) = query.get().unwrap();
-                    *col_tf = new_sprite_tf;
-                }
-                None => {
-                    let mut builder = EntityBuilder::new();
-                    builder.add(new_sprite_tf);
-                    builder.add(Sprite {
-                        layer: draw_sprite.layer,
-                        texture: draw_sprite.texture_id,
-                        rect: Rect {
-                            x: draw_sprite.rect_pos.x as f32,
-                            y: draw_sprite.rect_pos.y as f32,
-                            w: draw_sprite.rect_size.x as f32,
-                            h: draw_sprite.rect_size.y as f32,
-                        },
-                        color: WHITE,
-                        sort_offset: draw_sprite.sort_offset,
-                        local_offset: Vec2::ZERO,
-                    });
-                    builder.add(event);
-                    cmds.spawn(builder.build());
-                }
+        }
+
+        for (animation, anim, pos) in character.layers() {
+            for (clip_id, draw_sprite) in anim.active_clips::<DrawSprite>(pos) {
+                let event = ClipActionObject {
+                    parent,
+                    animation,
+                    clip_id,
+                    kind: TypeId::of::<DrawSprite>(),
+                };
+                let new_sprite_tf = sprite_tf(&draw_sprite);
+                put_sprite(
+                    world,
+                    cmds,
+                    active_events,
+                    event,
+                    new_sprite_tf,
+                    &draw_sprite,
+                    sprite_color(&draw_sprite, 1.0),
+                );
+            }
+        }
+
+        let Some((fade, _)) = fade else {
+            return;
+        };
+        for (clip_id, track, draw_sprite) in &fading {
+            if morphed.contains(track) {
+                continue;
             }
+            let event = ClipActionObject {
+                parent,
+                animation: fade.from,
+                clip_id: *clip_id,
+                kind: TypeId::of::<DrawSprite>(),
+            };
+            put_sprite(
+                world,
+                cmds,
+                active_events,
+                event,
+                sprite_tf(draw_sprite),
+                draw_sprite,
+                sprite_color(draw_sprite, 1.0 - weight),
+            );
         }
     });
 }
+
+fn put_sprite(
+    world: &World,
+    cmds: &mut CommandBuffer,
+    active_events: &HashMap<ClipActionObject, Entity>,
+    event: ClipActionObject,
+    new_sprite_tf: Transform,
+    draw_sprite: &DrawSprite,
+    color: Color,
+) {
+    match active_events.get(&event).copied() {
+        Some(ent) => {
+            let mut query = world
+                .query_one::<(&mut Transform, &mut Sprite)>(ent)
+                .expect("incomplete sprite components");
+            let (sprite_tf, sprite) = query.get().unwrap();
+            *sprite_tf = new_sprite_tf;
+            sprite.color = color;
+            sprite.scale = draw_sprite.scale;
+        }
+        None => {
+            let mut builder = EntityBuilder::new();
+            builder.add(new_sprite_tf);
+            builder.add(Sprite {
+                layer: draw_sprite.layer,
+                texture: draw_sprite.texture_id,
+                rect: Rect {
+                    x: draw_sprite.rect_pos.x as f32,
+                    y: draw_sprite.rect_pos.y as f32,
+                    w: draw_sprite.rect_size.x as f32,
+                    h: draw_sprite.rect_size.y as f32,
+                },
+                color,
+                sort_offset: draw_sprite.sort_offset,
+                local_offset: Vec2::ZERO,
+                scale: draw_sprite.scale,
+                flip_x: draw_sprite.flip_x,
+                flip_y: draw_sprite.flip_y,
+            });
+            builder.add(event);
+            cmds.spawn(builder.build());
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::any::TypeId;
+
+    use hashbrown::HashMap;
+    use hecs::{CommandBuffer, Entity, EntityBuilder, World};
+    use lib_asset::Symbol;
+    use lib_col::Shape;
+    use macroquad::math::{Vec2, vec2};
+
+    use super::{
+        AnimEvent, Animation, AnimationEvent, CancelWindow, Clip, ClipAction, Clips, Hurtbox, Move,
+        Track, collect_clip_action_objects, update, update_anim_events, update_defense,
+        update_hurtboxes,
+    };
+    use crate::{
+        AnimationLayers, AnimationPlay, CollisionSolver, Crossfade, Faction, FactionManifest,
+        FactionTable, Health, Resources, Transform, build_attack, build_character, build_player,
+        col_group, for_each_character, health,
+    };
+    use lib_asset::animation_manifest::AnimationId;
+
+    /// Put the clips onto their own tracks of `anim`
+    pub(crate) fn add_clips<T: ClipAction>(anim: &mut Animation, clips: Vec<(Clip, T)>) {
+        let tracks = clips
+            .iter()
+            .map(|(clip, _)| Track {
+                name: format!("track {}", clip.track_id),
+            })
+            .collect();
+        anim.action_tracks
+            .insert(TypeId::of::<T>(), Box::new(Clips { clips, tracks }));
+    }
+
+    pub(crate) fn clip(track_id: u32, start: u32, len: u32) -> Clip {
+        Clip {
+            track_id,
+            start,
+            len,
+        }
+    }
+
+    /// The resources with `anim` as [AnimationId::BnuuyWalkD]
+    /// and a faction for the characters. The other faction,
+    /// `other`, is neutral to it.
+    pub(crate) fn character_resources(anim: Animation) -> (Resources, Faction) {
+        let mut resources = Resources::new();
+        resources.animations.insert(AnimationId::BnuuyWalkD, anim);
+        let manifest = FactionManifest {
+            factions: vec!["test".to_string(), "other".to_string()],
+            relations: Vec::new(),
+        };
+        resources.factions = FactionTable::from_manifest(&manifest).unwrap();
+        let faction = resources.factions.resolve("test").unwrap();
+        (resources, faction)
+    }
+
+    /// A character playing [AnimationId::BnuuyWalkD]
+    pub(crate) fn spawn_character(world: &mut World, faction: Faction) -> Entity {
+        let mut builder = EntityBuilder::new();
+        build_character(
+            &mut builder,
+            Vec2::ZERO,
+            col_group::NONE,
+            Shape::Circle { radius: 4.0 },
+            10,
+            faction,
+        );
+        world.spawn(builder.build())
+    }
+
+    fn animation(is_looping: bool) -> Animation {
+        let mut anim = Animation {
+            is_looping,
+            ..Default::default()
+        };
+        let clip = Clip {
+            track_id: 0,
+            start: 0,
+            len: 10,
+        };
+        anim.action_tracks.insert(
+            TypeId::of::<Move>(),
+            Box::new(Clips {
+                clips: vec![(clip, Move)],
+                tracks: vec![Track {
+                    name: "move".to_string(),
+                }],
+            }),
+        );
+        anim
+    }
+
+    fn event_names(world: &World) -> Vec<Symbol> {
+        world
+            .query::<&AnimationEvent>()
+            .iter()
+            .map(|(_, event)| event.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_anim_events() {
+        let mut anim = Animation::default();
+        let event = |name| AnimEvent {
+            name: Symbol::new(name),
+        };
+        add_clips(
+            &mut anim,
+            vec![
+                (clip(0, 0, 10), event("first")),
+                (clip(1, 20, 10), event("second")),
+            ],
+        );
+        let (resources, faction) = character_resources(anim);
+        let mut world = World::new();
+        let ent = spawn_character(&mut world, faction);
+        let mut cmds = CommandBuffer::new();
+        let mut objects = HashMap::new();
+        let mut tick = |world: &mut World| {
+            collect_clip_action_objects(world, &mut objects);
+            update_anim_events(world, &resources, &mut cmds, &objects);
+            cmds.run_on(world);
+        };
+
+        tick(&mut world);
+        assert_eq!(event_names(&world), vec![Symbol::new("first")]);
+        // The event is sent once per clip
+        tick(&mut world);
+        assert_eq!(event_names(&world).len(), 1);
+
+        let anim = &resources.animations[&AnimationId::BnuuyWalkD];
+        world.get::<&mut AnimationPlay>(ent).unwrap().seek(25, anim);
+        tick(&mut world);
+        let mut names = event_names(&world);
+        names.sort_by_key(|name| name.as_str());
+        assert_eq!(names, vec![Symbol::new("first"), Symbol::new("second")]);
+        assert!(
+            world
+                .query::<&AnimationEvent>()
+                .iter()
+                .all(|(_, event)| event.entity == ent)
+        );
+    }
+
+    #[test]
+    fn test_replaced_body_hurtbox() {
+        let mut anim = Animation::default();
+        let hurtbox = Hurtbox {
+            local_pos: vec2(20.0, 0.0),
+            shape: Shape::Circle { radius: 4.0 },
+            replace_body: true,
+            ..Default::default()
+        };
+        add_clips(&mut anim, vec![(clip(0, 0, 10), hurtbox)]);
+        let (resources, faction) = character_resources(anim);
+        let other = resources.factions.resolve("other").unwrap();
+        let mut world = World::new();
+        let mut builder = EntityBuilder::new();
+        let shape = Shape::Circle { radius: 4.0 };
+        build_player(&mut builder, Vec2::ZERO, shape, 10, faction);
+        let player = world.spawn(builder.build());
+
+        let mut cmds = CommandBuffer::new();
+        update_defense(&mut world, &resources);
+        update_hurtboxes(&mut world, &resources, &mut cmds, &HashMap::new());
+        cmds.run_on(&mut world);
+
+        // A bullet, that only looks for the player
+        let mut builder = EntityBuilder::new();
+        let tf = Transform::from_xy(20.0, 0.0);
+        build_attack(&mut builder, tf, other, shape, 0.0, col_group::PLAYER);
+        world.spawn(builder.build());
+
+        let mut solver = CollisionSolver::new();
+        solver.import_colliders(&mut world);
+        solver.compute_collisions(&mut world);
+        health::collect_damage(&mut world, &resources, &solver);
+        assert_eq!(world.get::<&Health>(player).unwrap().damage, 1);
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut walk = Animation::default();
+        add_clips::<CancelWindow>(&mut walk, Vec::new());
+        let mut hit = animation(false);
+        let window = CancelWindow {
+            into: Symbol::new("attack"),
+        };
+        add_clips(&mut hit, vec![(clip(0, 0, 10), window)]);
+        let (mut resources, faction) = character_resources(walk);
+        resources.animations.insert(AnimationId::BnuuyHit, hit);
+        let mut world = World::new();
+        let ent = spawn_character(&mut world, faction);
+        let mut layers = AnimationLayers::default();
+        layers.play(AnimationId::BnuuyHit);
+        world.insert_one(ent, layers).unwrap();
+
+        let can_cancel = |world: &World| {
+            let mut can_cancel = false;
+            for_each_character::<()>(world, &resources, |_, character| {
+                can_cancel = character.can_cancel_into(Symbol::new("attack"));
+            });
+            can_cancel
+        };
+        // The layer's clips count as the character's own
+        assert!(can_cancel(&world));
+
+        // The layer is dropped once it is done
+        update(0.1, &mut world, &resources);
+        assert!(
+            !world
+                .get::<&AnimationLayers>(ent)
+                .unwrap()
+                .is_playing(AnimationId::BnuuyHit)
+        );
+        assert!(!can_cancel(&world));
+    }
+
+    #[test]
+    fn test_blended_clips() {
+        let hurtbox = |x| Hurtbox {
+            local_pos: vec2(x, 0.0),
+            local_rotation: x,
+            ..Default::default()
+        };
+        let mut walk = Animation::default();
+        add_clips(&mut walk, vec![(clip(0, 0, 10), hurtbox(10.0))]);
+        let mut idle = Animation::default();
+        add_clips(&mut idle, vec![(clip(0, 0, 10), hurtbox(0.0))]);
+        let (mut resources, faction) = character_resources(walk);
+        resources.animations.insert(AnimationId::BnuuyIdleD, idle);
+        let mut world = World::new();
+        let ent = spawn_character(&mut world, faction);
+        world.get::<&mut AnimationPlay>(ent).unwrap().fade = Some(Crossfade {
+            from: AnimationId::BnuuyIdleD,
+            mirrored: false,
+            position: 0,
+            elapsed: 0.1,
+            duration: 0.2,
+        });
+
+        let mut blended = Vec::new();
+        for_each_character::<()>(&world, &resources, |_, character| {
+            blended.extend(character.blended_clips::<Hurtbox>());
+        });
+        let [(animation, _, hurtbox)] = blended[..] else {
+            panic!("expected one hurtbox, got {blended:?}");
+        };
+        assert_eq!(animation, AnimationId::BnuuyWalkD);
+        // Halfway through the fade, the hurtbox is halfway between the poses
+        assert_eq!(hurtbox.local_pos, vec2(5.0, 0.0));
+        assert_eq!(hurtbox.local_rotation, 5.0);
+    }
+
+    #[test]
+    fn test_playback_speed() {
+        let anim = animation(false);
+        let mut play = AnimationPlay::new(AnimationId::BnuuyIdleD);
+        play.speed = 2.0;
+        play.advance(0.00325, &anim);
+        assert_eq!(play.position(&anim), 6);
+        play.advance(0.01, &anim);
+        assert!(play.is_done(&anim));
+
+        let mut held = AnimationPlay::new(AnimationId::BnuuyIdleD);
+        held.speed = 0.0;
+        held.advance(1.0, &anim);
+        assert_eq!(held.position(&anim), 0);
+        assert_eq!(held.total_dt, 0.0);
+    }
+
+    #[test]
+    fn test_reverse_playback() {
+        let anim = animation(false);
+        let mut play = AnimationPlay::new(AnimationId::BnuuyIdleD);
+        play.speed = -1.0;
+        // Restarted animations play from the end
+        play.advance(0.0015, &anim);
+        assert_eq!(play.position(&anim), 8);
+        assert!(!play.is_done(&anim));
+
+        // Turning around keeps the position
+        play.speed = 1.0;
+        play.advance(0.0, &anim);
+        assert_eq!(play.position(&anim), 8);
+
+        play.speed = -1.0;
+        play.advance(0.1, &anim);
+        assert!(play.is_reversed());
+        assert!(play.is_done(&anim));
+
+        let looping = animation(true);
+        let mut play = AnimationPlay::new(AnimationId::BnuuyIdleD);
+        play.speed = -1.0;
+        play.advance(0.0125, &looping);
+        assert_eq!(play.position(&looping), 6);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let anim = animation(false);
+        // Picking the first animation doesn't fade
+        let mut play = AnimationPlay::new(AnimationId::BnuuyIdleD);
+        play.switch_to(AnimationId::BnuuyWalkD, 0.2, 0);
+        assert!(play.fade.is_none());
+
+        play.switch_to(AnimationId::BnuuyIdleD, 0.2, 0);
+        play.advance(0.0035, &anim);
+        play.restart(&anim);
+        play.switch_to(AnimationId::BnuuyWalkD, 0.2, 0);
+        let fade = play.fade.unwrap();
+        assert_eq!(fade.from, AnimationId::BnuuyIdleD);
+        assert_eq!(fade.position, 3);
+        assert_eq!(play.position(&anim), 0);
+
+        play.advance(0.1, &anim);
+        assert_eq!(play.fade.unwrap().weight(), 0.5);
+        play.advance(0.1, &anim);
+        assert!(play.fade.is_none());
+    }
+}
@@ -1,22 +1,34 @@
 use crate::{
-    BodyTag, CollisionSolver, GrazeGain, GrazeValue, Health, Team, Transform, col_group, col_query,
+    BlockEvent, BodyTag, CharacterLook, ClipActionObject, CollisionSolver, Defense, Deflected,
+    Faction, GrazeGain, GrazeValue, Health, HurtboxOf, LocalTimeScale, ParryEvent, ParryKind,
+    ParryWindow, ProjectileData, Resources, ShieldArc, SpawnedBy, Transform, col_group, col_query,
+    emit_event, local_dt,
 };
-use hecs::{EntityBuilder, Query, World};
+use hecs::{CommandBuffer, Entity, EntityBuilder, Query, World};
 use lib_col::{Group, Shape};
 
 #[derive(Query)]
 pub struct AttackQuery<'a> {
     pub tf: &'a mut Transform,
-    pub team: &'a Team,
+    pub faction: &'a Faction,
     pub query: &'a col_query::Damage,
     pub graze_hitbox: &'a BodyTag,
     pub graze_value: &'a GrazeValue,
 }
 
-pub(crate) fn update_grazing(dt: f32, world: &mut World, col_solver: &CollisionSolver) {
-    for (_, (graze_q, graze_gain, health)) in
-        &mut world.query::<(&mut col_query::Grazing, &mut GrazeGain, &Health)>()
-    {
+pub(crate) fn update_grazing(
+    dt: f32,
+    world: &mut World,
+    resources: &Resources,
+    col_solver: &CollisionSolver,
+) {
+    for (_, (graze_q, graze_gain, health, faction, time_scale)) in &mut world.query::<(
+        &mut col_query::Grazing,
+        &mut GrazeGain,
+        &Health,
+        &Faction,
+        Option<&LocalTimeScale>,
+    )>() {
         // invulnerable characters can't graze
         if health.is_invulnerable {
             continue;
@@ -25,23 +37,214 @@ pub(crate) fn update_grazing(dt: f32, world: &mut World, col_solver: &CollisionS
             let Ok(graze_val) = world.get::<&GrazeValue>(*collision) else {
                 continue;
             };
-            graze_gain.value += graze_val.0 * dt;
+            // only the attacks that can hurt count
+            let Ok(attack_faction) = world.get::<&Faction>(*collision) else {
+                continue;
+            };
+            if !resources
+                .factions
+                .relation(*attack_faction, *faction)
+                .can_damage()
+            {
+                continue;
+            }
+            graze_gain.value += graze_val.0 * local_dt(dt, time_scale);
             graze_gain.value = graze_gain.value.min(graze_gain.max_value);
         }
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+enum DefenseMove {
+    Parry(ParryWindow),
+    Block(ShieldArc),
+}
+
+/// Negate the attacks that hit characters with an active [crate::ParryWindow]
+/// or attacks that hit their [crate::ShieldArc].
+/// Must run before the damage is collected.
+pub(crate) fn resolve_defense(
+    world: &mut World,
+    resources: &Resources,
+    col_solver: &CollisionSolver,
+    cmds: &mut CommandBuffer,
+) {
+    let defended = collect_defended_attacks(world, resources, col_solver);
+    for (attack, defender, defender_faction, defense_move) in defended {
+        let attacker = attack_owner(world, attack);
+        let is_projectile = world.get::<&ProjectileData>(attack).is_ok();
+        world
+            .insert_one(attack, Deflected { by: defender })
+            .unwrap();
+
+        match defense_move {
+            DefenseMove::Parry(parry) => {
+                if let Ok(mut graze_gain) = world.get::<&mut GrazeGain>(defender) {
+                    graze_gain.value += parry.stamina_bonus;
+                    graze_gain.value = graze_gain.value.min(graze_gain.max_value);
+                }
+                match (parry.kind, is_projectile) {
+                    (ParryKind::Parry, true) if parry.reflect => {
+                        reflect_projectile(world, attack, attacker, defender_faction);
+                    }
+                    (ParryKind::Parry, true) => cmds.despawn(attack),
+                    _ => (),
+                }
+                emit_event(
+                    cmds,
+                    ParryEvent {
+                        kind: parry.kind,
+                        defender,
+                        attack,
+                        attacker,
+                    },
+                );
+            }
+            DefenseMove::Block(shield) => {
+                if let Ok(mut graze_gain) = world.get::<&mut GrazeGain>(defender) {
+                    graze_gain.value -= shield.stamina_cost;
+                    graze_gain.value = graze_gain.value.max(0.0);
+                }
+                if let Ok(mut health) = world.get::<&mut Health>(defender) {
+                    health.damage += shield.chip_damage;
+                }
+                if is_projectile {
+                    cmds.despawn(attack);
+                }
+                emit_event(
+                    cmds,
+                    BlockEvent {
+                        defender,
+                        attack,
+                        attacker,
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn collect_defended_attacks(
+    world: &World,
+    resources: &Resources,
+    col_solver: &CollisionSolver,
+) -> Vec<(Entity, Entity, Faction, DefenseMove)> {
+    let mut defended = Vec::new();
+    let mut defender_query = world.query::<(
+        &Defense,
+        &Faction,
+        &Transform,
+        &CharacterLook,
+        Option<&GrazeGain>,
+    )>();
+    let defender_query = defender_query.view();
+    for (attack, (damage_q, attack_faction, attack_tf, deflected, clip_object)) in &mut world
+        .query::<(
+            &col_query::Damage,
+            &Faction,
+            &Transform,
+            Option<&Deflected>,
+            Option<&ClipActionObject>,
+        )>()
+    {
+        // Melee attacks come from their owner, not the hitbox
+        let attacker_pos = clip_object
+            .and_then(|x| world.get::<&Transform>(x.parent).ok())
+            .map_or(attack_tf.pos, |tf| tf.pos);
+        for entity in col_solver.collisions_for(damage_q) {
+            let Some(target) = hit_target(world, *entity) else {
+                continue;
+            };
+            if deflected.is_some_and(|x| x.by == target) {
+                continue;
+            }
+            let Some((defense, target_faction, target_tf, look, graze_gain)) =
+                defender_query.get(target)
+            else {
+                continue;
+            };
+            let relation = resources
+                .factions
+                .relation(*attack_faction, *target_faction);
+            if !relation.can_damage() {
+                continue;
+            }
+
+            let defense_move = if let Some(parry) = defense.parry {
+                DefenseMove::Parry(parry)
+            } else if let Some(shield) = defense.shield
+                && shield.covers(*look, attacker_pos - target_tf.pos)
+                && graze_gain.is_none_or(|x| x.value >= shield.stamina_cost)
+            {
+                DefenseMove::Block(shield)
+            } else {
+                continue;
+            };
+            defended.push((attack, target, *target_faction, defense_move));
+            break;
+        }
+    }
+    defended
+}
+
+/// The character that gets hit, when an attack touches `entity`.
+/// The hurtboxes pass the hits to their owners, while the bodies
+/// replaced by hurtboxes can't be hit at all.
+pub(crate) fn hit_target(world: &World, entity: Entity) -> Option<Entity> {
+    if let Ok(hurtbox) = world.get::<&HurtboxOf>(entity) {
+        return Some(hurtbox.owner);
+    }
+    let body_replaced = world
+        .get::<&Defense>(entity)
+        .is_ok_and(|defense| defense.body_replaced);
+    (!body_replaced).then_some(entity)
+}
+
+/// The character that owns an attack: the parent of a melee hitbox
+/// or the spawner of a projectile.
+fn attack_owner(world: &World, attack: Entity) -> Option<Entity> {
+    if let Ok(clip_object) = world.get::<&ClipActionObject>(attack) {
+        return Some(clip_object.parent);
+    }
+    world.get::<&SpawnedBy>(attack).ok().map(|x| x.owner)
+}
+
+/// Turn the projectile against its `owner`. Projectiles whose owner
+/// is gone just fly back the way they came.
+fn reflect_projectile(world: &mut World, attack: Entity, owner: Option<Entity>, faction: Faction) {
+    let owner_pos = owner
+        .and_then(|owner| world.get::<&Transform>(owner).ok())
+        .map(|tf| tf.pos);
+    let mut query = world
+        .query_one::<(
+            &Transform,
+            &mut ProjectileData,
+            &mut Faction,
+            &mut col_query::Damage,
+        )>(attack)
+        .expect("incomplete projectile components");
+    let Some((tf, data, attack_faction, damage_q)) = query.get() else {
+        return;
+    };
+    data.dir = owner_pos
+        .and_then(|pos| (pos - tf.pos).try_normalize())
+        .unwrap_or(-data.dir);
+    *attack_faction = faction;
+    // The projectile can now hit anyone the new faction can hurt
+    damage_q.filter = col_group::NONE;
+}
+
 pub fn build_attack(
     builder: &mut EntityBuilder,
     tf: Transform,
-    team: Team,
+    faction: Faction,
     shape: Shape,
     graze_value: f32,
     filter: Group,
 ) {
     builder.add_bundle((
         tf,
-        team,
+        faction,
         col_query::Damage::new(shape, col_group::CHARACTERS, filter),
         BodyTag {
             groups: col_group::ATTACKS,
@@ -50,3 +253,227 @@ pub fn build_attack(
         GrazeValue(graze_value),
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use hecs::{CommandBuffer, Entity, EntityBuilder, World};
+    use lib_col::Shape;
+    use macroquad::prelude::*;
+
+    use super::{build_attack, hit_target, resolve_defense};
+    use crate::{
+        Attitude, BodyTag, CharacterLook, CollisionSolver, Defense, Deflected, Faction,
+        FactionManifest, FactionTable, GrazeGain, HurtboxOf, ParryEvent, ParryKind, ParryWindow,
+        ProjectileData, Relation, RelationDef, Resources, SpawnedBy, Transform, col_group,
+    };
+
+    const SHAPE: Shape = Shape::Rect {
+        width: 8.0,
+        height: 8.0,
+    };
+
+    fn test_resources() -> (Resources, Faction, Faction) {
+        let hostile = |from: &str, to: &str| RelationDef {
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: Relation {
+                attitude: Attitude::Hostile,
+                friendly_fire: false,
+            },
+        };
+        let manifest = FactionManifest {
+            factions: vec!["player".to_string(), "enemy".to_string()],
+            relations: vec![hostile("player", "enemy"), hostile("enemy", "player")],
+        };
+        let mut resources = Resources::new();
+        resources.factions = FactionTable::from_manifest(&manifest).unwrap();
+        let player = resources.factions.resolve("player").unwrap();
+        let enemy = resources.factions.resolve("enemy").unwrap();
+        (resources, player, enemy)
+    }
+
+    fn spawn_defender(world: &mut World, faction: Faction, parry: ParryWindow) -> Entity {
+        world.spawn((
+            Transform::from_xy(0.0, 0.0),
+            faction,
+            CharacterLook(0.0),
+            Defense {
+                parry: Some(parry),
+                ..Defense::default()
+            },
+            GrazeGain {
+                value: 0.0,
+                max_value: 100.0,
+            },
+            BodyTag {
+                groups: col_group::CHARACTERS,
+                shape: SHAPE,
+            },
+        ))
+    }
+
+    fn spawn_bullet(world: &mut World, faction: Faction, owner: Entity) -> Entity {
+        let mut builder = EntityBuilder::new();
+        build_attack(
+            &mut builder,
+            Transform::from_xy(0.0, 0.0),
+            faction,
+            SHAPE,
+            0.0,
+            col_group::NONE,
+        );
+        builder.add(ProjectileData::new(-Vec2::X, 100.0, Default::default()));
+        builder.add(SpawnedBy { owner });
+        world.spawn(builder.build())
+    }
+
+    fn run_defense(world: &mut World, resources: &Resources) {
+        let mut solver = CollisionSolver::new();
+        let mut cmds = CommandBuffer::new();
+        solver.import_colliders(world);
+        solver.compute_collisions(world);
+        resolve_defense(world, resources, &solver, &mut cmds);
+        cmds.run_on(world);
+    }
+
+    fn parry_events(world: &World) -> Vec<ParryEvent> {
+        world
+            .query::<&ParryEvent>()
+            .iter()
+            .map(|(_, event)| *event)
+            .collect()
+    }
+
+    #[test]
+    fn test_parry_reflects_at_owner() {
+        let (resources, player, enemy) = test_resources();
+        let mut world = World::new();
+        let parry = ParryWindow {
+            kind: ParryKind::Parry,
+            reflect: true,
+            stamina_bonus: 10.0,
+        };
+        let defender = spawn_defender(&mut world, player, parry);
+        let owner = world.spawn((Transform::from_xy(30.0, 40.0),));
+        let bullet = spawn_bullet(&mut world, enemy, owner);
+
+        run_defense(&mut world, &resources);
+
+        let data = world.get::<&ProjectileData>(bullet).unwrap();
+        assert!(data.dir.abs_diff_eq(vec2(0.6, 0.8), 1e-5));
+        assert_eq!(*world.get::<&Faction>(bullet).unwrap(), player);
+        assert_eq!(world.get::<&Deflected>(bullet).unwrap().by, defender);
+        assert_eq!(world.get::<&GrazeGain>(defender).unwrap().value, 10.0);
+
+        let events = parry_events(&world);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ParryKind::Parry);
+        assert_eq!(events[0].defender, defender);
+        assert_eq!(events[0].attacker, Some(owner));
+    }
+
+    #[test]
+    fn test_parry_reflects_without_owner() {
+        let (resources, player, enemy) = test_resources();
+        let mut world = World::new();
+        let parry = ParryWindow {
+            kind: ParryKind::Parry,
+            reflect: true,
+            stamina_bonus: 0.0,
+        };
+        spawn_defender(&mut world, player, parry);
+        let owner = world.spawn((Transform::from_xy(30.0, 40.0),));
+        let bullet = spawn_bullet(&mut world, enemy, owner);
+        world.despawn(owner).unwrap();
+
+        run_defense(&mut world, &resources);
+
+        let data = world.get::<&ProjectileData>(bullet).unwrap();
+        assert_eq!(data.dir, Vec2::X);
+    }
+
+    #[test]
+    fn test_parry_without_reflect() {
+        let (resources, player, enemy) = test_resources();
+        let mut world = World::new();
+        let parry = ParryWindow {
+            kind: ParryKind::Parry,
+            reflect: false,
+            stamina_bonus: 0.0,
+        };
+        spawn_defender(&mut world, player, parry);
+        let owner = world.spawn((Transform::from_xy(30.0, 40.0),));
+        let bullet = spawn_bullet(&mut world, enemy, owner);
+
+        run_defense(&mut world, &resources);
+
+        assert!(!world.contains(bullet));
+        assert_eq!(parry_events(&world).len(), 1);
+    }
+
+    #[test]
+    fn test_perfect_dodge() {
+        let (resources, player, enemy) = test_resources();
+        let mut world = World::new();
+        let parry = ParryWindow {
+            kind: ParryKind::PerfectDodge,
+            reflect: true,
+            stamina_bonus: 25.0,
+        };
+        let defender = spawn_defender(&mut world, player, parry);
+        let owner = world.spawn((Transform::from_xy(30.0, 40.0),));
+        let bullet = spawn_bullet(&mut world, enemy, owner);
+
+        run_defense(&mut world, &resources);
+
+        // The bullet flies on and can't hurt the dodger anymore
+        assert_eq!(world.get::<&ProjectileData>(bullet).unwrap().dir, -Vec2::X);
+        assert_eq!(*world.get::<&Faction>(bullet).unwrap(), enemy);
+        assert_eq!(world.get::<&Deflected>(bullet).unwrap().by, defender);
+        assert_eq!(world.get::<&GrazeGain>(defender).unwrap().value, 25.0);
+
+        let events = parry_events(&world);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ParryKind::PerfectDodge);
+
+        // Deflected attacks are not defended again
+        run_defense(&mut world, &resources);
+        assert_eq!(world.get::<&GrazeGain>(defender).unwrap().value, 25.0);
+    }
+
+    #[test]
+    fn test_parry_ignores_allies() {
+        let (resources, player, _) = test_resources();
+        let mut world = World::new();
+        let parry = ParryWindow {
+            kind: ParryKind::Parry,
+            reflect: true,
+            stamina_bonus: 10.0,
+        };
+        let defender = spawn_defender(&mut world, player, parry);
+        let owner = world.spawn((Transform::from_xy(30.0, 40.0),));
+        let bullet = spawn_bullet(&mut world, player, owner);
+
+        run_defense(&mut world, &resources);
+
+        assert!(world.get::<&Deflected>(bullet).is_err());
+        assert_eq!(world.get::<&GrazeGain>(defender).unwrap().value, 0.0);
+        assert!(parry_events(&world).is_empty());
+    }
+
+    #[test]
+    fn test_hit_target() {
+        let mut world = World::new();
+        let owner = world.spawn((Defense::default(),));
+        let hurtbox = world.spawn((HurtboxOf { owner },));
+        let prop = world.spawn(());
+
+        assert_eq!(hit_target(&world, owner), Some(owner));
+        assert_eq!(hit_target(&world, hurtbox), Some(owner));
+        assert_eq!(hit_target(&world, prop), Some(prop));
+
+        world.get::<&mut Defense>(owner).unwrap().body_replaced = true;
+        assert_eq!(hit_target(&world, owner), None);
+        assert_eq!(hit_target(&world, hurtbox), Some(owner));
+    }
+}
@@ -0,0 +1,414 @@
+use std::{cell::RefCell, path::Path};
+
+use hashbrown::HashMap;
+use lib_asset::{AssetContainer, AssetKey, gamecfg::sections};
+use log::warn;
+use macroquad::audio::{self, PlaySoundParams, Sound};
+
+pub use lib_asset::animation_manifest::AudioBus;
+
+/// Where the sounds actually go. The game uses [MacroquadAudio],
+/// the tests use [NullAudio].
+pub trait AudioBackend {
+    /// Take the loaded sound data
+    fn insert(&mut self, key: AssetKey, sound: Sound);
+    fn play(&mut self, key: AssetKey, playback: Playback);
+    /// Stop all the copies of the sound. Only the music gets stopped,
+    /// and it is loaded apart from the sound effects, see [SoundClip::music].
+    fn stop(&mut self, key: AssetKey);
+    fn set_volume(&mut self, key: AssetKey, volume: f32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    pub volume: f32,
+    /// `1.0` is the original pitch
+    pub pitch: f32,
+    pub looped: bool,
+}
+
+/// Plays the sounds through macroquad.
+/// NOTE: macroquad can't change the pitch, so it is ignored.
+#[derive(Default)]
+pub struct MacroquadAudio {
+    sounds: HashMap<AssetKey, Sound>,
+}
+
+impl MacroquadAudio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AudioBackend for MacroquadAudio {
+    fn insert(&mut self, key: AssetKey, sound: Sound) {
+        self.sounds.insert(key, sound);
+    }
+
+    fn play(&mut self, key: AssetKey, playback: Playback) {
+        let Some(sound) = self.sounds.get(&key) else {
+            return;
+        };
+        audio::play_sound(
+            sound,
+            PlaySoundParams {
+                looped: playback.looped,
+                volume: playback.volume,
+            },
+        );
+    }
+
+    fn stop(&mut self, key: AssetKey) {
+        if let Some(sound) = self.sounds.get(&key) {
+            audio::stop_sound(sound);
+        }
+    }
+
+    fn set_volume(&mut self, key: AssetKey, volume: f32) {
+        if let Some(sound) = self.sounds.get(&key) {
+            audio::set_sound_volume(sound, volume);
+        }
+    }
+}
+
+/// Swallows all the sounds
+#[derive(Debug, Default)]
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn insert(&mut self, _key: AssetKey, _sound: Sound) {}
+    fn play(&mut self, _key: AssetKey, _playback: Playback) {}
+    fn stop(&mut self, _key: AssetKey) {}
+    fn set_volume(&mut self, _key: AssetKey, _volume: f32) {}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SoundClip {
+    /// In seconds
+    pub length: f32,
+    /// Music can only be played with [AudioManager::play_music] and the
+    /// sound effects only with [AudioManager::play]. Macroquad can only
+    /// stop all the copies of a sound at once, so this way stopping the
+    /// music never cuts off the sound effects.
+    pub music: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SoundRequest {
+    sound: AssetKey,
+    bus: AudioBus,
+    volume: f32,
+    pitch: f32,
+    looped: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Voice {
+    sound: AssetKey,
+    bus: AudioBus,
+    volume: f32,
+    remaining: f32,
+    looped: bool,
+}
+
+/// Mixes the sounds of the game. The sounds requested during a frame
+/// are sent to the backend by [AudioManager::update]. Requesting only
+/// takes `&self`, so sounds can be played from any phase.
+pub struct AudioManager {
+    pub sounds: AssetContainer<SoundClip>,
+    /// How many copies of the same sound may play at once
+    pub max_instances: u32,
+    bus_volumes: [f32; 3],
+    requests: RefCell<Vec<SoundRequest>>,
+    voices: Vec<Voice>,
+    backend: Box<dyn AudioBackend>,
+}
+
+impl AudioManager {
+    pub fn new(backend: Box<dyn AudioBackend>) -> Self {
+        Self {
+            sounds: AssetContainer::new(),
+            max_instances: 1,
+            bus_volumes: [1.0; 3],
+            requests: RefCell::new(Vec::new()),
+            voices: Vec::new(),
+            backend,
+        }
+    }
+
+    pub fn configure(&mut self, cfg: &sections::Audio) {
+        self.max_instances = cfg.max_instances;
+        self.set_bus_volume(AudioBus::Sfx, cfg.sfx_volume);
+        self.set_bus_volume(AudioBus::Music, cfg.music_volume);
+        self.set_bus_volume(AudioBus::Ui, cfg.ui_volume);
+    }
+
+    pub fn insert(&mut self, src_path: &Path, clip: SoundClip, sound: Sound) -> AssetKey {
+        let key = self.sounds.insert(src_path, clip);
+        self.backend.insert(key, sound);
+        key
+    }
+
+    pub fn bus_volume(&self, bus: AudioBus) -> f32 {
+        self.bus_volumes[bus as usize]
+    }
+
+    /// Also changes the volume of the music, that is already playing
+    pub fn set_bus_volume(&mut self, bus: AudioBus, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        self.bus_volumes[bus as usize] = volume;
+        for voice in self.voices.iter().filter(|voice| voice.bus == bus) {
+            self.backend.set_volume(voice.sound, voice.volume * volume);
+        }
+    }
+
+    /// Play a sound once. The pitch is randomly shifted by up to `pitch_variance`.
+    pub fn play(&self, sound: AssetKey, bus: AudioBus, volume: f32, pitch_variance: f32) {
+        let pitch = 1.0 + macroquad::rand::gen_range(-pitch_variance, pitch_variance);
+        self.requests.borrow_mut().push(SoundRequest {
+            sound,
+            bus,
+            volume,
+            pitch,
+            looped: false,
+        });
+    }
+
+    /// Loop a sound on the [AudioBus::Music] bus, replacing the current music
+    pub fn play_music(&self, sound: AssetKey, volume: f32) {
+        self.requests.borrow_mut().push(SoundRequest {
+            sound,
+            bus: AudioBus::Music,
+            volume,
+            pitch: 1.0,
+            looped: true,
+        });
+    }
+
+    /// How many copies of `sound` are playing
+    pub fn playing(&self, sound: AssetKey) -> usize {
+        self.voices
+            .iter()
+            .filter(|voice| voice.sound == sound)
+            .count()
+    }
+
+    /// Send the requested sounds to the backend. `dt` is the real time
+    /// since the last call. It is used to tell which sounds are over.
+    pub fn update(&mut self, dt: f32) {
+        for voice in &mut self.voices {
+            voice.remaining -= dt;
+        }
+        self.voices
+            .retain(|voice| voice.looped || voice.remaining > 0.0);
+
+        let requests = std::mem::take(self.requests.get_mut());
+        for request in requests {
+            let Some(clip) = self.sounds.get(request.sound).copied() else {
+                continue;
+            };
+            if clip.music != request.looped {
+                warn!(
+                    "{:?}: music and sound effects can't be played in place of each other",
+                    self.sounds.inverse_resolve(request.sound),
+                );
+                continue;
+            }
+            if request.looped {
+                self.stop_music();
+            }
+            if self.playing(request.sound) >= self.max_instances as usize {
+                continue;
+            }
+            let playback = Playback {
+                volume: request.volume * self.bus_volume(request.bus),
+                pitch: request.pitch,
+                looped: request.looped,
+            };
+            self.backend.play(request.sound, playback);
+            self.voices.push(Voice {
+                sound: request.sound,
+                bus: request.bus,
+                volume: request.volume,
+                remaining: clip.length,
+                looped: request.looped,
+            });
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        for voice in self.voices.iter().filter(|voice| voice.looped) {
+            self.backend.stop(voice.sound);
+        }
+        self.voices.retain(|voice| !voice.looped);
+    }
+}
+
+/// The length of a PCM WAV file in seconds
+pub fn wav_length(bytes: &[u8]) -> Option<f32> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut byte_rate = None;
+    let mut pos = 12;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let body = pos + 8;
+        match &header[0..4] {
+            b"fmt " => {
+                let rate = bytes.get(body + 8..body + 12)?;
+                byte_rate = Some(u32::from_le_bytes(rate.try_into().unwrap()));
+            }
+            b"data" => return Some(size as f32 / byte_rate.filter(|x| *x > 0)? as f32),
+            _ => (),
+        }
+        // The chunks are padded to the even size
+        pos = body + size as usize + (size as usize & 1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, path::Path, rc::Rc};
+
+    use lib_asset::AssetKey;
+    use macroquad::audio::Sound;
+
+    use super::{AudioBackend, AudioBus, AudioManager, Playback, SoundClip, wav_length};
+
+    /// Remembers what has been played
+    #[derive(Default, Clone)]
+    struct LogAudio(Rc<RefCell<Vec<(AssetKey, Playback)>>>);
+
+    impl AudioBackend for LogAudio {
+        fn insert(&mut self, _key: AssetKey, _sound: Sound) {}
+        fn play(&mut self, key: AssetKey, playback: Playback) {
+            self.0.borrow_mut().push((key, playback));
+        }
+        fn stop(&mut self, _key: AssetKey) {}
+        fn set_volume(&mut self, _key: AssetKey, _volume: f32) {}
+    }
+
+    fn manager() -> (AudioManager, LogAudio, AssetKey) {
+        let log = LogAudio::default();
+        let mut audio = AudioManager::new(Box::new(log.clone()));
+        let key = audio.sounds.insert(
+            Path::new("sound/test.wav"),
+            SoundClip {
+                length: 0.5,
+                music: false,
+            },
+        );
+        (audio, log, key)
+    }
+
+    #[test]
+    fn test_bus_volume() {
+        let (mut audio, log, key) = manager();
+        audio.set_bus_volume(AudioBus::Sfx, 0.5);
+        audio.play(key, AudioBus::Sfx, 0.8, 0.0);
+        audio.play(key, AudioBus::Ui, 0.8, 0.0);
+        audio.max_instances = 2;
+        audio.update(0.0);
+
+        let log = log.0.borrow();
+        assert_eq!(log[0].1.volume, 0.4);
+        assert_eq!(log[1].1.volume, 0.8);
+        assert_eq!(log[0].1.pitch, 1.0);
+    }
+
+    #[test]
+    fn test_pitch_variance() {
+        let (mut audio, log, key) = manager();
+        audio.max_instances = 16;
+        for _ in 0..16 {
+            audio.play(key, AudioBus::Sfx, 1.0, 0.1);
+        }
+        audio.update(0.0);
+
+        let log = log.0.borrow();
+        assert_eq!(log.len(), 16);
+        assert!(
+            log.iter()
+                .all(|(_, playback)| (playback.pitch - 1.0).abs() <= 0.1)
+        );
+    }
+
+    #[test]
+    fn test_instance_cap() {
+        let (mut audio, log, key) = manager();
+        audio.max_instances = 2;
+        for _ in 0..3 {
+            audio.play(key, AudioBus::Sfx, 1.0, 0.0);
+        }
+        audio.update(0.0);
+        assert_eq!(log.0.borrow().len(), 2);
+        assert_eq!(audio.playing(key), 2);
+
+        // The old copies are over, so there is room again
+        audio.update(0.5);
+        assert_eq!(audio.playing(key), 0);
+        audio.play(key, AudioBus::Sfx, 1.0, 0.0);
+        audio.update(0.0);
+        assert_eq!(log.0.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_music_replaces_music() {
+        let (mut audio, _, _) = manager();
+        let music = |audio: &mut AudioManager, path| {
+            audio.sounds.insert(
+                Path::new(path),
+                SoundClip {
+                    length: 0.1,
+                    music: true,
+                },
+            )
+        };
+        let first = music(&mut audio, "music/first.wav");
+        let second = music(&mut audio, "music/second.wav");
+        audio.play_music(first, 1.0);
+        audio.update(0.0);
+        audio.play_music(second, 1.0);
+        // Music keeps playing past its length
+        audio.update(1.0);
+        assert_eq!(audio.playing(first), 0);
+        assert_eq!(audio.playing(second), 1);
+    }
+
+    #[test]
+    fn test_music_apart_from_sfx() {
+        let (mut audio, log, key) = manager();
+        let music = audio.sounds.insert(
+            Path::new("music/test.wav"),
+            SoundClip {
+                length: 0.1,
+                music: true,
+            },
+        );
+        audio.play(key, AudioBus::Sfx, 1.0, 0.0);
+        audio.play_music(key, 1.0);
+        audio.play(music, AudioBus::Sfx, 1.0, 0.0);
+        audio.update(0.0);
+        assert_eq!(log.0.borrow().len(), 1);
+        assert_eq!(audio.playing(key), 1);
+        assert_eq!(audio.playing(music), 0);
+    }
+
+    #[test]
+    fn test_wav_length() {
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        wav.extend_from_slice(b"fmt \x10\0\0\0");
+        // PCM, mono, 8000 Hz, 16000 bytes per second, 2 byte blocks, 16 bits
+        wav.extend_from_slice(&[1, 0, 1, 0, 0x40, 0x1f, 0, 0, 0x80, 0x3e, 0, 0, 2, 0, 16, 0]);
+        wav.extend_from_slice(b"data\x40\x1f\0\0");
+        assert_eq!(wav_length(&wav), Some(0.5));
+        assert_eq!(wav_length(b"nope"), None);
+
+        let shipped = std::fs::read("../assets/sound/ball.wav").unwrap();
+        assert!(wav_length(&shipped).is_some_and(|x| x > 0.0));
+    }
+}
@@ -0,0 +1,565 @@
+use hecs::{Query, World};
+use lib_asset::{
+    AssetKey, BtCondition, BtGoal, BtNode, EnemyKind, animation_manifest::AnimationId,
+};
+use macroquad::prelude::*;
+
+use crate::{
+    Character, CharacterData, Emitter, NavGrid, PathFollower, Perception, Resources,
+    for_each_character, steering,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BtState {
+    Idle,
+    Walking,
+    /// Playing an animation from an `Attack` node
+    Action(AnimationId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+/// Per-node state. Nodes that finished or were not run on the
+/// previous tick of the tree start from scratch.
+#[derive(Debug, Clone, Copy, Default)]
+struct NodeMemory {
+    last_tick: u64,
+    cursor: usize,
+    elapsed: f32,
+    started: bool,
+}
+
+/// Makes a character run the behaviour tree of an [EnemyKind].
+#[derive(Debug, Clone)]
+pub struct BehaviourAgent {
+    pub kind: AssetKey,
+    idle_animation: AnimationId,
+    walk_animation: AnimationId,
+    state: BtState,
+    memory: Vec<NodeMemory>,
+    tick: u64,
+}
+
+impl BehaviourAgent {
+    pub fn new(key: AssetKey, kind: &EnemyKind) -> Self {
+        Self {
+            kind: key,
+            idle_animation: kind.idle_animation,
+            walk_animation: kind.walk_animation,
+            state: BtState::Idle,
+            memory: vec![NodeMemory::default(); kind.behaviour.size()],
+            tick: 0,
+        }
+    }
+
+    pub fn state(&self) -> BtState {
+        self.state
+    }
+}
+
+#[derive(Query)]
+pub struct BehaviourData<'a> {
+    pub agent: &'a mut BehaviourAgent,
+    pub path: &'a mut PathFollower,
+    pub perception: &'a mut Perception,
+    pub emitter: Option<&'a mut Emitter>,
+}
+
+impl CharacterData for BehaviourData<'_> {
+    type StateId = BtState;
+
+    fn get_state(&self) -> Self::StateId {
+        self.agent.state
+    }
+    fn set_state(&mut self, new_state: Self::StateId) {
+        self.agent.state = new_state
+    }
+    fn state_to_anim(character: &Character<Self>) -> (AnimationId, bool) {
+        let agent = &character.data.agent;
+        let animation = match agent.state {
+            BtState::Idle => agent.idle_animation,
+            BtState::Walking => agent.walk_animation,
+            BtState::Action(animation) => animation,
+        };
+        (animation, false)
+    }
+    fn on_anim_end(character: &mut Character<Self>) {
+        if let BtState::Action(_) = character.get_state() {
+            character.set_state(BtState::Idle);
+        }
+    }
+}
+
+/// The [Emitter] carried by the enemies of `kind`. It stays
+/// inactive till the enemy gets aggroed.
+pub fn kind_emitter(kind: &EnemyKind) -> Option<Emitter> {
+    let mut emitter = Emitter::new(kind.emitter?, 0.0);
+    emitter.active = false;
+    Some(emitter)
+}
+
+struct BtContext<'a> {
+    dt: f32,
+    nav: &'a NavGrid,
+    walked: bool,
+}
+
+/// Run the behaviour trees of all characters with a [BehaviourAgent].
+/// The trees are paused while an attack is playing.
+pub fn run_behaviours(dt: f32, world: &mut World, resources: &Resources) {
+    for_each_character::<BehaviourData>(world, resources, |_, mut character| {
+        character.set_walk_step(Vec2::ZERO);
+        // The emitter keeps firing during the actions
+        let aggro = character.data.perception.is_aggro();
+        if let Some(emitter) = character.data.emitter.as_deref_mut() {
+            emitter.active = aggro;
+        }
+        if let BtState::Action(_) = character.get_state() {
+            return;
+        }
+        let Some(kind) = resources.enemy_kinds.get(character.data.agent.kind) else {
+            return;
+        };
+
+        let mut ctx = BtContext {
+            dt: character.local_dt(dt),
+            nav: &resources.nav,
+            walked: false,
+        };
+        character.data.agent.tick += 1;
+        run_node(&kind.behaviour, 0, &mut character, &mut ctx);
+        if !ctx.walked && character.get_state() == BtState::Walking {
+            character.set_state(BtState::Idle);
+        }
+    });
+}
+
+fn run_node(
+    node: &BtNode,
+    idx: usize,
+    character: &mut Character<BehaviourData>,
+    ctx: &mut BtContext,
+) -> Status {
+    let agent = &mut *character.data.agent;
+    let memory = &mut agent.memory[idx];
+    if memory.last_tick + 1 != agent.tick {
+        *memory = NodeMemory::default();
+    }
+    memory.last_tick = agent.tick;
+
+    let status = tick_node(node, idx, character, ctx);
+    if status != Status::Running {
+        character.data.agent.memory[idx] = NodeMemory::default();
+    }
+    status
+}
+
+fn tick_node(
+    node: &BtNode,
+    idx: usize,
+    character: &mut Character<BehaviourData>,
+    ctx: &mut BtContext,
+) -> Status {
+    let memory = &mut character.data.agent.memory[idx];
+    match node {
+        BtNode::Sequence { children } => {
+            let cursor = memory.cursor;
+            let mut child_idx = idx + 1;
+            for (pos, child) in children.iter().enumerate() {
+                if pos < cursor {
+                    child_idx += child.size();
+                    continue;
+                }
+                match run_node(child, child_idx, character, ctx) {
+                    Status::Success => child_idx += child.size(),
+                    Status::Running => {
+                        character.data.agent.memory[idx].cursor = pos;
+                        return Status::Running;
+                    }
+                    Status::Failure => {
+                        character.data.agent.memory[idx].cursor = 0;
+                        return Status::Failure;
+                    }
+                }
+            }
+            character.data.agent.memory[idx].cursor = 0;
+            Status::Success
+        }
+        BtNode::Selector { children } => {
+            let mut child_idx = idx + 1;
+            for child in children {
+                let status = run_node(child, child_idx, character, ctx);
+                if status != Status::Failure {
+                    return status;
+                }
+                child_idx += child.size();
+            }
+            Status::Failure
+        }
+        BtNode::Invert { child } => match run_node(child, idx + 1, character, ctx) {
+            Status::Success => Status::Failure,
+            Status::Failure => Status::Success,
+            Status::Running => Status::Running,
+        },
+        BtNode::Succeed { child } => match run_node(child, idx + 1, character, ctx) {
+            Status::Running => Status::Running,
+            _ => Status::Success,
+        },
+        BtNode::Condition { condition } => {
+            if check_condition(*condition, character) {
+                Status::Success
+            } else {
+                Status::Failure
+            }
+        }
+        BtNode::MoveTo {
+            goal,
+            speed,
+            stop_distance,
+        } => {
+            let Some(goal) = goal_pos(*goal, character) else {
+                return Status::Failure;
+            };
+            if character.pos().distance(goal) <= *stop_distance {
+                return Status::Success;
+            }
+            let offset = character.path_offset(ctx.nav, goal, |data| &mut *data.path);
+            walk(character, ctx, steering::seek(Vec2::ZERO, offset) * *speed);
+            Status::Running
+        }
+        BtNode::Flee { speed, distance } => {
+            let Some(threat) = character.data.perception.last_seen() else {
+                return Status::Failure;
+            };
+            if character.pos().distance(threat) >= *distance {
+                return Status::Success;
+            }
+            let pos = character.pos();
+            let away = steering::flee(pos, threat);
+            let half_width = steering::body_half_width(character.character_q.body.shape);
+            let dir = steering::blend(&[
+                (1.0, away),
+                (
+                    1.0,
+                    steering::avoid_walls(ctx.nav, pos, away, half_width, *distance),
+                ),
+            ]);
+            walk(character, ctx, dir * *speed);
+            Status::Running
+        }
+        BtNode::LookAt { goal } => {
+            let Some(goal) = goal_pos(*goal, character) else {
+                return Status::Failure;
+            };
+            let dir = goal - character.pos();
+            if dir != Vec2::ZERO {
+                character.set_look_direction(dir);
+            }
+            Status::Success
+        }
+        BtNode::Attack { animation } => {
+            if memory.started {
+                return Status::Success;
+            }
+            memory.started = true;
+            character.set_state(BtState::Action(*animation));
+            Status::Running
+        }
+        BtNode::Wait { duration } => {
+            memory.elapsed += ctx.dt;
+            if memory.elapsed >= *duration {
+                Status::Success
+            } else {
+                Status::Running
+            }
+        }
+        BtNode::ForgetNoise => {
+            character.data.perception.forget_noise();
+            Status::Success
+        }
+    }
+}
+
+fn check_condition(condition: BtCondition, character: &Character<BehaviourData>) -> bool {
+    let perception = &character.data.perception;
+    match condition {
+        BtCondition::SeesTarget => perception.sees_target(),
+        BtCondition::IsAggro => perception.is_aggro(),
+        BtCondition::LostTarget => perception.lost_target(),
+        BtCondition::HeardNoise => perception.heard_noise().is_some(),
+        BtCondition::TargetCloser { distance } => perception
+            .last_seen()
+            .is_some_and(|pos| pos.distance(character.pos()) < distance),
+    }
+}
+
+fn goal_pos(goal: BtGoal, character: &Character<BehaviourData>) -> Option<Vec2> {
+    match goal {
+        BtGoal::Target => character.data.perception.last_seen(),
+        BtGoal::Noise => character.data.perception.heard_noise(),
+    }
+}
+
+fn walk(character: &mut Character<BehaviourData>, ctx: &mut BtContext, velocity: Vec2) {
+    if character.get_state() != BtState::Walking {
+        character.set_state(BtState::Walking);
+    }
+    character.set_walk_step(velocity * ctx.dt);
+    if velocity != Vec2::ZERO {
+        character.set_look_direction(velocity);
+    }
+    ctx.walked = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use hecs::{Entity, EntityBuilder, World};
+    use lib_asset::{
+        BtCondition, BtNode, EnemyKind, FactionManifest, FactionTable, Senses,
+        animation_manifest::AnimationId,
+    };
+    use lib_col::Shape;
+    use macroquad::prelude::*;
+
+    use super::{BehaviourAgent, BtState, run_behaviours};
+    use crate::{Animation, PathFollower, Perception, Resources, build_enemy};
+
+    const SENSES: Senses = Senses {
+        sight_range: 64.0,
+        fov: 1.0,
+        hearing_radius: 64.0,
+        memory: 10.0,
+        aggro_time: 0.0,
+        deaggro_time: 1.0,
+    };
+
+    fn spawn_agent(behaviour: BtNode) -> (World, Resources, Entity) {
+        let mut resources = Resources::new();
+        let factions = FactionManifest {
+            factions: vec!["enemy".to_string()],
+            relations: Vec::new(),
+        };
+        resources.factions = FactionTable::from_manifest(&factions).unwrap();
+        // The characters without a loaded animation are skipped
+        resources
+            .animations
+            .insert(AnimationId::BnuuyWalkD, Animation::default());
+        let kind = EnemyKind {
+            faction: "enemy".to_string(),
+            max_hp: 1,
+            hit_cooldown: 0.0,
+            shape: Shape::Rect {
+                width: 8.0,
+                height: 8.0,
+            },
+            senses: SENSES,
+            idle_animation: AnimationId::StabberIdle,
+            walk_animation: AnimationId::StabberIdle,
+            emitter: None,
+            behaviour,
+        };
+        let key = resources.enemy_kinds.insert("ai/test.json", kind);
+        let kind = resources.enemy_kinds.get(key).unwrap();
+
+        let mut builder = EntityBuilder::new();
+        let faction = resources.factions.resolve("enemy").unwrap();
+        build_enemy(&mut builder, Vec2::ZERO, kind.shape, kind.max_hp, faction);
+        builder.add_bundle((
+            BehaviourAgent::new(key, kind),
+            PathFollower::new(),
+            Perception::new(kind.senses),
+        ));
+        let mut world = World::new();
+        let ent = world.spawn(builder.build());
+        (world, resources, ent)
+    }
+
+    fn hear_noise(world: &World, ent: Entity, heard: bool) {
+        let mut perception = world.get::<&mut Perception>(ent).unwrap();
+        if heard {
+            perception.observe(0.0, None, Some(Vec2::ONE));
+        } else {
+            perception.forget_noise();
+        }
+    }
+
+    fn state(world: &World, ent: Entity) -> BtState {
+        world.get::<&BehaviourAgent>(ent).unwrap().state()
+    }
+
+    /// What happens once the action animation is over
+    fn finish_action(world: &World, ent: Entity) {
+        world.get::<&mut BehaviourAgent>(ent).unwrap().state = BtState::Idle;
+    }
+
+    fn heard_noise() -> BtNode {
+        BtNode::Condition {
+            condition: BtCondition::HeardNoise,
+        }
+    }
+
+    fn attack(animation: AnimationId) -> BtNode {
+        BtNode::Attack { animation }
+    }
+
+    fn wait(duration: f32) -> BtNode {
+        BtNode::Wait { duration }
+    }
+
+    #[test]
+    fn test_sequence_resumes() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Sequence {
+            children: vec![heard_noise(), wait(0.3), attack(AnimationId::StabberAttack)],
+        });
+
+        hear_noise(&world, ent, true);
+        run_behaviours(0.2, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+
+        // The condition is not checked again while the wait is running
+        hear_noise(&world, ent, false);
+        run_behaviours(0.2, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+    }
+
+    #[test]
+    fn test_selector_priority() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Selector {
+            children: vec![
+                BtNode::Sequence {
+                    children: vec![heard_noise(), attack(AnimationId::StabberAttack)],
+                },
+                wait(10.0),
+            ],
+        });
+
+        run_behaviours(0.1, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+
+        // The first child interrupts the running wait
+        hear_noise(&world, ent, true);
+        run_behaviours(0.1, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+    }
+
+    #[test]
+    fn test_attack_memory() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Sequence {
+            children: vec![
+                attack(AnimationId::StabberAttack),
+                wait(1.0),
+                attack(AnimationId::StabberGuard),
+            ],
+        });
+
+        run_behaviours(0.1, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+        // The tree is paused during the action
+        run_behaviours(0.1, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+
+        // The finished attack succeeds instead of playing again
+        finish_action(&world, ent);
+        run_behaviours(0.5, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+        run_behaviours(0.6, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberGuard)
+        );
+    }
+
+    #[test]
+    fn test_attack_loop() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Sequence {
+            children: vec![heard_noise(), attack(AnimationId::StabberAttack)],
+        });
+        hear_noise(&world, ent, true);
+
+        for _ in 0..2 {
+            run_behaviours(0.1, &mut world, &resources);
+            assert_eq!(
+                state(&world, ent),
+                BtState::Action(AnimationId::StabberAttack)
+            );
+            finish_action(&world, ent);
+            run_behaviours(0.1, &mut world, &resources);
+            assert_eq!(state(&world, ent), BtState::Idle);
+        }
+    }
+
+    #[test]
+    fn test_wait_loop() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Sequence {
+            children: vec![wait(0.5), heard_noise(), attack(AnimationId::StabberAttack)],
+        });
+
+        run_behaviours(0.3, &mut world, &resources);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+
+        // The finished wait runs in full on the next pass
+        hear_noise(&world, ent, true);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+    }
+
+    #[test]
+    fn test_wait_restarts() {
+        let (mut world, resources, ent) = spawn_agent(BtNode::Selector {
+            children: vec![
+                BtNode::Sequence {
+                    children: vec![heard_noise(), attack(AnimationId::StabberAttack)],
+                },
+                BtNode::Sequence {
+                    children: vec![wait(0.5), attack(AnimationId::StabberGuard)],
+                },
+            ],
+        });
+
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+        hear_noise(&world, ent, true);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberAttack)
+        );
+
+        finish_action(&world, ent);
+        hear_noise(&world, ent, false);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+
+        // The interrupted wait starts over
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(state(&world, ent), BtState::Idle);
+        run_behaviours(0.3, &mut world, &resources);
+        assert_eq!(
+            state(&world, ent),
+            BtState::Action(AnimationId::StabberGuard)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use hecs::{CommandBuffer, World};
+use lib_asset::{AssetKey, BossDef};
+
+use crate::{
+    BehaviourAgent, BehaviourData, BtState, Emitter, Health, Resources, emit_event,
+    for_each_character, kind_emitter,
+};
+
+/// Emitted once a boss has been defeated
+#[derive(Debug, Clone, Copy)]
+pub struct BossDefeated {
+    pub boss: AssetKey,
+}
+
+/// Makes a [BehaviourAgent] character a boss. The boss switches
+/// its behaviour at the health thresholds of its [BossDef].
+#[derive(Debug, Clone, Copy)]
+pub struct Boss {
+    pub def: AssetKey,
+    pub max_hp: i32,
+    phase: usize,
+    in_transition: bool,
+}
+
+impl Boss {
+    pub fn new(def: AssetKey, max_hp: i32) -> Self {
+        Self {
+            def,
+            max_hp,
+            phase: 0,
+            in_transition: false,
+        }
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    /// Whether the boss is playing a phase transition
+    pub fn in_transition(&self) -> bool {
+        self.in_transition
+    }
+
+    pub fn health_fraction(&self, hp: &Health) -> f32 {
+        hp.value.max(0) as f32 / self.max_hp as f32
+    }
+
+    /// The phase the boss should switch to. The phases never go back.
+    fn next_phase(&self, def: &BossDef, hp: &Health) -> Option<usize> {
+        let phase = def.phase_at(self.health_fraction(hp));
+        (phase > self.phase).then_some(phase)
+    }
+}
+
+/// The bosses can't be damaged during the phase transitions
+pub(crate) fn update_invulnerability(world: &mut World) {
+    for (_, (boss, agent, hp)) in world.query_mut::<(&mut Boss, &BehaviourAgent, &mut Health)>() {
+        if !boss.in_transition {
+            continue;
+        }
+        if matches!(agent.state(), BtState::Action(_)) {
+            hp.is_invulnerable = true;
+        } else {
+            boss.in_transition = false;
+        }
+    }
+}
+
+/// Switch the boss phases and report the defeated bosses.
+/// Run this after the damage has been applied.
+pub(crate) fn update(world: &mut World, resources: &Resources, cmds: &mut CommandBuffer) {
+    let mut bosses = world.query::<&mut Boss>();
+    let mut bosses = bosses.view();
+
+    for_each_character::<BehaviourData>(world, resources, |ent, mut character| {
+        let Some(boss) = bosses.get_mut(ent) else {
+            return;
+        };
+        if character.character_q.hp.value <= 0 {
+            emit_event(cmds, BossDefeated { boss: boss.def });
+            return;
+        }
+        let Some(def) = resources.bosses.get(boss.def) else {
+            return;
+        };
+        let Some(phase) = boss.next_phase(def, character.character_q.hp) else {
+            return;
+        };
+
+        boss.phase = phase;
+        let phase = &def.phases[phase];
+        let Some((key, kind)) = resources
+            .enemy_kinds
+            .resolve(phase.kind.as_str())
+            .and_then(|key| Some((key, resources.enemy_kinds.get(key)?)))
+        else {
+            return;
+        };
+        *character.data.agent = BehaviourAgent::new(key, kind);
+        character.data.path.invalidate();
+        match kind_emitter(kind) {
+            Some(emitter) => cmds.insert_one(ent, emitter),
+            None => cmds.remove_one::<Emitter>(ent),
+        }
+        if let Some(transition) = phase.transition {
+            character.set_state(BtState::Action(transition));
+            boss.in_transition = true;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_asset::{BossDef, BossPhase, INVALID_ASSET, Symbol};
+
+    use super::Boss;
+    use crate::Health;
+
+    #[test]
+    fn test_next_phase() {
+        let phase = |health_fraction| BossPhase {
+            health_fraction,
+            kind: Symbol::new("ai/brute.json"),
+            transition: None,
+        };
+        let def = BossDef {
+            name: "Test".to_string(),
+            max_hp: 10,
+            phases: vec![phase(1.0), phase(0.5), phase(0.2)],
+        };
+        let mut boss = Boss::new(INVALID_ASSET, def.max_hp);
+
+        assert_eq!(boss.next_phase(&def, &Health::new(6)), None);
+        assert_eq!(boss.next_phase(&def, &Health::new(5)), Some(1));
+        // Big hits skip the phases
+        assert_eq!(boss.next_phase(&def, &Health::new(1)), Some(2));
+
+        boss.phase = 2;
+        assert_eq!(boss.next_phase(&def, &Health::new(10)), None);
+    }
+}
@@ -1,13 +1,17 @@
-use crate::{LockInput, Move, animation::Animation};
+use crate::{
+    AnimationEvent, AnimationLayers, CancelWindow, ClipAction, LockInput, Move,
+    animation::Animation,
+};
+use hashbrown::HashMap;
 use hecs::{Entity, EntityBuilder, Query, World};
-use lib_asset::animation_manifest::AnimationId;
+use lib_asset::{Symbol, animation_manifest::AnimationId};
 use lib_col::{Group, Shape};
 use log::warn;
 use macroquad::prelude::*;
 
 use crate::{
-    AnimationPlay, BodyTag, CharacterLook, Direction, Health, KinematicControl, Resources, Team,
-    Transform, col_group, draw_shape_lines,
+    AnimationPlay, BodyTag, CharacterLook, Defense, Faction, Health, KinematicControl,
+    LocalTimeScale, Resources, Transform, col_group, draw_shape_lines, local_dt,
 };
 
 pub fn draw_char_state(world: &World, resources: &Resources) {
@@ -15,7 +19,8 @@ pub fn draw_char_state(world: &World, resources: &Resources) {
         let debug_texts = [
             format!("ID {ent:?}"),
             format!("{:?}", character.animation_id()),
-            format!("cursor (ms): {}", character.character_q.play.cursor),
+            format!("cursor (ms): {}", character.anim_cursor()),
+            format!("anim speed: {:.2}", character.character_q.play.speed),
             format!("look: {:.2}", character.character_q.look.0.to_degrees()),
             format!("dr: {:.2}", character.character_q.kinematic.dr),
         ];
@@ -54,7 +59,17 @@ where
     for<'a> Q::Item<'a>: CharacterData,
 {
     for_each_character::<Q>(world, resources, |_, character| {
-        character.character_q.play.animation = Q::Item::state_to_anim(&character);
+        let (next, mirrored) = Q::Item::state_to_anim(&character);
+        let crossfade = resources
+            .animations
+            .get(&next)
+            .map_or(0.0, |anim| anim.crossfade);
+        let position = character.anim_cursor();
+        character
+            .character_q
+            .play
+            .switch_to(next, crossfade, position);
+        character.character_q.play.mirrored = mirrored;
     });
 }
 
@@ -70,6 +85,26 @@ where
     });
 }
 
+/// Hands the [AnimationEvent]s over to [CharacterData::on_anim_event]
+pub fn dispatch_anim_events<Q: Query>(world: &mut World, resources: &Resources)
+where
+    for<'a> Q::Item<'a>: CharacterData,
+{
+    let mut events = HashMap::<Entity, Vec<Symbol>>::new();
+    for (_, event) in world.query_mut::<&AnimationEvent>() {
+        events.entry(event.entity).or_default().push(event.name);
+    }
+    if events.is_empty() {
+        return;
+    }
+
+    for_each_character::<Q>(world, resources, |ent, mut character| {
+        for name in events.get(&ent).into_iter().flatten() {
+            Q::Item::on_anim_event(&mut character, *name);
+        }
+    });
+}
+
 pub fn for_each_character<Q: Query>(
     world: &World,
     resources: &Resources,
@@ -90,6 +125,7 @@ pub fn for_each_character<Q: Query>(
                 character_q,
                 data: state,
                 animation,
+                resources,
             },
         );
     }
@@ -99,6 +135,8 @@ pub struct Character<'a, T> {
     pub character_q: CharacterQuery<'a>,
     pub data: T,
     pub animation: &'a Animation,
+    /// Lets the characters look up the assets they only store keys to
+    pub resources: &'a Resources,
 }
 
 impl<'a, T> Character<'a, T> {
@@ -127,32 +165,110 @@ impl<'a, T> Character<'a, T> {
     }
 
     pub fn get_input_flags(&self) -> (bool, bool) {
-        self.animation
-            .active_clips::<LockInput>(self.anim_cursor())
-            .map(|(_, x)| (x.allow_walk_input, x.allow_look_input))
+        self.active_clips::<LockInput>()
+            .map(|(_, _, x)| (x.allow_walk_input, x.allow_look_input))
             .next()
             .unwrap_or((true, true))
     }
 
     pub fn can_move(&self) -> bool {
-        self.animation
-            .active_clips::<Move>(self.anim_cursor())
-            .next()
-            .is_some()
+        self.active_clips::<Move>().next().is_some()
+    }
+
+    /// Checks if the animation may be interrupted by `into` right now.
+    /// See [CancelWindow].
+    pub fn can_cancel_into(&self, into: Symbol) -> bool {
+        self.active_clips::<CancelWindow>()
+            .any(|(_, _, window)| window.into == into)
+    }
+
+    /// The [AnimationLayers] with the positions they are at
+    pub fn layers(&self) -> impl Iterator<Item = (AnimationId, &'a Animation, u32)> + '_ {
+        let animations = &self.resources.animations;
+        self.character_q
+            .layers
+            .iter()
+            .flat_map(|layers| &layers.layers)
+            .filter_map(|layer| {
+                let animation = animations.get(&layer.animation)?;
+                Some((layer.animation, animation, layer.position(animation)))
+            })
+    }
+
+    /// The main animation and the layers with the positions they are at
+    pub fn playing(&self) -> impl Iterator<Item = (AnimationId, &'a Animation, u32)> + '_ {
+        let main = (self.animation_id(), self.animation, self.anim_cursor());
+        std::iter::once(main).chain(self.layers())
     }
 
+    /// The active clips of the main animation and the layers,
+    /// along with the animations they come from
+    pub fn active_clips<C: ClipAction>(&self) -> impl Iterator<Item = (AnimationId, u32, C)> + '_ {
+        self.playing().flat_map(|(id, animation, pos)| {
+            let mirrored = self.is_mirrored(id);
+            animation
+                .active_clips::<C>(pos)
+                .map(move |(clip_id, mut action)| {
+                    if mirrored {
+                        action.mirror();
+                    }
+                    (id, clip_id, action)
+                })
+        })
+    }
+
+    /// Same as [Character::active_clips], but during a crossfade the clips
+    /// of the main animation start from the pose of the clips on the tracks
+    /// with the same name in the animation that is faded out
+    pub fn blended_clips<C: ClipAction>(&self) -> impl Iterator<Item = (AnimationId, u32, C)> + '_ {
+        let play = &*self.character_q.play;
+        let fade = play
+            .fade
+            .and_then(|fade| Some((fade, self.resources.animations.get(&fade.from)?)));
+        let main = self
+            .animation
+            .active_clips_named::<C>(self.anim_cursor())
+            .map(move |(clip_id, track, mut action)| {
+                if play.mirrored {
+                    action.mirror();
+                }
+                let old = fade.and_then(|(fade, from)| {
+                    let (_, _, mut old) = from
+                        .active_clips_named::<C>(fade.position)
+                        .find(|(_, name, _)| *name == track)?;
+                    if fade.mirrored {
+                        old.mirror();
+                    }
+                    Some((old, fade.weight()))
+                });
+                if let Some((old, weight)) = old {
+                    action.blend_from(&old, weight);
+                }
+                (play.animation, clip_id, action)
+            });
+        let layers = self.layers().flat_map(|(id, animation, pos)| {
+            animation
+                .active_clips::<C>(pos)
+                .map(move |(clip_id, action)| (id, clip_id, action))
+        });
+        main.chain(layers)
+    }
+
+    /// Checks if the clips of `animation` must be mirrored.
+    /// Only the main animation can be played mirrored.
+    pub fn is_mirrored(&self, animation: AnimationId) -> bool {
+        self.character_q.play.mirrored && animation == self.animation_id()
+    }
+
+    /// The position inside the animation, that the clips are sampled at
     pub fn anim_cursor(&self) -> u32 {
-        self.character_q.play.cursor
+        self.character_q.play.position(self.animation)
     }
 
     pub fn look_angle(&self) -> f32 {
         self.character_q.look.0
     }
 
-    pub fn look_dir_enum(&self) -> Direction {
-        self.character_q.look.to_dir_enum()
-    }
-
     pub fn transform_child(&self, rotate: bool, pos: Vec2, angle: f32) -> Transform {
         if rotate {
             Transform {
@@ -185,12 +301,20 @@ impl<'a, T> Character<'a, T> {
     pub fn animation_id(&self) -> AnimationId {
         self.character_q.play.animation
     }
+
+    pub fn faction(&self) -> Faction {
+        *self.character_q.faction
+    }
+
+    /// Apply the character's [LocalTimeScale] to `dt`
+    pub fn local_dt(&self, dt: f32) -> f32 {
+        local_dt(dt, self.character_q.time_scale)
+    }
 }
 
 impl<'a, T: CharacterData> Character<'a, T> {
     pub fn set_state(&mut self, state_id: T::StateId) {
-        self.character_q.play.cursor = 0;
-        self.character_q.play.total_dt = 0.0f32;
+        self.character_q.play.restart(self.animation);
         self.data.set_state(state_id);
     }
 
@@ -203,8 +327,14 @@ pub trait CharacterData: Sized {
     type StateId;
     fn get_state(&self) -> Self::StateId;
     fn set_state(&mut self, new_state: Self::StateId);
-    fn state_to_anim(character: &Character<Self>) -> AnimationId;
+
+    /// The animation for the current state, and whether it is
+    /// played mirrored. See [crate::CharacterLook::pick_animation].
+    fn state_to_anim(character: &Character<Self>) -> (AnimationId, bool);
     fn on_anim_end(character: &mut Character<Self>);
+
+    /// Called for each [crate::AnimEvent] clip, that the animation reaches
+    fn on_anim_event(_character: &mut Character<Self>, _event: Symbol) {}
 }
 
 #[derive(Query)]
@@ -212,32 +342,39 @@ pub struct CharacterQuery<'a> {
     pub tf: &'a mut Transform,
     pub kinematic: &'a mut KinematicControl,
     pub play: &'a mut AnimationPlay,
+    pub layers: Option<&'a mut AnimationLayers>,
     pub look: &'a mut CharacterLook,
     pub hp: &'a mut Health,
-    pub team: &'a Team,
+    pub faction: &'a Faction,
     pub body: &'a BodyTag,
+    pub time_scale: Option<&'a LocalTimeScale>,
 }
 
-pub fn build_player(builder: &mut EntityBuilder, pos: Vec2, shape: Shape, spawn_health: i32) {
+pub fn build_player(
+    builder: &mut EntityBuilder,
+    pos: Vec2,
+    shape: Shape,
+    spawn_health: i32,
+    faction: Faction,
+) {
     build_character(
         builder,
         pos,
         col_group::PLAYER,
         shape,
         spawn_health,
-        Team::Player,
+        faction,
     )
 }
 
-pub fn build_enemy(builder: &mut EntityBuilder, pos: Vec2, shape: Shape, spawn_health: i32) {
-    build_character(
-        builder,
-        pos,
-        col_group::NONE,
-        shape,
-        spawn_health,
-        Team::Enemy,
-    )
+pub fn build_enemy(
+    builder: &mut EntityBuilder,
+    pos: Vec2,
+    shape: Shape,
+    spawn_health: i32,
+    faction: Faction,
+) {
+    build_character(builder, pos, col_group::NONE, shape, spawn_health, faction)
 }
 
 pub fn build_character(
@@ -246,23 +383,117 @@ pub fn build_character(
     group: Group,
     shape: Shape,
     spawn_health: i32,
-    team: Team,
+    faction: Faction,
 ) {
     builder.add_bundle((
-        team,
+        faction,
         Transform::from_pos(pos),
         CharacterLook(0.0),
         Health::new(spawn_health),
+        Defense::default(),
         KinematicControl::new_slide(col_group::LEVEL),
         BodyTag {
             groups: col_group::CHARACTERS.union(group),
             shape,
         },
-        AnimationPlay {
-            pause: false,
-            animation: AnimationId::BnuuyWalkD,
-            total_dt: 0.0,
-            cursor: 0,
-        },
+        AnimationPlay::new(AnimationId::BnuuyWalkD),
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use hecs::{Query, World};
+    use lib_asset::{Symbol, animation_manifest::AnimationId};
+
+    use super::{Character, CharacterData, dispatch_anim_events, for_each_character};
+    use crate::animation::tests::{add_clips, character_resources, clip, spawn_character};
+    use crate::{Animation, AnimationEvent, AnimationPlay, CancelWindow};
+
+    /// The events a test character got
+    struct SeenEvents(Vec<Symbol>);
+
+    #[derive(Query)]
+    struct TestData<'a> {
+        seen: &'a mut SeenEvents,
+    }
+
+    impl CharacterData for TestData<'_> {
+        type StateId = ();
+
+        fn get_state(&self) -> Self::StateId {}
+        fn set_state(&mut self, _new_state: Self::StateId) {}
+        fn state_to_anim(_character: &Character<Self>) -> (AnimationId, bool) {
+            (AnimationId::BnuuyWalkD, false)
+        }
+        fn on_anim_end(_character: &mut Character<Self>) {}
+        fn on_anim_event(character: &mut Character<Self>, event: Symbol) {
+            character.data.seen.0.push(event);
+        }
+    }
+
+    #[test]
+    fn test_dispatch_anim_events() {
+        let (resources, faction) = character_resources(Animation::default());
+        let mut world = World::new();
+        let ent = spawn_character(&mut world, faction);
+        let other = spawn_character(&mut world, faction);
+        world.insert_one(ent, SeenEvents(Vec::new())).unwrap();
+        let event = |entity, name| AnimationEvent {
+            entity,
+            animation: AnimationId::BnuuyWalkD,
+            name: Symbol::new(name),
+        };
+        world.spawn((event(ent, "swing"),));
+        world.spawn((event(other, "step"),));
+        world.spawn((event(ent, "land"),));
+
+        dispatch_anim_events::<TestData>(&mut world, &resources);
+        let mut seen = world.get::<&SeenEvents>(ent).unwrap().0.clone();
+        seen.sort_by_key(|name| name.as_str());
+        assert_eq!(seen, vec![Symbol::new("land"), Symbol::new("swing")]);
+    }
+
+    #[test]
+    fn test_can_cancel_into() {
+        let mut anim = Animation::default();
+        let window = |into| CancelWindow {
+            into: Symbol::new(into),
+        };
+        add_clips(
+            &mut anim,
+            vec![
+                (clip(0, 0, 10), window("dash")),
+                (clip(1, 5, 5), window("attack")),
+                (clip(2, 15, 5), window("block")),
+            ],
+        );
+        let (resources, faction) = character_resources(anim);
+        let mut world = World::new();
+        let ent = spawn_character(&mut world, faction);
+        let cancels = |world: &World| {
+            let mut result = Vec::new();
+            for_each_character::<()>(world, &resources, |_, character| {
+                result = ["dash", "attack", "block"]
+                    .into_iter()
+                    .filter(|into| character.can_cancel_into(Symbol::new(into)))
+                    .collect();
+            });
+            result
+        };
+        let seek = |world: &World, pos| {
+            let anim = &resources.animations[&AnimationId::BnuuyWalkD];
+            world
+                .get::<&mut AnimationPlay>(ent)
+                .unwrap()
+                .seek(pos, anim);
+        };
+
+        assert_eq!(cancels(&world), vec!["dash"]);
+        seek(&world, 7);
+        assert_eq!(cancels(&world), vec!["dash", "attack"]);
+        seek(&world, 12);
+        assert_eq!(cancels(&world), Vec::<&str>::new());
+        seek(&world, 17);
+        assert_eq!(cancels(&world), vec!["block"]);
+    }
+}
@@ -49,6 +49,8 @@ pub struct KinematicControl {
     pub collision: Group,
     pub slide: bool,
     pub collided: bool,
+    /// The normal of the last surface the body has hit
+    pub normal: Vec2,
 }
 
 impl KinematicControl {
@@ -60,6 +62,7 @@ impl KinematicControl {
             collision,
             slide: true,
             collided: false,
+            normal: Vec2::ZERO,
         }
     }
 
@@ -69,6 +72,7 @@ impl KinematicControl {
             collision,
             slide: false,
             collided: false,
+            normal: Vec2::ZERO,
         }
     }
 }
@@ -17,8 +17,16 @@ fn draw_queries<const ID: usize>(world: &World) {
 }
 
 fn draw_bodies(world: &World) {
-    for (_, (tf, tag)) in &mut world.query::<(&Transform, &BodyTag)>() {
-        draw_shape(tf, &tag.shape, DARKBLUE);
+    for (_, (tf, tag, hurtbox, defense)) in
+        &mut world.query::<(&Transform, &BodyTag, Option<&HurtboxOf>, Option<&Defense>)>()
+    {
+        if hurtbox.is_some() {
+            draw_shape(tf, &tag.shape, ORANGE);
+        } else if defense.is_some_and(|x| x.body_replaced) {
+            draw_shape_lines(tf, &tag.shape, DARKBLUE);
+        } else {
+            draw_shape(tf, &tag.shape, DARKBLUE);
+        }
     }
 }
 
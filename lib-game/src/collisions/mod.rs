@@ -42,10 +42,13 @@ impl CollisionSolver {
             character.group = kin.collision;
 
             let dr = lib_col::conv::topleft_corner_vector_to_crate(kin.dr);
-            let (new_tf, collided) =
+            let (new_tf, normal) =
                 process_character_movement(&mut self.solver, dr, character, kin.slide);
             tf.pos = lib_col::conv::crate_vector_to_topleft_corner(new_tf.translation);
-            kin.collided = collided;
+            kin.collided = normal.is_some();
+            kin.normal = normal
+                .map(lib_col::conv::crate_vector_to_topleft_corner)
+                .unwrap_or_default();
         }
     }
 
@@ -107,8 +110,8 @@ fn process_character_movement(
     mut dr: Vec2,
     mut character: lib_col::Collider,
     slide: bool,
-) -> (Affine2, bool) {
-    let mut collided = false;
+) -> (Affine2, Option<Vec2>) {
+    let mut last_normal = None;
     for _ in 0..CHAR_MOVEMENT_ITERS {
         let offlen = dr.length();
         let direction = dr.normalize_or_zero();
@@ -122,13 +125,13 @@ fn process_character_movement(
 
         dr -= dr.dot(normal) * normal;
         dr += normal * CHAR_NORMAL_NUDGE;
-        collided = true;
+        last_normal = Some(normal);
         if !slide {
             break;
         }
     }
 
-    (character.tf, collided)
+    (character.tf, last_normal)
 }
 
 fn get_query_collider<const ID: usize>(
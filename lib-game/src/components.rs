@@ -1,14 +1,49 @@
 use std::any::TypeId;
 
-use crate::animation::Animation;
+use crate::animation::{ANIMATION_TIME_UNIT, Animation, ParryKind, ParryWindow, ShieldArc};
 use hecs::Entity;
-use lib_asset::{AssetKey, animation_manifest::AnimationId};
+use lib_asset::{
+    AssetKey, ProjectileBehaviour, animation_manifest::AnimationId, gamecfg::sections,
+};
 use macroquad::prelude::*;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ProjectileData {
+    /// The direction the projectile is flying in.
+    /// The wave motion is applied on top of it.
     pub dir: Vec2,
     pub speed: f32,
+    pub behaviour: ProjectileBehaviour,
+    /// For how long the projectile has been flying
+    pub age: f32,
+    /// How far the projectile has flown
+    pub travelled: f32,
+    pub bounces_left: u32,
+}
+
+impl ProjectileData {
+    pub fn new(dir: Vec2, speed: f32, behaviour: ProjectileBehaviour) -> Self {
+        Self {
+            dir,
+            speed,
+            behaviour,
+            age: 0.0,
+            travelled: 0.0,
+            bounces_left: behaviour.bounces,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let too_old = self
+            .behaviour
+            .lifetime
+            .is_some_and(|lifetime| self.age >= lifetime);
+        let too_far = self
+            .behaviour
+            .max_range
+            .is_some_and(|range| self.travelled >= range);
+        too_old || too_far
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -19,12 +54,10 @@ pub struct Sprite {
     pub color: Color,
     pub sort_offset: f32,
     pub local_offset: Vec2,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Team {
-    Player,
-    Enemy,
+    /// Stretches the sprite away from its position
+    pub scale: Vec2,
+    pub flip_x: bool,
+    pub flip_y: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -36,6 +69,57 @@ pub struct GrazeGain {
 #[derive(Clone, Copy, Debug)]
 pub struct GrazeValue(pub f32);
 
+/// [Defense] stores the defensive moves the character is doing
+/// this frame. It is refreshed from the animation every frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Defense {
+    pub parry: Option<ParryWindow>,
+    pub shield: Option<ShieldArc>,
+    /// Only the hurtboxes of the character can be hit.
+    /// See [crate::Hurtbox].
+    pub body_replaced: bool,
+}
+
+/// Put onto the hurtboxes spawned by [crate::Hurtbox] clips.
+/// Hitting the hurtbox counts as hitting its `owner`.
+#[derive(Clone, Copy, Debug)]
+pub struct HurtboxOf {
+    pub owner: Entity,
+}
+
+/// Put onto the entities spawned by [crate::Spawn] clips and emitters.
+/// Lets reflected projectiles find their way back.
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnedBy {
+    pub owner: Entity,
+}
+
+/// Put onto an attack once it was parried or dodged.
+/// The attack can't hurt `by` anymore.
+#[derive(Clone, Copy, Debug)]
+pub struct Deflected {
+    pub by: Entity,
+}
+
+/// Emitted when an attack lands on a character's shield.
+/// `attacker` is the character that owns the attack, if there is one.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockEvent {
+    pub defender: Entity,
+    pub attack: Entity,
+    pub attacker: Option<Entity>,
+}
+
+/// Emitted when a character parries or perfectly dodges an attack.
+/// `attacker` is the character that owns the attack, if there is one.
+#[derive(Clone, Copy, Debug)]
+pub struct ParryEvent {
+    pub kind: ParryKind,
+    pub defender: Entity,
+    pub attack: Entity,
+    pub attacker: Option<Entity>,
+}
+
 /// [Health] component stores entity's health.
 /// Normally, to do damage, you should just put it into the `damage` field.
 /// `damage` is zeroed every frame and is substracted to `value`.
@@ -77,6 +161,47 @@ impl DamageCooldown {
     }
 }
 
+/// Makes the character's sprites react to damage. They get tinted
+/// with `color` right after a hit and blink while invulnerable.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteFlash {
+    pub color: Color,
+    /// For how long the tint stays after a hit
+    pub duration: f32,
+    /// Blinks per second
+    pub blink_rate: f32,
+    pub remaining: f32,
+    /// Drives the blinking
+    pub time: f32,
+}
+
+impl SpriteFlash {
+    pub fn new(cfg: &sections::SpriteFlash) -> Self {
+        Self {
+            color: Color::from_vec(cfg.color.into()),
+            duration: cfg.duration,
+            blink_rate: cfg.blink_rate,
+            remaining: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// The color to draw a sprite with, instead of `color`
+    pub fn apply(&self, color: Color, is_invulnerable: bool) -> Color {
+        let mut result = color;
+        if self.remaining > 0.0 && self.duration > 0.0 {
+            let t = self.remaining / self.duration;
+            result.r *= 1.0 + (self.color.r - 1.0) * t;
+            result.g *= 1.0 + (self.color.g - 1.0) * t;
+            result.b *= 1.0 + (self.color.b - 1.0) * t;
+        }
+        if is_invulnerable && (self.time * self.blink_rate).fract() >= 0.5 {
+            result.a *= 0.25;
+        }
+        result
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Transform {
     pub pos: Vec2,
@@ -101,17 +226,182 @@ impl Transform {
 pub struct AnimationPlay {
     pub animation: AnimationId,
     pub total_dt: f32,
+    /// How far the animation has played. When the animation plays
+    /// backwards, this counts from its end. The clips are sampled
+    /// at [AnimationPlay::position].
     pub cursor: u32,
     pub pause: bool,
+    /// The playback rate. `1.0` is the authored speed, `0.0` holds
+    /// the current frame and negative values play backwards.
+    pub speed: f32,
+    /// Plays the animation flipped left to right.
+    /// See [CharacterLook::pick_animation].
+    pub mirrored: bool,
+    /// The animation, that is being faded out
+    pub fade: Option<Crossfade>,
+    reversed: bool,
+    /// The animation and the position, where it was left by [AnimationPlay::restart]
+    left: Option<(AnimationId, u32)>,
+    /// The first switch picks the animation for the freshly spawned
+    /// character, so there is nothing to fade from
+    synced: bool,
 }
 
 impl AnimationPlay {
+    pub fn new(animation: AnimationId) -> Self {
+        Self {
+            animation,
+            total_dt: 0.0,
+            cursor: 0,
+            pause: false,
+            speed: 1.0,
+            mirrored: false,
+            fade: None,
+            reversed: false,
+            left: None,
+            synced: false,
+        }
+    }
+
+    /// Start the animation over. The pose it is left at is
+    /// faded out, if the character switches to another animation.
+    pub fn restart(&mut self, animation: &Animation) {
+        self.left = Some((self.animation, self.position(animation)));
+        self.cursor = 0;
+        self.total_dt = 0.0;
+    }
+
+    /// Switch to `animation`, fading out the current one for `crossfade`
+    /// seconds. `position` is where the current animation is now.
+    pub fn switch_to(&mut self, animation: AnimationId, crossfade: f32, position: u32) {
+        let left = self.left.take();
+        let synced = std::mem::replace(&mut self.synced, true);
+        if animation == self.animation {
+            return;
+        }
+
+        let position = left
+            .filter(|(id, _)| *id == self.animation)
+            .map_or(position, |(_, pos)| pos);
+        self.fade = (synced && crossfade > 0.0).then_some(Crossfade {
+            from: self.animation,
+            mirrored: self.mirrored,
+            position,
+            elapsed: 0.0,
+            duration: crossfade,
+        });
+        self.animation = animation;
+    }
+
+    /// Checks if the animation has reached its end
+    /// (or its start, when playing backwards)
     pub fn is_done(&self, animation: &Animation) -> bool {
         if animation.is_looping {
             return false;
         }
         self.cursor == animation.max_pos()
     }
+
+    /// A held animation keeps the direction it had
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// The position inside the animation, that the clips are sampled at
+    pub fn position(&self, animation: &Animation) -> u32 {
+        if !self.reversed {
+            return self.cursor;
+        }
+        animation.max_pos().saturating_sub(self.cursor)
+    }
+
+    /// Jump to `pos` inside the animation, keeping the direction
+    pub fn seek(&mut self, pos: u32, animation: &Animation) {
+        self.cursor = if !self.reversed {
+            pos
+        } else {
+            animation.max_pos().saturating_sub(pos)
+        };
+    }
+
+    pub(crate) fn advance(&mut self, dt: f32, animation: &Animation) {
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += dt;
+            if fade.elapsed >= fade.duration {
+                self.fade = None;
+            }
+        }
+
+        let max_pos = animation.max_pos();
+        if max_pos == 0 || self.pause || self.speed == 0.0 {
+            return;
+        }
+
+        if (self.speed < 0.0) != self.reversed {
+            // Keep the position, unless the animation has just been restarted
+            self.reversed = !self.reversed;
+            if self.cursor > 0 {
+                self.cursor = max_pos.saturating_sub(self.cursor);
+            }
+        }
+
+        self.total_dt += dt * self.speed.abs();
+        if self.total_dt < ANIMATION_TIME_UNIT {
+            return;
+        }
+
+        let cursor_delta = self.total_dt.div_euclid(ANIMATION_TIME_UNIT);
+        self.total_dt -= cursor_delta * ANIMATION_TIME_UNIT;
+        self.cursor += cursor_delta as u32;
+        if animation.is_looping {
+            self.cursor %= max_pos;
+        } else {
+            self.cursor = self.cursor.min(max_pos);
+        }
+    }
+}
+
+/// The previous animation of a character, that stays
+/// frozen at `position` while it fades out.
+#[derive(Debug, Clone, Copy)]
+pub struct Crossfade {
+    pub from: AnimationId,
+    pub mirrored: bool,
+    pub position: u32,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl Crossfade {
+    /// How much of the new animation is shown, from `0.0` to `1.0`
+    pub fn weight(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Animations played on top of the main [AnimationPlay]. Their clips
+/// work the same as the main animation's, e.g. an overlay can draw
+/// over the walk cycle. The layers, that don't loop, are dropped once done.
+/// The layers must not play the main animation.
+#[derive(Default)]
+pub struct AnimationLayers {
+    pub layers: Vec<AnimationPlay>,
+}
+
+impl AnimationLayers {
+    /// Play `animation` on top. Plays it from the start, if it is already on.
+    pub fn play(&mut self, animation: AnimationId) {
+        self.stop(animation);
+        self.layers.push(AnimationPlay::new(animation));
+    }
+
+    pub fn stop(&mut self, animation: AnimationId) {
+        self.layers.retain(|layer| layer.animation != animation);
+    }
+
+    pub fn is_playing(&self, animation: AnimationId) -> bool {
+        self.layers.iter().any(|layer| layer.animation == animation)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -134,25 +424,44 @@ impl CharacterLook {
         Self(dir.to_angle())
     }
 
-    pub fn to_dir_enum(self) -> Direction {
-        let angle = self.0;
-        let increment = std::f32::consts::FRAC_PI_4;
-        if (-3.0 * increment..=-increment).contains(&angle) {
-            Direction::Up
-        } else if (-increment..increment).contains(&angle) {
-            Direction::Right
-        } else if (increment..=3.0 * increment).contains(&angle) {
-            Direction::Down
-        } else {
-            Direction::Left
-        }
+    /// The closest of `count` directions, that are evenly
+    /// spaced clockwise starting from the right
+    pub fn to_sector(self, count: usize) -> usize {
+        let step = std::f32::consts::TAU / count as f32;
+        (self.0.rem_euclid(std::f32::consts::TAU) / step).round() as usize % count
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Direction {
-    Right = 0,
-    Down = 1,
-    Left = 2,
-    Up = 3,
+    /// Picks the animation for the look out of `directions` (laid out
+    /// as in [CharacterLook::to_sector]). A missing direction is replaced
+    /// by its mirror image, in which case the flag is `true` and the
+    /// animation must be played mirrored. Otherwise the nearest direction is used.
+    pub fn pick_animation(self, directions: &[Option<AnimationId>]) -> Option<(AnimationId, bool)> {
+        use std::f32::consts::{PI, TAU};
+
+        let count = directions.len();
+        if count == 0 {
+            return None;
+        }
+        let idx = self.to_sector(count);
+        if let Some(animation) = directions[idx] {
+            return Some((animation, false));
+        }
+
+        let step = TAU / count as f32;
+        let mirrored = CharacterLook(PI - idx as f32 * step).to_sector(count);
+        if let Some(animation) = directions[mirrored] {
+            return Some((animation, true));
+        }
+
+        // Try the side, that the look leans to, first
+        let lean = (self.0 - idx as f32 * step + PI).rem_euclid(TAU) - PI;
+        (1..=count / 2)
+            .flat_map(|offset| {
+                let cw = (idx + offset) % count;
+                let ccw = (idx + count - offset) % count;
+                if lean >= 0.0 { [cw, ccw] } else { [ccw, cw] }
+            })
+            .find_map(|idx| directions[idx])
+            .map(|animation| (animation, false))
+    }
 }
@@ -4,7 +4,7 @@ mod sequencer;
 
 use std::any::TypeId;
 
-use egui::{Button, ComboBox, DragValue, Label, Modal, Response, WidgetText, vec2};
+use egui::{Button, ComboBox, DragValue, Label, Modal, Response, vec2};
 use egui::{Ui, Widget};
 use macroquad::math::Vec2;
 
@@ -16,7 +16,7 @@ use save_ui::*;
 use sequencer::*;
 
 use crate::animation::Animation;
-use crate::{AnimationPlay, AttackBox, CLIP_TYPES, CharacterLook, Resources};
+use crate::{AnimationId, AnimationPlay, AttackBox, CLIP_TYPES, CharacterLook, Resources};
 
 pub struct AnimationEdit {
     pub playback: Entity,
@@ -92,6 +92,8 @@ impl AnimationEdit {
         ui.horizontal(|ui| {
             animation_load_ui(ui, resources, play.animation);
         });
+        let mut anim_ids = resources.animations.keys().copied().collect::<Vec<_>>();
+        anim_ids.sort_by_key(|id| id.as_str());
         let anim = resources
             .animations
             .entry(play.animation)
@@ -100,9 +102,25 @@ impl AnimationEdit {
             ui.drag_angle(&mut look.0);
             ui.label("look");
         });
-        enum_select(ui, "animation_id", "animation", &mut play.animation);
+        animation_select(ui, &anim_ids, &mut play.animation);
         ui.checkbox(&mut anim.is_looping, "is looping");
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut anim.crossfade)
+                    .range(0.0..=1.0)
+                    .speed(0.01),
+            );
+            ui.label("crossfade");
+        });
         ui.checkbox(&mut play.pause, "Pause");
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut play.speed)
+                    .range(-4.0..=4.0)
+                    .speed(0.05),
+            );
+            ui.label("speed");
+        });
 
         let poen_global_offset_button = Button::new("Global offset");
         let global_offset_resp =
@@ -121,7 +139,8 @@ impl AnimationEdit {
             if let Some((kind, track_id)) = self.selected_track
                 && add_clip
             {
-                anim.add_clip(kind, track_id, play.cursor, 500);
+                let pos = play.position(anim);
+                anim.add_clip(kind, track_id, pos, 500);
             }
 
             let delete_track = delete_pressed && !shift_down;
@@ -147,16 +166,18 @@ impl AnimationEdit {
             }
         });
 
+        let mut cursor_pos = play.position(anim);
         Sequencer {
             state: &mut self.sequencer_state,
-            anim,
-            cursor_pos: &mut play.cursor,
+            anim: &mut *anim,
+            cursor_pos: &mut cursor_pos,
             size: egui::vec2(500.0, 200.0),
             tf: &mut self.tf,
             selected_clip: &mut self.selected_clip,
             selected_track: &mut self.selected_track,
         }
         .ui(ui);
+        play.seek(cursor_pos, anim);
     }
 
     fn track_creation_modal(&mut self, anim: &mut Animation, ui: &mut Ui) {
@@ -271,23 +292,25 @@ fn selected_clip_ui(
     });
 }
 
-fn enum_select<T>(
-    ui: &mut Ui,
-    id_salt: impl std::hash::Hash,
-    label: impl Into<WidgetText>,
-    current_value: &mut T,
-) where
-    T: Copy + PartialEq,
-    T: strum::VariantArray,
-    for<'a> &'a T: Into<&'static str>,
-{
-    let current_text: &'static str = (&*current_value).into();
-    ComboBox::new(id_salt, label)
-        .selected_text(current_text)
+/// Pick one of the loaded animations or name a new one
+fn animation_select(ui: &mut Ui, anim_ids: &[AnimationId], current: &mut AnimationId) {
+    ComboBox::new("animation_id", "animation")
+        .selected_text(current.as_str())
         .show_ui(ui, |ui| {
-            for selected_value in T::VARIANTS {
-                let text: &'static str = selected_value.into();
-                ui.selectable_value(current_value, *selected_value, text);
+            for anim_id in anim_ids {
+                ui.selectable_value(current, *anim_id, anim_id.as_str());
             }
         });
+
+    // Keep the edited name around, so we only intern the final one
+    let id = ui.id().with("new_animation_id");
+    let mut name = ui.data_mut(|x| x.get_temp::<String>(id).unwrap_or_default());
+    ui.horizontal(|ui| {
+        ui.label("new animation");
+        if ui.text_edit_singleline(&mut name).lost_focus() && !name.is_empty() {
+            *current = AnimationId::new(&name);
+            name.clear();
+        }
+    });
+    ui.data_mut(|x| x.insert_temp(id, name));
 }
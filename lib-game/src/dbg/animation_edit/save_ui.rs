@@ -8,9 +8,8 @@ use crate::Resources;
 use crate::animation::Animation;
 use hashbrown::HashMap;
 use lib_asset::animation_manifest::AnimationId;
-use log::{error, info, warn};
+use log::{error, info};
 use rfd::FileDialog;
-use strum::VariantArray;
 
 pub fn load_anim_pack_ui(resources: &mut Resources) {
     let src = FileDialog::new()
@@ -80,18 +79,12 @@ pub fn save_anim_pack_ui(resources: &mut Resources) {
     let pack_id_name = filename.to_pascal_case();
 
     let mut output = HashMap::new();
-    for anim_id in AnimationId::VARIANTS {
-        let anim_id_name: &'static str = anim_id.into();
-        if !anim_id_name.starts_with(&pack_id_name) {
+    for (anim_id, anim) in &resources.animations {
+        if !anim_id.as_str().starts_with(&pack_id_name) {
             info!("Skipping {anim_id:?}");
             continue;
         }
-
-        let Some(anim) = resources.animations.get(anim_id) else {
-            warn!("Skipping {anim_id:?}: not loaded");
-            continue;
-        };
-        info!("Adding {anim_id_name}");
+        info!("Adding {anim_id}");
         output.insert(*anim_id, anim.to_manifest(resources));
     }
 
@@ -103,7 +96,7 @@ pub fn save_anim_pack_ui(resources: &mut Resources) {
 
 pub fn animation_load_ui(ui: &mut Ui, resources: &mut Resources, current_anim_id: AnimationId) {
     if ui.button("Save").clicked() {
-        let fname: &'static str = current_anim_id.into();
+        let fname = current_anim_id.as_str();
         let dst = FileDialog::new()
             .set_title("Save animation")
             .set_file_name(format!("{fname}.json"))
@@ -114,7 +107,7 @@ pub fn animation_load_ui(ui: &mut Ui, resources: &mut Resources, current_anim_id
         }
     }
     if ui.button("Load").clicked() {
-        let fname: &'static str = current_anim_id.into();
+        let fname = current_anim_id.as_str();
         let src = FileDialog::new()
             .set_title("Load animation")
             .set_file_name(format!("{fname}.json"))
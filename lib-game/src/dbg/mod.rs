@@ -136,6 +136,28 @@ impl DebugStuff {
                     Err(e) => error!("{e:#}"),
                 }
             }
+            "ts" => {
+                if cmd.args.is_empty() {
+                    error!("Not enough args");
+                    return;
+                }
+
+                match cmd.args[0].parse::<f32>() {
+                    Ok(x) => app.resources.time_scale.set_scale(x),
+                    Err(e) => error!("{e:#}"),
+                }
+            }
+            "hitstop" => {
+                if cmd.args.is_empty() {
+                    error!("Not enough args");
+                    return;
+                }
+
+                match cmd.args[0].parse::<f32>() {
+                    Ok(x) => app.resources.time_scale.hitstop(x),
+                    Err(e) => error!("{e:#}"),
+                }
+            }
             unmatched => {
                 if !game.handle_command(app, &cmd) {
                     error!("Unknown command: {unmatched:?}");
@@ -153,6 +175,11 @@ impl DebugStuff {
 
         dump!("Dt: {:.2}", app.accumelated_time);
         dump!("FPS: {:?}", get_fps());
+        dump!("Time scale: {:.2}", app.resources.time_scale.scale());
+        dump!(
+            "Hitstop: {:.3}",
+            app.resources.time_scale.hitstop_remaining()
+        );
         dump!("Entities: {ent_count}");
         self.dump_archetypes(app);
         GLOBAL_DUMP.lock();
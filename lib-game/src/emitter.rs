@@ -0,0 +1,184 @@
+use hecs::{CommandBuffer, EntityBuilder, World};
+use lib_asset::{Aim, BulletPattern, Symbol, Volley, level::CharacterDef};
+use log::warn;
+
+use crate::{
+    CharacterLook, Faction, Game, LocalTimeScale, Resources, SpawnedBy, Transform, collect_targets,
+    local_dt, nearest_hostile,
+};
+
+/// Fires bullets according to a [BulletPattern]. Emitters work
+/// on any entity with a [Transform] and a [Faction], so they can
+/// be attached to both enemies and level objects. The spawned
+/// bullets belong to the faction of the emitter.
+///
+/// The emitter state only advances by the game `dt`, so
+/// patterns replay the same way every time.
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter {
+    pub pattern: Symbol,
+    /// Inactive emitters do not fire and keep their progress
+    pub active: bool,
+    /// The forward direction for entities without a [CharacterLook]
+    pub look_angle: f32,
+    volley: usize,
+    repeat: u32,
+    timer: f32,
+    done: bool,
+}
+
+impl Emitter {
+    pub fn new(pattern: Symbol, look_angle: f32) -> Self {
+        Self {
+            pattern,
+            active: true,
+            look_angle,
+            volley: 0,
+            repeat: 0,
+            timer: 0.0,
+            done: false,
+        }
+    }
+
+    /// Start the pattern from the beginning
+    pub fn restart(&mut self) {
+        *self = Emitter {
+            active: self.active,
+            ..Emitter::new(self.pattern, self.look_angle)
+        };
+    }
+
+    /// Whether a non-looping pattern has been fired completely
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Advance the emitter by `dt` and call `fire` for every
+    /// shot that is due with its volley and repeat.
+    pub fn tick(&mut self, dt: f32, pattern: &BulletPattern, mut fire: impl FnMut(&Volley, u32)) {
+        if !self.active || self.done {
+            return;
+        }
+
+        while !self.done && self.timer <= 0.0 {
+            let volley = &pattern.volleys[self.volley];
+            fire(volley, self.repeat);
+
+            self.repeat += 1;
+            if self.repeat < volley.repeat {
+                self.timer += volley.interval;
+                continue;
+            }
+
+            self.repeat = 0;
+            self.timer += volley.cooldown;
+            self.volley += 1;
+            if self.volley == pattern.volleys.len() {
+                self.volley = 0;
+                self.done = !pattern.looping;
+            }
+        }
+        self.timer -= dt;
+    }
+}
+
+pub(crate) fn update<G: Game>(
+    dt: f32,
+    world: &mut World,
+    resources: &Resources,
+    cmds: &mut CommandBuffer,
+    game: &G,
+) {
+    let targets = collect_targets(world);
+    let mut shots = Vec::new();
+
+    for (ent, (tf, faction, emitter, look, time_scale)) in world.query_mut::<(
+        &Transform,
+        &Faction,
+        &mut Emitter,
+        Option<&CharacterLook>,
+        Option<&LocalTimeScale>,
+    )>() {
+        let Some(pattern) = resources.bullet_patterns.get(&emitter.pattern) else {
+            warn!("No bullet pattern {:?}", emitter.pattern);
+            emitter.active = false;
+            continue;
+        };
+        let forward = look.map(|x| x.0).unwrap_or(emitter.look_angle);
+
+        emitter.tick(local_dt(dt, time_scale), pattern, |volley, repeat| {
+            let base_angle = match volley.aim {
+                Aim::Forward => forward,
+                Aim::Target => nearest_hostile(resources, &targets, ent, tf.pos, *faction)
+                    .map(|target| (target.pos - tf.pos).to_angle())
+                    .unwrap_or(forward),
+            };
+            for angle in volley.shape.angles(repeat) {
+                let def = CharacterDef {
+                    look_angle: base_angle + angle,
+                    pos: tf.pos,
+                    info: volley.projectile,
+                };
+                shots.push((def, *faction, ent));
+            }
+        });
+    }
+
+    for (def, faction, owner) in shots {
+        let mut builder = EntityBuilder::new();
+        game.init_character(resources, &mut builder, def);
+        builder.add(faction);
+        builder.add(SpawnedBy { owner });
+        cmds.spawn(builder.build());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_asset::{Aim, BulletPattern, Symbol, Volley, VolleyShape, level::CharacterInfo};
+
+    use super::Emitter;
+
+    fn volley(repeat: u32, interval: f32, cooldown: f32) -> Volley {
+        Volley {
+            shape: VolleyShape::Ring { count: 4 },
+            aim: Aim::Forward,
+            projectile: CharacterInfo::BasicBullet { behaviour: None },
+            repeat,
+            interval,
+            cooldown,
+        }
+    }
+
+    fn record(pattern: &BulletPattern, ticks: usize) -> Vec<(usize, u32)> {
+        let mut emitter = Emitter::new(Symbol::new("test"), 0.0);
+        let mut shots = Vec::new();
+        for tick in 0..ticks {
+            emitter.tick(0.25, pattern, |_, repeat| shots.push((tick, repeat)));
+        }
+        shots
+    }
+
+    #[test]
+    fn test_emitter_timing() {
+        let pattern = BulletPattern {
+            volleys: vec![volley(3, 0.25, 0.5), volley(1, 0.0, 0.5)],
+            looping: false,
+        };
+
+        assert_eq!(record(&pattern, 10), vec![(0, 0), (1, 1), (2, 2), (4, 0)]);
+    }
+
+    #[test]
+    fn test_emitter_replays() {
+        let pattern = BulletPattern {
+            volleys: vec![volley(2, 0.25, 0.5)],
+            looping: true,
+        };
+
+        // One loop takes 3 ticks and fires twice
+        let shots = record(&pattern, 39);
+        assert_eq!(shots, record(&pattern, 39));
+        assert_eq!(shots.len(), 26);
+    }
+}
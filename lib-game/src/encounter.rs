@@ -0,0 +1,444 @@
+use hecs::{CommandBuffer, Entity, EntityBuilder, World};
+use lib_asset::level::{AreaDef, CharacterDef, EncounterDef, SpawnZoneDef};
+use lib_asset::{AssetKey, SpawnGroup, Symbol, WaveComplete, WaveScript};
+use lib_col::Shape;
+use log::warn;
+use macroquad::prelude::*;
+
+use crate::{
+    BodyTag, Game, LocalTimeScale, NavGrid, Resources, Sprite, Transform, col_group, col_query,
+    emit_event, local_dt,
+};
+
+/// Emitted once the player enters an arena
+#[derive(Debug, Clone, Copy)]
+pub struct EncounterStarted {
+    pub encounter: Symbol,
+}
+
+/// Emitted once a wave of an encounter is complete
+#[derive(Debug, Clone, Copy)]
+pub struct WaveCompleted {
+    pub encounter: Symbol,
+    pub wave: usize,
+}
+
+/// Emitted once the last wave of an encounter is complete
+#[derive(Debug, Clone, Copy)]
+pub struct EncounterCompleted {
+    pub encounter: Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncounterState {
+    /// Waiting for the player to step in
+    Waiting,
+    Running,
+    Done,
+}
+
+/// An arena fight, see [EncounterDef]
+#[derive(Debug, Clone)]
+pub struct Encounter {
+    pub name: Symbol,
+    pub script: AssetKey,
+    pub zones: Vec<SpawnZoneDef>,
+    state: EncounterState,
+    waves: WaveRunner,
+    /// The spawned enemies, that might be still alive
+    members: Vec<Entity>,
+    spawn_count: u32,
+}
+
+impl Encounter {
+    pub fn new(def: &EncounterDef, script: AssetKey) -> Self {
+        Self {
+            name: def.name,
+            script,
+            zones: def.spawn_zones.clone(),
+            state: EncounterState::Waiting,
+            waves: WaveRunner::new(),
+            members: Vec::new(),
+            spawn_count: 0,
+        }
+    }
+
+    pub fn state(&self) -> EncounterState {
+        self.state
+    }
+
+    /// The index of the current wave
+    pub fn wave(&self) -> usize {
+        self.waves.wave
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.members.len()
+    }
+
+    fn spawn_pos(&mut self, group: &SpawnGroup) -> Vec2 {
+        let zone = group
+            .zone
+            .and_then(|name| {
+                let zone = self.zones.iter().find(|zone| zone.name == name);
+                if zone.is_none() {
+                    warn!("Encounter {:?} has no spawn zone {name:?}", self.name);
+                }
+                zone
+            })
+            .unwrap_or(&self.zones[self.spawn_count as usize % self.zones.len()]);
+        let pos = spawn_point(&zone.area, self.spawn_count);
+        self.spawn_count += 1;
+        pos
+    }
+}
+
+/// A part of a door, that locks an arena. Doors are built out of
+/// tiles, that act like walls while locked, for the navigation too.
+#[derive(Debug, Clone, Copy)]
+pub struct Door {
+    pub encounter: Symbol,
+    pub locked: bool,
+    sprite: Option<Sprite>,
+}
+
+/// The progress of a [WaveScript]
+#[derive(Debug, Clone, Default)]
+struct WaveRunner {
+    wave: usize,
+    /// Time since the start of the wave, including the delay
+    elapsed: f32,
+    /// How many enemies of each group have been spawned
+    spawned: Vec<u32>,
+}
+
+impl WaveRunner {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn is_done(&self, script: &WaveScript) -> bool {
+        self.wave >= script.waves.len()
+    }
+
+    /// Advance the wave. `alive` is the amount of the wave's enemies,
+    /// that are still alive. Returns the index of the completed wave.
+    fn tick(
+        &mut self,
+        dt: f32,
+        script: &WaveScript,
+        alive: usize,
+        mut spawn: impl FnMut(&SpawnGroup, u32),
+    ) -> Option<usize> {
+        let wave = script.waves.get(self.wave)?;
+        self.spawned.resize(wave.groups.len(), 0);
+
+        let time = self.elapsed - wave.delay;
+        let all_spawned = wave
+            .groups
+            .iter()
+            .zip(&self.spawned)
+            .all(|(group, spawned)| *spawned >= group.count);
+        let complete = match wave.complete {
+            WaveComplete::AllDead => alive == 0,
+            WaveComplete::After { seconds } => time >= seconds,
+        };
+        if all_spawned && complete {
+            let done = self.wave;
+            self.wave += 1;
+            self.elapsed = 0.0;
+            self.spawned.clear();
+            return Some(done);
+        }
+
+        if time >= 0.0 {
+            for (group, spawned) in wave.groups.iter().zip(&mut self.spawned) {
+                while *spawned < group.count && *spawned as f32 * group.interval <= time {
+                    spawn(group, *spawned);
+                    *spawned += 1;
+                }
+            }
+        }
+        self.elapsed += dt;
+
+        None
+    }
+}
+
+/// A point inside the area. The consecutive points
+/// are spread evenly (it's the Halton sequence).
+fn spawn_point(area: &AreaDef, idx: u32) -> Vec2 {
+    fn halton(mut idx: u32, base: u32) -> f32 {
+        let mut fraction = 1.0;
+        let mut result = 0.0;
+        while idx > 0 {
+            fraction /= base as f32;
+            result += fraction * (idx % base) as f32;
+            idx /= base;
+        }
+        result
+    }
+
+    area.pos + area.size * vec2(halton(idx + 1, 2), halton(idx + 1, 3))
+}
+
+/// Spawn the encounter triggers and the doors of the level.
+/// `door_sprite` is how the locked door tiles look.
+pub(crate) fn spawn_encounters(
+    world: &mut World,
+    resources: &Resources,
+    door_sprite: Option<Sprite>,
+) {
+    const TILE_SIDE_F32: f32 = lib_asset::level::TILE_SIDE as f32;
+
+    for def in &resources.level.encounters {
+        let Some(script) = resources.wave_scripts.resolve(def.script.as_str()) else {
+            warn!("Encounter {:?}: script not loaded", def.name);
+            continue;
+        };
+        world.spawn((
+            Transform::from_pos(def.trigger.center()),
+            Encounter::new(def, script),
+            col_query::Pickup::new(
+                Shape::Rect {
+                    width: def.trigger.size.x,
+                    height: def.trigger.size.y,
+                },
+                col_group::PLAYER,
+                col_group::NONE,
+            ),
+        ));
+
+        for door in &def.doors {
+            let tiles = (door.size / TILE_SIDE_F32)
+                .round()
+                .as_uvec2()
+                .max(UVec2::ONE);
+            for x in 0..tiles.x {
+                for y in 0..tiles.y {
+                    let pos = door.pos + (vec2(x as f32, y as f32) + 0.5) * TILE_SIDE_F32;
+                    world.spawn((
+                        Transform::from_pos(pos),
+                        Door {
+                            encounter: def.name,
+                            locked: false,
+                            sprite: door_sprite,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn update<G: Game>(
+    dt: f32,
+    world: &mut World,
+    resources: &mut Resources,
+    cmds: &mut CommandBuffer,
+    game: &G,
+) {
+    let mut spawns = Vec::new();
+    let mut lock_changes = Vec::new();
+
+    for (ent, (encounter, trigger, time_scale)) in world
+        .query::<(&mut Encounter, &col_query::Pickup, Option<&LocalTimeScale>)>()
+        .iter()
+    {
+        let Some(script) = resources.wave_scripts.get(encounter.script) else {
+            continue;
+        };
+        match encounter.state {
+            EncounterState::Waiting if trigger.has_collided() => {
+                encounter.state = EncounterState::Running;
+                lock_changes.push((encounter.name, true));
+                emit_event(
+                    cmds,
+                    EncounterStarted {
+                        encounter: encounter.name,
+                    },
+                );
+            }
+            EncounterState::Running => {
+                encounter.members.retain(|member| world.contains(*member));
+                let mut to_spawn = Vec::new();
+                let completed = encounter.waves.tick(
+                    local_dt(dt, time_scale),
+                    script,
+                    encounter.members.len(),
+                    |group, _| to_spawn.push(*group),
+                );
+                for group in to_spawn {
+                    let pos = encounter.spawn_pos(&group);
+                    spawns.push((ent, pos, group));
+                }
+                if let Some(wave) = completed {
+                    emit_event(
+                        cmds,
+                        WaveCompleted {
+                            encounter: encounter.name,
+                            wave,
+                        },
+                    );
+                }
+                if encounter.waves.is_done(script) {
+                    encounter.state = EncounterState::Done;
+                    lock_changes.push((encounter.name, false));
+                    emit_event(
+                        cmds,
+                        EncounterCompleted {
+                            encounter: encounter.name,
+                        },
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for (encounter, pos, group) in spawns {
+        let mut builder = EntityBuilder::new();
+        game.init_character(
+            resources,
+            &mut builder,
+            CharacterDef {
+                look_angle: 0.0,
+                pos,
+                info: group.enemy,
+            },
+        );
+        let member = world.reserve_entity();
+        cmds.insert(member, builder.build());
+        if let Ok(mut encounter) = world.get::<&mut Encounter>(encounter) {
+            encounter.members.push(member);
+        }
+    }
+
+    for (name, locked) in lock_changes {
+        let mut cells = Vec::new();
+        for (ent, (door, tf)) in world.query_mut::<(&mut Door, &Transform)>() {
+            if door.encounter != name {
+                continue;
+            }
+            door.locked = locked;
+            cells.push(NavGrid::cell(tf.pos));
+            if !locked {
+                cmds.remove_one::<BodyTag>(ent);
+                cmds.remove_one::<Sprite>(ent);
+                continue;
+            }
+            cmds.insert_one(
+                ent,
+                BodyTag {
+                    groups: col_group::LEVEL,
+                    shape: Shape::Rect {
+                        width: lib_asset::level::TILE_SIDE as f32,
+                        height: lib_asset::level::TILE_SIDE as f32,
+                    },
+                },
+            );
+            if let Some(sprite) = door.sprite {
+                cmds.insert_one(ent, sprite);
+            }
+        }
+        resources.nav.set_blocked(cells, locked);
+    }
+}
+
+pub fn draw_encounters(world: &World, resources: &Resources) {
+    for def in &resources.level.encounters {
+        let trigger = def.trigger;
+        draw_rectangle_lines(
+            trigger.pos.x,
+            trigger.pos.y,
+            trigger.size.x,
+            trigger.size.y,
+            1.0,
+            ORANGE,
+        );
+        for zone in &def.spawn_zones {
+            let area = zone.area;
+            draw_rectangle_lines(area.pos.x, area.pos.y, area.size.x, area.size.y, 1.0, RED);
+        }
+    }
+    for (_, (tf, encounter)) in &mut world.query::<(&Transform, &Encounter)>() {
+        draw_text(
+            &format!(
+                "{:?} wave {} alive {}",
+                encounter.state,
+                encounter.wave(),
+                encounter.alive_count()
+            ),
+            tf.pos.x,
+            tf.pos.y,
+            8.0,
+            ORANGE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib_asset::level::{AreaDef, CharacterInfo};
+    use lib_asset::{SpawnGroup, WaveComplete, WaveDef, WaveScript};
+    use macroquad::math::vec2;
+
+    use super::{WaveRunner, spawn_point};
+
+    fn wave(count: u32, interval: f32, delay: f32, complete: WaveComplete) -> WaveDef {
+        WaveDef {
+            delay,
+            groups: vec![SpawnGroup {
+                enemy: CharacterInfo::Stabber {},
+                count,
+                interval,
+                zone: None,
+            }],
+            complete,
+        }
+    }
+
+    #[test]
+    fn test_waves() {
+        let script = WaveScript {
+            waves: vec![
+                wave(3, 0.25, 0.0, WaveComplete::AllDead),
+                wave(1, 0.0, 0.5, WaveComplete::After { seconds: 0.25 }),
+            ],
+        };
+        let mut runner = WaveRunner::new();
+        let mut spawned = Vec::new();
+        let mut alive = 0;
+        let mut events = Vec::new();
+        for tick in 0..12 {
+            // Everything dies right after spawning from tick 4 on
+            if tick >= 4 {
+                alive = 0;
+            }
+            let event = runner.tick(0.25, &script, alive, |_, idx| {
+                spawned.push((tick, idx));
+                alive += 1;
+            });
+            if let Some(wave) = event {
+                events.push((tick, wave));
+            }
+        }
+
+        assert_eq!(spawned, vec![(0, 0), (1, 1), (2, 2), (7, 0)]);
+        assert_eq!(events, vec![(4, 0), (8, 1)]);
+        assert!(runner.is_done(&script));
+    }
+
+    #[test]
+    fn test_spawn_point() {
+        let area = AreaDef {
+            pos: vec2(16.0, 16.0),
+            size: vec2(32.0, 32.0),
+        };
+        assert!(spawn_point(&area, 0).abs_diff_eq(vec2(32.0, 16.0 + 32.0 / 3.0), 1e-5));
+        for idx in 0..32 {
+            let pos = spawn_point(&area, idx);
+            assert!(pos.cmpge(area.pos).all() && pos.cmple(area.pos + area.size).all());
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use hecs::{CommandBuffer, Component, World};
+
+/// [EventTag] marks entities that are events.
+/// Events are spawned through the command buffer and live
+/// until the end of the next tick, so every system gets to
+/// see each event exactly once. To listen to an event, just
+/// query for its component.
+#[derive(Debug, Clone, Copy)]
+pub struct EventTag {
+    stale: bool,
+}
+
+pub fn emit_event(cmds: &mut CommandBuffer, event: impl Component) {
+    cmds.spawn((EventTag { stale: false }, event));
+}
+
+pub(crate) fn update_events(world: &mut World, cmds: &mut CommandBuffer) {
+    for (entity, tag) in world.query_mut::<&mut EventTag>() {
+        if tag.stale {
+            cmds.despawn(entity);
+        } else {
+            tag.stale = true;
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{CollisionSolver, DamageCooldown, Health, Team, col_query};
+use crate::{
+    CollisionSolver, DamageCooldown, Deflected, Faction, Health, HitStop, LocalTimeScale,
+    Resources, SpriteFlash, col_query, hit_target, local_dt,
+};
 
 use hecs::{CommandBuffer, World};
 
@@ -10,10 +13,12 @@ pub fn reset(world: &mut World) {
 }
 
 pub fn update_cooldown(dt: f32, world: &mut World) {
-    for (_, (cooldown, hp)) in world.query_mut::<(&mut DamageCooldown, &mut Health)>() {
+    for (_, (cooldown, hp, time_scale)) in
+        world.query_mut::<(&mut DamageCooldown, &mut Health, Option<&LocalTimeScale>)>()
+    {
         hp.is_invulnerable = hp.is_invulnerable || cooldown.remaining > 0.0;
         if cooldown.remaining > 0.0 {
-            cooldown.remaining -= dt;
+            cooldown.remaining -= local_dt(dt, time_scale);
         }
     }
 }
@@ -36,18 +41,58 @@ pub fn apply_damage(world: &mut World) {
     }
 }
 
-pub fn collect_damage(world: &mut World, col_solver: &CollisionSolver) {
-    let mut hp_query = world.query::<(&mut Health, &Team)>();
+/// Must run after the damage is applied, but before it is reset
+pub fn update_sprite_flash(dt: f32, world: &mut World) {
+    for (_, (flash, hp, time_scale)) in
+        world.query_mut::<(&mut SpriteFlash, &Health, Option<&LocalTimeScale>)>()
+    {
+        let dt = local_dt(dt, time_scale);
+        flash.time += dt;
+        flash.remaining = (flash.remaining - dt).max(0.0);
+        if hp.damage > 0 && !hp.is_invulnerable {
+            flash.remaining = flash.duration;
+        }
+    }
+}
+
+pub fn collect_damage(world: &mut World, resources: &Resources, col_solver: &CollisionSolver) {
+    let mut hp_query = world.query::<(&mut Health, &Faction)>();
     let mut hp_query = hp_query.view();
-    for (_, (damage_q, attack_team)) in &mut world.query::<(&col_query::Damage, &Team)>() {
+    let mut targets = Vec::new();
+    for (_, (damage_q, attack_faction, deflected, hitstop)) in &mut world.query::<(
+        &col_query::Damage,
+        &Faction,
+        Option<&Deflected>,
+        Option<&HitStop>,
+    )>() {
+        // An attack touching both the body and a hurtbox hits only once
+        targets.clear();
         for entity in col_solver.collisions_for(damage_q) {
-            let Some((health, target_team)) = hp_query.get_mut(*entity) else {
+            let Some(target) = hit_target(world, *entity) else {
+                continue;
+            };
+            if targets.contains(&target) {
+                continue;
+            }
+            targets.push(target);
+            if deflected.is_some_and(|x| x.by == target) {
+                continue;
+            }
+            let Some((health, target_faction)) = hp_query.get_mut(target) else {
                 continue;
             };
-            if *attack_team == *target_team {
+            let relation = resources
+                .factions
+                .relation(*attack_faction, *target_faction);
+            if !relation.can_damage() {
                 continue;
             }
             health.damage += 1;
+            if let Some(hitstop) = hitstop
+                && !health.is_invulnerable
+            {
+                resources.time_scale.hitstop(hitstop.0);
+            }
         }
     }
 }
@@ -59,3 +104,39 @@ pub fn despawn_on_zero_health(world: &mut World, cmds: &mut CommandBuffer) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hecs::World;
+    use lib_asset::gamecfg::sections;
+    use macroquad::prelude::*;
+
+    use super::update_sprite_flash;
+    use crate::{Health, SpriteFlash};
+
+    #[test]
+    fn test_sprite_flash() {
+        let cfg = sections::SpriteFlash {
+            color: [1.0, 0.0, 0.0, 1.0],
+            duration: 0.2,
+            blink_rate: 3.0,
+        };
+        let mut world = World::new();
+        let mut hp = Health::new(3);
+        hp.damage = 1;
+        let ent = world.spawn((SpriteFlash::new(&cfg), hp));
+
+        update_sprite_flash(0.1, &mut world);
+        let flash = *world.get::<&SpriteFlash>(ent).unwrap();
+        assert_eq!(flash.remaining, 0.2);
+        assert_eq!(flash.apply(WHITE, false), Color::new(1.0, 0.0, 0.0, 1.0));
+
+        world.get::<&mut Health>(ent).unwrap().damage = 0;
+        update_sprite_flash(0.1, &mut world);
+        let flash = *world.get::<&SpriteFlash>(ent).unwrap();
+        assert_eq!(flash.apply(WHITE, false), Color::new(1.0, 0.5, 0.5, 1.0));
+        // Half way through the blink the sprites are see-through
+        let faded = flash.apply(WHITE, true);
+        assert_eq!(faded.a, 0.25);
+    }
+}
@@ -11,7 +11,9 @@ pub struct InputModel {
     pub pause_requested: bool,
     pub fullscreen_toggle_requested: bool,
     pub attack_down: bool,
+    pub attack_pressed: bool,
     pub dash_pressed: bool,
+    pub block_down: bool,
     pub scroll_up: bool,
     pub scroll_down: bool,
     pub aim: Vec2,
@@ -32,16 +34,20 @@ impl InputModel {
         let pause_requested = is_key_pressed(KeyCode::Escape);
         let fullscreen_toggle_requested = is_key_pressed(KeyCode::F11);
         let attack_down = is_mouse_button_down(MouseButton::Left);
+        let attack_pressed = is_mouse_button_pressed(MouseButton::Left);
         let console_toggle_requested =
             is_key_pressed(KeyCode::GraveAccent) || is_key_pressed(KeyCode::Apostrophe);
         let scroll_up = is_key_down(KeyCode::PageUp);
         let scroll_down = is_key_down(KeyCode::PageDown);
         let dash_pressed = is_mouse_button_pressed(MouseButton::Right);
+        let block_down = is_key_down(KeyCode::LeftShift);
 
         Self {
             console_toggle_requested,
             attack_down,
+            attack_pressed,
             dash_pressed,
+            block_down,
             left_movement_down,
             right_movement_down,
             confirmation_detected,
@@ -54,4 +60,15 @@ impl InputModel {
             aim,
         }
     }
+
+    /// Combine with an input captured later. The presses from both
+    /// are kept, while the held buttons and the aim come from `later`.
+    pub fn merge(&self, later: &InputModel) -> InputModel {
+        InputModel {
+            confirmation_detected: self.confirmation_detected || later.confirmation_detected,
+            attack_pressed: self.attack_pressed || later.attack_pressed,
+            dash_pressed: self.dash_pressed || later.dash_pressed,
+            ..*later
+        }
+    }
 }
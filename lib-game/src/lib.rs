@@ -1,16 +1,29 @@
 mod animation;
 mod attack;
+mod audio;
+mod behaviour;
+mod boss;
 mod character;
 mod collisions;
 mod components;
+mod emitter;
+mod encounter;
+mod events;
 mod health;
 mod input;
+mod navigation;
+mod perception;
 mod projectile;
 mod render;
+mod squad;
+mod state_machine;
+mod targeting;
+mod time;
 
 #[cfg(feature = "dbg")]
 pub mod dbg;
 
+pub mod steering;
 pub mod sys;
 
 use std::path::{Path, PathBuf};
@@ -20,16 +33,28 @@ use hecs::EntityBuilder;
 
 pub use animation::*;
 pub use attack::*;
+pub use audio::*;
+pub use behaviour::*;
+pub use boss::*;
 pub use character::*;
 pub use collisions::*;
 pub use components::*;
+pub use emitter::*;
+pub use encounter::*;
+pub use events::*;
 pub use input::*;
 pub use lib_asset::animation_manifest::AnimationId;
 use lib_asset::animation_manifest::load_animation_manifest;
 pub use lib_asset::level::*;
 pub use lib_asset::*;
+pub use navigation::*;
+pub use perception::*;
 pub use projectile::*;
 pub use render::*;
+pub use squad::*;
+pub use state_machine::*;
+pub use targeting::*;
+pub use time::*;
 
 #[macro_export]
 #[cfg(feature = "dbg")]
@@ -179,6 +204,8 @@ pub struct App {
     clip_action_objects: HashMap<ClipActionObject, Entity>,
     pub world: World,
     cmds: CommandBuffer,
+    /// The input received during a hit-stop
+    held_input: Option<InputModel>,
 
     render_world: bool,
     #[allow(unused)]
@@ -189,6 +216,8 @@ impl App {
     pub async fn new(conf: &Conf) -> anyhow::Result<Self> {
         let mut resources = Resources::new();
         resources.cfg = load_game_cfg().await?;
+        resources.audio = AudioManager::new(Box::new(MacroquadAudio::new()));
+        resources.audio.configure(&resources.cfg.audio);
 
         Ok(Self {
             fullscreen: conf.fullscreen,
@@ -205,6 +234,7 @@ impl App {
             clip_action_objects: HashMap::new(),
             world: World::new(),
             cmds: CommandBuffer::new(),
+            held_input: None,
 
             render_world: true,
             freeze: false,
@@ -249,6 +279,7 @@ impl App {
             }
 
             self.game_present(real_dt, game);
+            self.resources.audio.update(real_dt);
 
             #[cfg(feature = "dbg")]
             debug.draw(&mut self);
@@ -276,10 +307,42 @@ impl App {
             .resolve(&level.map.atlas_image)
             .expect("Atlas not loaded");
 
+        for encounter in &level.encounters {
+            if self
+                .resources
+                .wave_scripts
+                .resolve(encounter.script.as_str())
+                .is_none()
+            {
+                self.resources
+                    .load_wave_script(encounter.script.as_str())
+                    .await;
+            }
+        }
+
+        // The characters can also come from the waves
+        let mut characters = level.characters.iter().map(|x| x.info).collect::<Vec<_>>();
+        for encounter in &level.encounters {
+            let script = self
+                .resources
+                .wave_scripts
+                .resolve(encounter.script.as_str())
+                .and_then(|key| self.resources.wave_scripts.get(key))
+                .unwrap();
+            let groups = script.waves.iter().flat_map(|wave| &wave.groups);
+            characters.extend(groups.map(|group| group.enemy));
+        }
+        for info in characters {
+            self.resources.load_character_assets(info).await;
+        }
+
         self.world.clear();
+        self.held_input = None;
+        self.resources.nav = NavGrid::new(&level.map);
         self.resources.level = level;
         self.spawn_tiles(atlas);
         self.spawn_characters(game);
+        self.spawn_encounters(atlas);
     }
 
     fn spawn_tiles(&mut self, atlas_key: AssetKey) {
@@ -302,9 +365,6 @@ impl App {
                     continue;
                 };
 
-                let tile_tex_x = (tile % tiles_in_x) as f32;
-                let tile_tex_y = (tile / tiles_in_x) as f32;
-
                 let tile_pos = vec2(tile_x as f32, tile_y as f32) * TILE_SIDE_F32
                     + Vec2::splat(TILE_SIDE_F32 / 2.0);
                 let ty = level.map.tiles[&tile].ty;
@@ -320,27 +380,35 @@ impl App {
                 builder.add_bundle((
                     Transform::from_pos(tile_pos),
                     ty,
-                    Sprite {
-                        layer: 0,
-                        texture: atlas_key,
-                        rect: Rect {
-                            x: (TILE_SIDE_F32 + map_def.atlas_spacing as f32) * tile_tex_x
-                                + map_def.atlas_margin as f32,
-                            y: (TILE_SIDE_F32 + map_def.atlas_spacing as f32) * tile_tex_y
-                                + map_def.atlas_margin as f32,
-                            w: TILE_SIDE_F32,
-                            h: TILE_SIDE_F32,
-                        },
-                        color: WHITE,
-                        sort_offset: 0.0,
-                        local_offset: Vec2::splat(-TILE_SIDE_F32 / 2.0),
-                    },
+                    tile_sprite(map_def, atlas_key, tiles_in_x, tile),
                 ));
                 self.world.spawn(builder.build());
             }
         }
     }
 
+    fn spawn_encounters(&mut self, atlas_key: AssetKey) {
+        let atlas = self.resources.textures.get(atlas_key).unwrap();
+        let map_def = &self.resources.level.map;
+        let (tiles_in_x, _) = get_tile_count_in_atlas(
+            atlas.width() as u32,
+            atlas.height() as u32,
+            map_def.atlas_margin,
+            map_def.atlas_spacing,
+        );
+        // Locked doors are drawn over the floor
+        let door_sprite = map_def
+            .tiles
+            .iter()
+            .find(|(_, tile)| tile.door)
+            .map(|(id, _)| *id)
+            .map(|tile| Sprite {
+                layer: 1,
+                ..tile_sprite(map_def, atlas_key, tiles_in_x, tile)
+            });
+        encounter::spawn_encounters(&mut self.world, &self.resources, door_sprite);
+    }
+
     fn spawn_characters<G: Game>(&mut self, game: &G) {
         for def in self.resources.level.characters.iter() {
             let mut builder = EntityBuilder::new();
@@ -359,10 +427,28 @@ impl App {
     }
 
     fn game_update<G: Game>(&mut self, input: &InputModel, game: &mut G) -> Option<AppState> {
-        game.input_phase(input, GAME_TICKRATE, &self.resources, &mut self.world);
-        projectile::ai(GAME_TICKRATE, &mut self.world);
+        let frozen = self.resources.time_scale.is_frozen();
+        let dt = self.resources.time_scale.advance(GAME_TICKRATE);
+
+        // Nothing happens during a hit-stop. The presses are held
+        // back till it ends, so they are not lost.
+        let held = self.held_input.take();
+        let input = held.map_or(*input, |held| held.merge(input));
+        if frozen {
+            self.held_input = Some(input);
+            return None;
+        }
+
+        events::update_events(&mut self.world, &mut self.cmds);
+        self.cmds.run_on(&mut self.world);
 
-        animation::update(GAME_TICKRATE, &mut self.world, &self.resources);
+        perception::update(dt, &mut self.world, &self.resources);
+        squad::update(dt, &mut self.world, &self.resources);
+        game.input_phase(&input, dt, &self.resources, &mut self.world);
+        projectile::ai(dt, &mut self.world, &self.resources);
+
+        animation::update(dt, &mut self.world, &self.resources);
+        animation::update_root_motion(dt, &mut self.world, &self.resources);
         animation::collect_clip_action_objects(&mut self.world, &mut self.clip_action_objects);
         animation::delete_clip_action_objects(
             &mut self.world,
@@ -376,6 +462,12 @@ impl App {
             &mut self.cmds,
             &self.clip_action_objects,
         );
+        animation::update_hurtboxes(
+            &mut self.world,
+            &self.resources,
+            &mut self.cmds,
+            &self.clip_action_objects,
+        );
         animation::update_spawned(
             &mut self.world,
             &self.resources,
@@ -383,6 +475,19 @@ impl App {
             game,
             &self.clip_action_objects,
         );
+        animation::update_sounds(
+            &mut self.world,
+            &self.resources,
+            &mut self.cmds,
+            &self.clip_action_objects,
+        );
+        animation::update_anim_events(
+            &mut self.world,
+            &self.resources,
+            &mut self.cmds,
+            &self.clip_action_objects,
+        );
+        emitter::update(dt, &mut self.world, &self.resources, &mut self.cmds, game);
         animation::update_draw_sprites(
             &mut self.world,
             &self.resources,
@@ -391,30 +496,48 @@ impl App {
         );
         health::reset(&mut self.world);
         animation::update_invulnerability(&mut self.world, &self.resources);
-        health::update_cooldown(GAME_TICKRATE, &mut self.world);
+        boss::update_invulnerability(&mut self.world);
+        animation::update_defense(&mut self.world, &self.resources);
+        health::update_cooldown(dt, &mut self.world);
 
         self.col_solver.import_colliders(&mut self.world);
         self.col_solver.export_kinematic_moves(&mut self.world);
 
-        game.plan_collision_queries(
-            GAME_TICKRATE,
-            &self.resources,
-            &mut self.world,
-            &mut self.cmds,
-        );
+        game.plan_collision_queries(dt, &self.resources, &mut self.world, &mut self.cmds);
         self.cmds.run_on(&mut self.world);
 
         self.col_solver.compute_collisions(&mut self.world);
 
-        health::collect_damage(&mut self.world, &self.col_solver);
+        attack::resolve_defense(
+            &mut self.world,
+            &self.resources,
+            &self.col_solver,
+            &mut self.cmds,
+        );
+        health::collect_damage(&mut self.world, &self.resources, &self.col_solver);
         health::apply_damage(&mut self.world);
+        health::update_sprite_flash(dt, &mut self.world);
         health::apply_cooldown(&mut self.world);
-        attack::update_grazing(GAME_TICKRATE, &mut self.world, &self.col_solver);
+        boss::update(&mut self.world, &self.resources, &mut self.cmds);
+        attack::update_grazing(dt, &mut self.world, &self.resources, &self.col_solver);
         health::despawn_on_zero_health(&mut self.world, &mut self.cmds);
-        projectile::despawn_on_hit(&mut self.world, &mut self.cmds);
+        projectile::bounce(&mut self.world);
+        projectile::despawn_on_hit(
+            &mut self.world,
+            &self.resources,
+            &self.col_solver,
+            &mut self.cmds,
+        );
+        encounter::update(
+            dt,
+            &mut self.world,
+            &mut self.resources,
+            &mut self.cmds,
+            game,
+        );
 
         let new_state = game.update(
-            GAME_TICKRATE,
+            dt,
             &self.resources,
             &mut self.world,
             &self.col_solver,
@@ -504,6 +627,16 @@ pub struct Resources {
     pub cfg: GameCfg,
     pub resolver: FsResolver,
     pub level: LevelDef,
+    pub nav: NavGrid,
+    pub factions: FactionTable,
+    pub movesets: AssetContainer<Moveset>,
+    pub bullet_patterns: HashMap<Symbol, BulletPattern>,
+    pub enemy_kinds: AssetContainer<EnemyKind>,
+    pub bosses: AssetContainer<BossDef>,
+    pub state_machines: AssetContainer<StateMachine>,
+    pub wave_scripts: AssetContainer<WaveScript>,
+    pub time_scale: TimeScale,
+    pub audio: AudioManager,
     pub animations: HashMap<AnimationId, Animation>,
     pub textures: AssetContainer<Texture2D>,
     pub fonts: AssetContainer<Font>,
@@ -515,6 +648,16 @@ impl Resources {
             cfg: GameCfg::default(),
             resolver: FsResolver::new(),
             level: LevelDef::default(),
+            nav: NavGrid::default(),
+            factions: FactionTable::default(),
+            movesets: AssetContainer::new(),
+            bullet_patterns: HashMap::new(),
+            enemy_kinds: AssetContainer::new(),
+            bosses: AssetContainer::new(),
+            state_machines: AssetContainer::new(),
+            wave_scripts: AssetContainer::new(),
+            time_scale: TimeScale::new(),
+            audio: AudioManager::new(Box::new(NullAudio)),
             animations: HashMap::new(),
             textures: AssetContainer::new(),
             fonts: AssetContainer::new(),
@@ -537,6 +680,109 @@ impl Resources {
         self.fonts.insert(src_path, font)
     }
 
+    /// Only PCM WAV files are supported
+    pub async fn load_sound(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        self.load_sound_clip(path, false).await
+    }
+
+    /// Loads a sound for [AudioManager::play_music]. Only PCM WAV files are supported.
+    pub async fn load_music(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        self.load_sound_clip(path, true).await
+    }
+
+    async fn load_sound_clip(&mut self, path: impl AsRef<Path>, music: bool) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let path = path.to_string_lossy();
+        let bytes = load_file(&path).await.unwrap();
+        let length =
+            wav_length(&bytes).unwrap_or_else(|| panic!("{src_path:?} is not a PCM WAV file"));
+        let sound = macroquad::audio::load_sound_from_bytes(&bytes)
+            .await
+            .unwrap();
+        self.audio
+            .insert(src_path, SoundClip { length, music }, sound)
+    }
+
+    pub async fn load_factions(&mut self, path: impl AsRef<Path>) {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        self.factions = load_faction_table(&path).await.unwrap();
+    }
+
+    pub async fn load_moveset(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let moveset = load_moveset(&path).await.unwrap();
+        self.movesets.insert(src_path, moveset)
+    }
+
+    /// The bullet patterns must be loaded first
+    pub async fn load_enemy_kind(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let mut kind = load_enemy_kind(&path).await.unwrap();
+        // Checked once here, as the aggro keeps turning the emitter back on
+        if let Some(pattern) = kind
+            .emitter
+            .filter(|pattern| !self.bullet_patterns.contains_key(pattern))
+        {
+            warn!("{src_path:?}: No bullet pattern {pattern:?}, the enemy won't fire");
+            kind.emitter = None;
+        }
+        self.enemy_kinds.insert(src_path, kind)
+    }
+
+    /// Also loads the enemy kinds of the boss phases
+    pub async fn load_boss(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let boss = load_boss(&path).await.unwrap();
+        for phase in &boss.phases {
+            if self.enemy_kinds.resolve(phase.kind.as_str()).is_none() {
+                self.load_enemy_kind(phase.kind.as_str()).await;
+            }
+        }
+        self.bosses.insert(src_path, boss)
+    }
+
+    /// Loads the assets a character refers to, unless they are loaded already
+    pub async fn load_character_assets(&mut self, info: CharacterInfo) {
+        match info {
+            CharacterInfo::Scripted { kind }
+                if self.enemy_kinds.resolve(kind.as_str()).is_none() =>
+            {
+                self.load_enemy_kind(kind.as_str()).await;
+            }
+            CharacterInfo::Boss { boss } if self.bosses.resolve(boss.as_str()).is_none() => {
+                self.load_boss(boss.as_str()).await;
+            }
+            _ => (),
+        }
+    }
+
+    pub async fn load_state_machine(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let machine = load_state_machine(&path).await.unwrap();
+        self.state_machines.insert(src_path, machine)
+    }
+
+    pub async fn load_wave_script(&mut self, path: impl AsRef<Path>) -> AssetKey {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let script = load_wave_script(&path).await.unwrap();
+        self.wave_scripts.insert(src_path, script)
+    }
+
+    /// **ADDITIVLY** loads a bullet pattern pack
+    pub async fn load_bullet_patterns(&mut self, path: impl AsRef<Path>) {
+        let src_path = path.as_ref();
+        let path = self.resolver.get_path(AssetRoot::Assets, src_path);
+        let patterns = load_bullet_patterns(&path).await.unwrap();
+        self.bullet_patterns.extend(patterns);
+    }
+
     /// **ADDITIVLY** loads an animations pack
     pub async fn load_animation_pack(&mut self, path: impl AsRef<Path>) {
         let src_path = path.as_ref();
@@ -555,6 +801,31 @@ impl Default for Resources {
     }
 }
 
+fn tile_sprite(map_def: &MapDef, atlas_key: AssetKey, tiles_in_x: u32, tile: u32) -> Sprite {
+    const TILE_SIDE_F32: f32 = TILE_SIDE as f32;
+
+    let tile_tex_x = (tile % tiles_in_x) as f32;
+    let tile_tex_y = (tile / tiles_in_x) as f32;
+    Sprite {
+        layer: 0,
+        texture: atlas_key,
+        rect: Rect {
+            x: (TILE_SIDE_F32 + map_def.atlas_spacing as f32) * tile_tex_x
+                + map_def.atlas_margin as f32,
+            y: (TILE_SIDE_F32 + map_def.atlas_spacing as f32) * tile_tex_y
+                + map_def.atlas_margin as f32,
+            w: TILE_SIDE_F32,
+            h: TILE_SIDE_F32,
+        },
+        color: WHITE,
+        sort_offset: 0.0,
+        local_offset: Vec2::splat(-TILE_SIDE_F32 / 2.0),
+        scale: Vec2::ONE,
+        flip_x: false,
+        flip_y: false,
+    }
+}
+
 fn get_tile_count_in_atlas(
     mut atlas_width: u32,
     mut atlas_height: u32,
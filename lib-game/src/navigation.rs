@@ -0,0 +1,573 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use hecs::World;
+use lib_asset::level::{MapDef, TILE_SIDE, TileTy};
+use lib_col::Shape;
+use macroquad::prelude::*;
+
+use crate::{Character, Resources};
+
+const TILE_SIDE_F32: f32 = TILE_SIDE as f32;
+const NEIGHBOURS: [IVec2; 8] = [
+    ivec2(1, 0),
+    ivec2(-1, 0),
+    ivec2(0, 1),
+    ivec2(0, -1),
+    ivec2(1, 1),
+    ivec2(1, -1),
+    ivec2(-1, 1),
+    ivec2(-1, -1),
+];
+/// How close a character must get to a waypoint to go for the next one
+const WAYPOINT_REACH: f32 = 1.0;
+
+/// The navigation grid of a level. Each cell is a tile of the map.
+/// The grid is built when the level is loaded. Only the locked
+/// doors can change it later, see [NavGrid::set_blocked].
+///
+/// Each cell stores its clearance: how many tiles around
+/// it are free of walls. Characters bigger than a tile need
+/// more clearance to walk through a cell.
+#[derive(Debug, Default)]
+pub struct NavGrid {
+    width: i32,
+    height: i32,
+    /// The walls of the map
+    walls: Vec<bool>,
+    /// The cells, that act like walls for now
+    blocked: Vec<bool>,
+    /// `-1` for walls
+    clearance: Vec<i32>,
+}
+
+impl NavGrid {
+    pub fn new(map: &MapDef) -> Self {
+        let width = map.width as i32;
+        let height = map.height as i32;
+        let walls = map
+            .tilemap
+            .iter()
+            .map(|tile| tile.is_some_and(|tile| map.tiles[&tile].ty == TileTy::Wall))
+            .collect::<Vec<_>>();
+
+        let mut nav = NavGrid {
+            width,
+            height,
+            blocked: vec![false; walls.len()],
+            walls,
+            clearance: Vec::new(),
+        };
+        nav.compute_clearance();
+        nav
+    }
+
+    /// Make the cells act like walls or free them again
+    pub fn set_blocked(&mut self, cells: impl IntoIterator<Item = IVec2>, blocked: bool) {
+        for cell in cells {
+            if let Some(idx) = self.index(cell) {
+                self.blocked[idx] = blocked;
+            }
+        }
+        self.compute_clearance();
+    }
+
+    fn compute_clearance(&mut self) {
+        let (width, height) = (self.width, self.height);
+
+        // Chebyshev distance to the closest wall. Everything outside
+        // of the map counts as a wall.
+        let mut dist = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let idx = (x + y * width) as usize;
+                if self.walls[idx] || self.blocked[idx] {
+                    dist.push(0);
+                } else {
+                    dist.push((x + 1).min(y + 1).min(width - x).min(height - y));
+                }
+            }
+        }
+
+        let mut relax = |x: i32, y: i32, around: [IVec2; 4]| {
+            let idx = (x + y * width) as usize;
+            for off in around {
+                let (nx, ny) = (x + off.x, y + off.y);
+                if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                dist[idx] = dist[idx].min(dist[(nx + ny * width) as usize] + 1);
+            }
+        };
+        let before = [ivec2(-1, 0), ivec2(-1, -1), ivec2(0, -1), ivec2(1, -1)];
+        let after = [ivec2(1, 0), ivec2(1, 1), ivec2(0, 1), ivec2(-1, 1)];
+        for y in 0..height {
+            for x in 0..width {
+                relax(x, y, before);
+            }
+        }
+        for y in (0..height).rev() {
+            for x in (0..width).rev() {
+                relax(x, y, after);
+            }
+        }
+
+        self.clearance = dist.into_iter().map(|x| x - 1).collect();
+    }
+
+    /// The clearance a character with the `shape` needs
+    pub fn clearance_for(shape: Shape) -> i32 {
+        let half_extent = match shape {
+            Shape::Rect { width, height } => width.max(height) / 2.0,
+            Shape::Circle { radius } => radius,
+        };
+        ((TILE_SIDE_F32 / 2.0 + half_extent) / TILE_SIDE_F32).ceil() as i32 - 1
+    }
+
+    pub fn cell(pos: Vec2) -> IVec2 {
+        (pos / TILE_SIDE_F32).floor().as_ivec2()
+    }
+
+    pub fn cell_center(cell: IVec2) -> Vec2 {
+        cell.as_vec2() * TILE_SIDE_F32 + Vec2::splat(TILE_SIDE_F32 / 2.0)
+    }
+
+    pub fn is_walkable(&self, cell: IVec2, clearance: i32) -> bool {
+        self.index(cell)
+            .is_some_and(|idx| self.clearance[idx] >= clearance)
+    }
+
+    /// Cast a ray from `from` along `dir`. Returns the distance to the
+    /// first wall, if it is not further than `max_dist`.
+    pub fn cast(&self, from: Vec2, dir: Vec2, max_dist: f32) -> Option<f32> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return None;
+        }
+
+        // Walk the cells the ray crosses. Everything is in tile units.
+        let start = from / TILE_SIDE_F32;
+        let max_t = max_dist / TILE_SIDE_F32;
+        let mut cell = NavGrid::cell(from);
+        let step = ivec2(dir.x.signum() as i32, dir.y.signum() as i32);
+        let boundary = |cell: i32, start: f32, dir: f32| {
+            if dir > 0.0 {
+                (cell as f32 + 1.0 - start) / dir
+            } else if dir < 0.0 {
+                (cell as f32 - start) / dir
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut t_next = vec2(
+            boundary(cell.x, start.x, dir.x),
+            boundary(cell.y, start.y, dir.y),
+        );
+        let t_delta = vec2(1.0 / dir.x.abs(), 1.0 / dir.y.abs());
+        let mut t = 0.0;
+        while t <= max_t {
+            if !self.is_walkable(cell, 0) {
+                return Some(t * TILE_SIDE_F32);
+            }
+            if t_next.x < t_next.y {
+                t = t_next.x;
+                t_next.x += t_delta.x;
+                cell.x += step.x;
+            } else {
+                t = t_next.y;
+                t_next.y += t_delta.y;
+                cell.y += step.y;
+            }
+        }
+        None
+    }
+
+    /// Find a path between two cells with A*. The path includes both `from`
+    /// and `to`. If `to` can't be reached, the path leads to the reachable
+    /// cell closest to it. Returns [Option::None] if `from` is outside of the map.
+    pub fn find_path(&self, from: IVec2, to: IVec2, clearance: i32) -> Option<Vec<IVec2>> {
+        let start = self.index(from)?;
+        let mut cost = vec![f32::INFINITY; self.clearance.len()];
+        let mut came_from = vec![usize::MAX; self.clearance.len()];
+        let mut open = BinaryHeap::new();
+        let mut closest = (octile(from, to), start);
+
+        cost[start] = 0.0;
+        open.push(OpenCell {
+            estimate: octile(from, to),
+            idx: start,
+        });
+        while let Some(OpenCell { estimate, idx }) = open.pop() {
+            let cell = self.cell_at(idx);
+            let cell_cost = cost[idx];
+            if cell == to {
+                closest = (0.0, idx);
+                break;
+            }
+            if estimate > cell_cost + octile(cell, to) {
+                // A stale entry
+                continue;
+            }
+            if octile(cell, to) < closest.0 {
+                closest = (octile(cell, to), idx);
+            }
+
+            for off in NEIGHBOURS {
+                let next = cell + off;
+                if !self.can_step(cell, off, clearance) {
+                    continue;
+                }
+                let next_idx = self.index(next).unwrap();
+                let next_cost = cell_cost + off.as_vec2().length();
+                if next_cost >= cost[next_idx] {
+                    continue;
+                }
+                cost[next_idx] = next_cost;
+                came_from[next_idx] = idx;
+                open.push(OpenCell {
+                    estimate: next_cost + octile(next, to),
+                    idx: next_idx,
+                });
+            }
+        }
+
+        let mut path = vec![self.cell_at(closest.1)];
+        let mut idx = closest.1;
+        while came_from[idx] != usize::MAX {
+            idx = came_from[idx];
+            path.push(self.cell_at(idx));
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Diagonal steps can't cut the wall corners
+    fn can_step(&self, cell: IVec2, off: IVec2, clearance: i32) -> bool {
+        if !self.is_walkable(cell + off, clearance) {
+            return false;
+        }
+        if off.x == 0 || off.y == 0 {
+            return true;
+        }
+        self.is_walkable(cell + ivec2(off.x, 0), clearance)
+            && self.is_walkable(cell + ivec2(0, off.y), clearance)
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        if cell.x < 0 || cell.y < 0 || cell.x >= self.width || cell.y >= self.height {
+            return None;
+        }
+        Some((cell.x + cell.y * self.width) as usize)
+    }
+
+    fn cell_at(&self, idx: usize) -> IVec2 {
+        ivec2(idx as i32 % self.width, idx as i32 / self.width)
+    }
+}
+
+/// The A* heuristic for a grid with diagonal moves
+fn octile(from: IVec2, to: IVec2) -> f32 {
+    let delta = (to - from).abs();
+    let (short, long) = (delta.min_element() as f32, delta.max_element() as f32);
+    (long - short) + short * std::f32::consts::SQRT_2
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OpenCell {
+    estimate: f32,
+    idx: usize,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed to make the heap pop the cheapest cell
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then(other.idx.cmp(&self.idx))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the path of a character. The path only gets planned
+/// again when the goal moves to another cell.
+#[derive(Debug, Clone, Default)]
+pub struct PathFollower {
+    waypoints: Vec<Vec2>,
+    next: usize,
+    goal_cell: Option<IVec2>,
+}
+
+impl PathFollower {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the current path
+    pub fn invalidate(&mut self) {
+        *self = PathFollower::new();
+    }
+
+    pub fn waypoints(&self) -> &[Vec2] {
+        &self.waypoints[self.next..]
+    }
+
+    /// The offset from `pos` to the next waypoint on the way to `goal`.
+    /// Returns [Option::None] if `pos` is outside of the map.
+    pub fn offset_to_next(
+        &mut self,
+        nav: &NavGrid,
+        shape: Shape,
+        pos: Vec2,
+        goal: Vec2,
+    ) -> Option<Vec2> {
+        let goal_cell = NavGrid::cell(goal);
+        if self.goal_cell != Some(goal_cell) {
+            let path =
+                nav.find_path(NavGrid::cell(pos), goal_cell, NavGrid::clearance_for(shape))?;
+            let reaches_goal = path.last() == Some(&goal_cell);
+            // Skip the cell the character is standing in
+            self.waypoints = path.into_iter().skip(1).map(NavGrid::cell_center).collect();
+            if reaches_goal {
+                self.waypoints.pop();
+                self.waypoints.push(goal);
+            }
+            self.next = 0;
+            self.goal_cell = Some(goal_cell);
+        }
+
+        if let Some(last) = self.waypoints.last_mut()
+            && NavGrid::cell(*last) == goal_cell
+        {
+            // Keep up with the goal moving inside its cell
+            *last = goal;
+        }
+
+        while self.next + 1 < self.waypoints.len()
+            && self.waypoints[self.next].distance(pos) <= WAYPOINT_REACH
+        {
+            self.next += 1;
+        }
+
+        Some(
+            self.waypoints
+                .get(self.next)
+                .map(|waypoint| *waypoint - pos)
+                .unwrap_or(Vec2::ZERO),
+        )
+    }
+}
+
+impl<T> Character<'_, T> {
+    /// The offset to the next waypoint on the way to `goal`. `follower`
+    /// must return the [PathFollower] of the character.
+    pub fn path_offset(
+        &mut self,
+        nav: &NavGrid,
+        goal: Vec2,
+        follower: impl FnOnce(&mut T) -> &mut PathFollower,
+    ) -> Vec2 {
+        let shape = self.character_q.body.shape;
+        let pos = self.pos();
+        follower(&mut self.data)
+            .offset_to_next(nav, shape, pos, goal)
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    /// Walk towards `goal` around the walls. `step` is the maximal
+    /// distance to walk this tick. Returns the walk direction.
+    pub fn walk_path(
+        &mut self,
+        nav: &NavGrid,
+        goal: Vec2,
+        step: f32,
+        follower: impl FnOnce(&mut T) -> &mut PathFollower,
+    ) -> Vec2 {
+        let offset = self.path_offset(nav, goal, follower);
+        self.set_walk_step(offset.clamp_length_max(step));
+        offset.normalize_or_zero()
+    }
+}
+
+pub fn draw_paths(world: &World, _resources: &Resources) {
+    for (_, follower) in &mut world.query::<&PathFollower>() {
+        for pair in follower.waypoints().windows(2) {
+            draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 1.0, GREEN);
+        }
+        for waypoint in follower.waypoints() {
+            draw_circle(waypoint.x, waypoint.y, 1.5, GREEN);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use hashbrown::HashMap;
+    use lib_asset::level::{MapDef, Tile, TileTy};
+    use lib_col::Shape;
+    use macroquad::math::{IVec2, Vec2, ivec2};
+
+    use super::NavGrid;
+
+    /// `#` is a wall, everything else is ground
+    pub(crate) fn map(rows: &[&str]) -> NavGrid {
+        let tiles = HashMap::from_iter([
+            (
+                0,
+                Tile {
+                    ty: TileTy::Ground,
+                    ..Default::default()
+                },
+            ),
+            (
+                1,
+                Tile {
+                    ty: TileTy::Wall,
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let tilemap = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|ch| Some(if ch == '#' { 1 } else { 0 }))
+            .collect();
+        NavGrid::new(&MapDef {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            tiles,
+            tilemap,
+            ..Default::default()
+        })
+    }
+
+    fn assert_valid(nav: &NavGrid, path: &[IVec2], clearance: i32) {
+        for pair in path.windows(2) {
+            let off = pair[1] - pair[0];
+            assert!(off.abs().max_element() == 1, "{path:?} has a gap");
+            assert!(nav.can_step(pair[0], off, clearance), "{path:?} is invalid");
+        }
+    }
+
+    #[test]
+    fn test_open_map() {
+        let nav = map(&["....", "....", "...."]);
+        let path = nav.find_path(ivec2(0, 0), ivec2(3, 2), 0).unwrap();
+        assert_eq!(path.first(), Some(&ivec2(0, 0)));
+        assert_eq!(path.last(), Some(&ivec2(3, 2)));
+        assert_eq!(path.len(), 4);
+        assert_valid(&nav, &path, 0);
+    }
+
+    #[test]
+    fn test_around_wall() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            ".....",
+            ".###.",
+            "..#..",
+            "..#..",
+        ]);
+        let path = nav.find_path(ivec2(1, 3), ivec2(3, 3), 0).unwrap();
+        assert_eq!(path.last(), Some(&ivec2(3, 3)));
+        assert!(path.contains(&ivec2(2, 0)));
+        assert_valid(&nav, &path, 0);
+    }
+
+    #[test]
+    fn test_no_corner_cutting() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            ".#",
+            "#.",
+        ]);
+        let path = nav.find_path(ivec2(0, 0), ivec2(1, 1), 0).unwrap();
+        assert_eq!(path, vec![ivec2(0, 0)]);
+    }
+
+    #[test]
+    fn test_clearance() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            "#########",
+            "#...#...#",
+            "#.......#",
+            "#...#...#",
+            "#########",
+        ]);
+        assert_eq!(nav.find_path(ivec2(2, 2), ivec2(6, 2), 0).unwrap().len(), 5);
+
+        // Wider than a tile: the gap is too tight
+        let wide = Shape::Rect {
+            width: 20.0,
+            height: 20.0,
+        };
+        let clearance = NavGrid::clearance_for(wide);
+        assert_eq!(clearance, 1);
+        let path = nav.find_path(ivec2(2, 2), ivec2(6, 2), clearance).unwrap();
+        assert_eq!(path, vec![ivec2(2, 2)]);
+    }
+
+    #[test]
+    fn test_cast() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            ".....",
+            "...#.",
+            ".....",
+        ]);
+        let from = NavGrid::cell_center(ivec2(0, 1));
+        let hit = nav.cast(from, Vec2::X, 100.0).unwrap();
+        assert!((hit - 40.0).abs() < 1e-3);
+        assert_eq!(nav.cast(from, Vec2::X, 39.0), None);
+        assert_eq!(nav.cast(from, Vec2::Y, 16.0), None);
+        // Out of the map counts as a wall
+        let hit = nav.cast(from, Vec2::Y, 100.0).unwrap();
+        assert!((hit - 24.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_blocked() {
+        #[rustfmt::skip]
+        let mut nav = map(&[
+            "#####",
+            "#...#",
+            "#####",
+        ]);
+        let door = ivec2(2, 1);
+        let from = NavGrid::cell_center(ivec2(1, 1));
+        assert_eq!(nav.find_path(ivec2(1, 1), ivec2(3, 1), 0).unwrap().len(), 3);
+        assert_eq!(nav.cast(from, Vec2::X, 24.0), None);
+
+        nav.set_blocked([door], true);
+        assert_eq!(
+            nav.find_path(ivec2(1, 1), ivec2(3, 1), 0).unwrap(),
+            vec![ivec2(1, 1)]
+        );
+        let hit = nav.cast(from, Vec2::X, 24.0).unwrap();
+        assert!((hit - 8.0).abs() < 1e-3);
+
+        nav.set_blocked([door], false);
+        assert_eq!(nav.find_path(ivec2(1, 1), ivec2(3, 1), 0).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_clearance_for() {
+        let rect = |side| Shape::Rect {
+            width: side,
+            height: side,
+        };
+        assert_eq!(NavGrid::clearance_for(rect(8.0)), 0);
+        assert_eq!(NavGrid::clearance_for(rect(16.0)), 0);
+        assert_eq!(NavGrid::clearance_for(rect(17.0)), 1);
+        assert_eq!(NavGrid::clearance_for(Shape::Circle { radius: 24.0 }), 1);
+        assert_eq!(NavGrid::clearance_for(Shape::Circle { radius: 25.0 }), 2);
+    }
+}
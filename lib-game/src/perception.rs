@@ -0,0 +1,301 @@
+use hecs::{Entity, World};
+use lib_asset::{Faction, Senses};
+use macroquad::prelude::*;
+
+use crate::{
+    CharacterLook, LocalTimeScale, NavGrid, Resources, Transform, collect_targets, local_dt,
+};
+
+/// A noise made by a character. Characters notice it if they
+/// are inside both the noise radius and their hearing radius.
+/// Send it with [crate::emit_event].
+#[derive(Debug, Clone, Copy)]
+pub struct Noise {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub faction: Faction,
+}
+
+/// What a character knows about its hostiles. The perception gets
+/// updated at the start of each tick, before the AI runs.
+///
+/// A character gets aggroed after seeing a target for a bit and
+/// calms down after not seeing it for a while. Aggroed characters
+/// track their target outside of their view cone.
+#[derive(Debug, Clone, Copy)]
+pub struct Perception {
+    pub senses: Senses,
+    target: Option<Entity>,
+    sees_target: bool,
+    last_seen: Option<Vec2>,
+    since_seen: f32,
+    aggro: bool,
+    aggro_timer: f32,
+    noise: Option<Vec2>,
+    since_noise: f32,
+}
+
+impl Perception {
+    pub fn new(senses: Senses) -> Self {
+        Self {
+            senses,
+            target: None,
+            sees_target: false,
+            last_seen: None,
+            since_seen: 0.0,
+            aggro: false,
+            aggro_timer: 0.0,
+            noise: None,
+            since_noise: 0.0,
+        }
+    }
+
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    /// Whether the target is visible this tick
+    pub fn sees_target(&self) -> bool {
+        self.sees_target
+    }
+
+    pub fn is_aggro(&self) -> bool {
+        self.aggro
+    }
+
+    /// The character is aggroed, but the target is out of sight
+    pub fn lost_target(&self) -> bool {
+        self.aggro && !self.sees_target
+    }
+
+    /// Where the target was seen the last time.
+    /// Only available for [Senses::memory] seconds.
+    pub fn last_seen(&self) -> Option<Vec2> {
+        self.last_seen
+    }
+
+    /// Where the last noise came from.
+    /// Only available for [Senses::memory] seconds.
+    pub fn heard_noise(&self) -> Option<Vec2> {
+        self.noise
+    }
+
+    /// Call this once the noise has been checked out
+    pub fn forget_noise(&mut self) {
+        self.noise = None;
+    }
+
+    /// Advance the timers with what has been perceived in the tick
+    pub fn observe(&mut self, dt: f32, sighting: Option<(Entity, Vec2)>, noise: Option<Vec2>) {
+        self.sees_target = sighting.is_some();
+        if let Some((target, pos)) = sighting {
+            self.target = Some(target);
+            self.last_seen = Some(pos);
+            self.since_seen = 0.0;
+            self.aggro_timer += dt;
+            self.aggro |= self.aggro_timer >= self.senses.aggro_time;
+        } else {
+            self.aggro_timer = 0.0;
+            self.since_seen += dt;
+            if self.since_seen >= self.senses.deaggro_time {
+                self.aggro = false;
+            }
+            if self.since_seen >= self.senses.memory {
+                self.last_seen = None;
+            }
+            if !self.aggro && self.last_seen.is_none() {
+                self.target = None;
+            }
+        }
+
+        if noise.is_some() {
+            self.noise = noise;
+            self.since_noise = 0.0;
+        } else {
+            self.since_noise += dt;
+            if self.since_noise >= self.senses.memory {
+                self.noise = None;
+            }
+        }
+    }
+}
+
+/// Whether a character at `pos` can see `target`. `look` is the
+/// view direction angle. Characters without one see all around.
+pub fn can_see(
+    senses: &Senses,
+    nav: &NavGrid,
+    pos: Vec2,
+    look: Option<f32>,
+    target: Vec2,
+    ignore_fov: bool,
+) -> bool {
+    let off = target - pos;
+    let dist = off.length();
+    if dist > senses.sight_range {
+        return false;
+    }
+    if !ignore_fov
+        && let Some(look) = look
+        && Vec2::from_angle(look).angle_to(off).abs() > senses.fov / 2.0
+    {
+        return false;
+    }
+    nav.cast(pos, off, dist).is_none()
+}
+
+pub(crate) fn update(dt: f32, world: &mut World, resources: &Resources) {
+    let targets = collect_targets(world);
+    let noises = world
+        .query::<&Noise>()
+        .iter()
+        .map(|(_, noise)| *noise)
+        .collect::<Vec<_>>();
+
+    for (ent, (tf, faction, perception, look, time_scale)) in world.query_mut::<(
+        &Transform,
+        &Faction,
+        &mut Perception,
+        Option<&CharacterLook>,
+        Option<&LocalTimeScale>,
+    )>() {
+        let is_hostile = |other: Faction| resources.factions.relation(*faction, other).is_hostile();
+        let look = look.map(|x| x.0);
+
+        // Keep the current target, if it's still visible
+        let sighting = targets
+            .iter()
+            .filter(|target| target.entity != ent && is_hostile(target.faction))
+            .filter(|target| {
+                can_see(
+                    &perception.senses,
+                    &resources.nav,
+                    tf.pos,
+                    look,
+                    target.pos,
+                    perception.is_aggro(),
+                )
+            })
+            .min_by(|a, b| {
+                let key = |x: &crate::TargetCandidate| {
+                    (Some(x.entity) != perception.target, x.pos.distance(tf.pos))
+                };
+                let (a, b) = (key(a), key(b));
+                a.0.cmp(&b.0).then(f32::total_cmp(&a.1, &b.1))
+            })
+            .map(|target| (target.entity, target.pos));
+        let noise = noises
+            .iter()
+            .filter(|noise| is_hostile(noise.faction))
+            .filter(|noise| {
+                let dist = noise.pos.distance(tf.pos);
+                dist <= noise.radius && dist <= perception.senses.hearing_radius
+            })
+            .min_by(|a, b| f32::total_cmp(&a.pos.distance(tf.pos), &b.pos.distance(tf.pos)))
+            .map(|noise| noise.pos);
+
+        perception.observe(local_dt(dt, time_scale), sighting, noise);
+    }
+}
+
+pub fn draw_perception(world: &World, _resources: &Resources) {
+    for (_, (tf, perception)) in &mut world.query::<(&Transform, &Perception)>() {
+        let color = if perception.is_aggro() { RED } else { YELLOW };
+        if let Some(seen) = perception.last_seen() {
+            draw_line(tf.pos.x, tf.pos.y, seen.x, seen.y, 1.0, color);
+        }
+        if let Some(noise) = perception.heard_noise() {
+            draw_circle_lines(noise.x, noise.y, 4.0, 1.0, ORANGE);
+        }
+        draw_circle_lines(
+            tf.pos.x,
+            tf.pos.y,
+            perception.senses.sight_range,
+            1.0,
+            color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hecs::World;
+    use lib_asset::Senses;
+    use macroquad::math::{Vec2, ivec2, vec2};
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::{Perception, can_see};
+    use crate::{NavGrid, navigation::tests::map};
+
+    const SENSES: Senses = Senses {
+        sight_range: 64.0,
+        fov: FRAC_PI_2,
+        hearing_radius: 32.0,
+        memory: 2.0,
+        aggro_time: 0.5,
+        deaggro_time: 1.0,
+    };
+
+    #[test]
+    fn test_can_see() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            ".....",
+            "..#..",
+            ".....",
+        ]);
+        let pos = NavGrid::cell_center(ivec2(0, 0));
+        let right = NavGrid::cell_center(ivec2(3, 0));
+        let behind_wall = NavGrid::cell_center(ivec2(3, 2));
+
+        assert!(can_see(&SENSES, &nav, pos, Some(0.0), right, false));
+        assert!(!can_see(&SENSES, &nav, pos, Some(0.0), behind_wall, false));
+        // Out of the view cone
+        assert!(!can_see(&SENSES, &nav, pos, Some(FRAC_PI_2), right, false));
+        assert!(can_see(&SENSES, &nav, pos, Some(FRAC_PI_2), right, true));
+        assert!(can_see(&SENSES, &nav, pos, None, right, false));
+        // Too far
+        assert!(!can_see(
+            &SENSES,
+            &nav,
+            pos,
+            None,
+            pos + vec2(65.0, 0.0),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_aggro() {
+        let target = World::new().spawn(());
+        let seen = Some((target, Vec2::ONE));
+        let mut perception = Perception::new(SENSES);
+
+        perception.observe(0.25, seen, None);
+        assert!(perception.sees_target());
+        assert!(!perception.is_aggro());
+        perception.observe(0.25, seen, None);
+        assert!(perception.is_aggro());
+
+        perception.observe(0.5, None, None);
+        assert!(perception.lost_target());
+        assert_eq!(perception.last_seen(), Some(Vec2::ONE));
+        perception.observe(0.5, None, None);
+        assert!(!perception.is_aggro());
+        assert_eq!(perception.target(), Some(target));
+        perception.observe(1.0, None, None);
+        assert_eq!(perception.last_seen(), None);
+        assert_eq!(perception.target(), None);
+    }
+
+    #[test]
+    fn test_noise() {
+        let mut perception = Perception::new(SENSES);
+        perception.observe(0.5, None, Some(Vec2::ONE));
+        assert_eq!(perception.heard_noise(), Some(Vec2::ONE));
+        perception.observe(1.5, None, None);
+        assert_eq!(perception.heard_noise(), Some(Vec2::ONE));
+        perception.observe(0.5, None, None);
+        assert_eq!(perception.heard_noise(), None);
+    }
+}
@@ -1,45 +1,164 @@
 use hecs::{CommandBuffer, EntityBuilder, World};
+use lib_asset::ProjectileBehaviour;
 use lib_col::Shape;
 use macroquad::math::Vec2;
 
 use crate::{
-    KinematicControl, ProjectileData, Team, Transform, build_attack, col_group, col_query,
+    CollisionSolver, Deflected, Faction, KinematicControl, LocalTimeScale, ProjectileData,
+    Resources, Transform, build_attack, col_group, col_query, collect_targets, hit_target,
+    local_dt, nearest_hostile,
 };
 
-pub(crate) fn ai(dt: f32, world: &mut World) {
-    for (_, (kinematic, data)) in world.query_mut::<(&mut KinematicControl, &ProjectileData)>() {
-        kinematic.dr = data.dir * data.speed * dt;
+pub(crate) fn ai(dt: f32, world: &mut World, resources: &Resources) {
+    let targets = collect_targets(world);
+    for (entity, (tf, kinematic, data, faction, time_scale)) in world.query_mut::<(
+        &Transform,
+        &mut KinematicControl,
+        &mut ProjectileData,
+        &Faction,
+        Option<&LocalTimeScale>,
+    )>() {
+        let dt = local_dt(dt, time_scale);
+        data.age += dt;
+
+        if let Some(acceleration) = data.behaviour.acceleration {
+            data.speed += acceleration.accel * dt;
+            data.speed = data
+                .speed
+                .clamp(acceleration.min_speed, acceleration.max_speed);
+        }
+
+        if let Some(homing) = data.behaviour.homing
+            && let Some(target) = nearest_hostile(resources, &targets, entity, tf.pos, *faction)
+            && target.pos.distance(tf.pos) <= homing.range
+        {
+            data.dir = turn_towards(data.dir, target.pos - tf.pos, homing.turn_rate * dt);
+        }
+
+        let mut velocity = data.dir * data.speed;
+        if let Some(wave) = data.behaviour.wave {
+            // The derivative of `amplitude * sin(omega * t)`
+            let omega = std::f32::consts::TAU * wave.frequency;
+            velocity += data.dir.perp() * wave.amplitude * omega * (omega * data.age).cos();
+        }
+
+        data.travelled += data.speed * dt;
+        kinematic.dr = velocity * dt;
     }
 }
 
-pub(crate) fn despawn_on_hit(world: &mut World, cmds: &mut CommandBuffer) {
-    for (entity, (kinematic, attack)) in
-        world.query_mut::<(&mut KinematicControl, &col_query::Damage)>()
+pub(crate) fn bounce(world: &mut World) {
+    for (_, (kinematic, data)) in world.query_mut::<(&mut KinematicControl, &mut ProjectileData)>()
     {
-        if kinematic.collided || attack.has_collided() {
+        if !kinematic.collided || data.bounces_left == 0 {
+            continue;
+        }
+        data.bounces_left -= 1;
+        data.dir = reflect(data.dir, kinematic.normal);
+        kinematic.collided = false;
+    }
+}
+
+pub(crate) fn despawn_on_hit(
+    world: &mut World,
+    resources: &Resources,
+    col_solver: &CollisionSolver,
+    cmds: &mut CommandBuffer,
+) {
+    for (entity, (kinematic, attack, faction, data, deflected)) in &mut world.query::<(
+        &mut KinematicControl,
+        &col_query::Damage,
+        &Faction,
+        Option<&ProjectileData>,
+        Option<&Deflected>,
+    )>() {
+        // Deflected projectiles fly through whoever deflected them.
+        // All projectiles fly through the ones they can't hurt.
+        let hit_something = col_solver
+            .collisions_for(attack)
+            .iter()
+            .filter_map(|x| hit_target(world, *x))
+            .filter(|x| deflected.is_none_or(|deflected| deflected.by != *x))
+            .any(|x| match world.get::<&Faction>(x) {
+                Ok(target) => resources.factions.relation(*faction, *target).can_damage(),
+                Err(_) => true,
+            });
+        let expired = data.is_some_and(|x| x.is_expired());
+        if kinematic.collided || hit_something || expired {
             cmds.despawn(entity);
         }
     }
 }
 
+/// Rotate `dir` towards `target_dir`, but not more than by `max_angle`
+fn turn_towards(dir: Vec2, target_dir: Vec2, max_angle: f32) -> Vec2 {
+    if target_dir == Vec2::ZERO {
+        return dir;
+    }
+    let angle = dir.angle_to(target_dir).clamp(-max_angle, max_angle);
+    Vec2::from_angle(angle).rotate(dir)
+}
+
+fn reflect(dir: Vec2, normal: Vec2) -> Vec2 {
+    let normal = normal.normalize_or_zero();
+    dir - 2.0 * dir.dot(normal) * normal
+}
+
+/// What a projectile built by [build_projectile] looks and flies like
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileParams {
+    pub shape: Shape,
+    pub graze_value: f32,
+    pub speed: f32,
+    pub behaviour: ProjectileBehaviour,
+}
+
 pub fn build_projectile(
     builder: &mut EntityBuilder,
     pos: Vec2,
-    shape: Shape,
+    faction: Faction,
     dir: Vec2,
-    graze_value: f32,
-    speed: f32,
+    params: ProjectileParams,
 ) {
     build_attack(
         builder,
         Transform::from_pos(pos),
-        Team::Enemy,
-        shape,
-        graze_value,
-        col_group::PLAYER,
+        faction,
+        params.shape,
+        params.graze_value,
+        col_group::NONE,
     );
     builder.add_bundle((
         KinematicControl::new_nonslide(col_group::LEVEL),
-        ProjectileData { dir, speed },
+        ProjectileData::new(dir, params.speed, params.behaviour),
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::{Vec2, vec2};
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::{reflect, turn_towards};
+
+    #[test]
+    fn test_turn_towards() {
+        let dir = turn_towards(Vec2::X, Vec2::Y, FRAC_PI_4);
+        assert!(dir.abs_diff_eq(Vec2::from_angle(FRAC_PI_4), 1e-5));
+
+        let dir = turn_towards(Vec2::X, -Vec2::Y, FRAC_PI_4);
+        assert!(dir.abs_diff_eq(Vec2::from_angle(-FRAC_PI_4), 1e-5));
+
+        let dir = turn_towards(Vec2::X, vec2(1.0, 0.1), FRAC_PI_4);
+        assert!(dir.abs_diff_eq(vec2(1.0, 0.1).normalize(), 1e-5));
+
+        let dir = turn_towards(Vec2::X, Vec2::ZERO, FRAC_PI_4);
+        assert_eq!(dir, Vec2::X);
+    }
+
+    #[test]
+    fn test_reflect() {
+        assert!(reflect(vec2(1.0, 1.0), -Vec2::X).abs_diff_eq(vec2(-1.0, 1.0), 1e-5));
+        assert!(reflect(vec2(1.0, -1.0), Vec2::Y).abs_diff_eq(vec2(1.0, 1.0), 1e-5));
+    }
+}
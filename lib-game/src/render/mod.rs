@@ -46,6 +46,7 @@ pub struct Render {
 
     pub announcement_text: Option<AnnouncementText>,
     pub sprite_buffer: Vec<SpriteData>,
+    rect_buffer: Vec<(Rect, Color)>,
     text_buffer: Vec<GlyphText>,
 }
 
@@ -55,10 +56,17 @@ impl Render {
             ui_font: INVALID_ASSET,
             announcement_text: None,
             sprite_buffer: Vec::new(),
+            rect_buffer: Vec::new(),
             text_buffer: Vec::new(),
         }
     }
 
+    /// Put a filled rectangle. The rectangles go over
+    /// the sprites, but under the texts.
+    pub fn put_rect(&mut self, rect: Rect, color: Color) {
+        self.rect_buffer.push((rect, color));
+    }
+
     pub fn put_text(
         &mut self,
         pos: Vec2,
@@ -104,6 +112,7 @@ impl Render {
     pub fn new_frame(&mut self) {
         self.announcement_text = None;
         self.sprite_buffer.clear();
+        self.rect_buffer.clear();
         self.text_buffer.clear();
     }
 
@@ -124,6 +133,7 @@ impl Render {
         if render_world {
             set_camera(camera);
             self.draw_sprites(resources);
+            self.draw_rects();
             self.draw_texts(resources);
         }
 
@@ -159,11 +169,11 @@ impl Render {
                 sprite.tf.pos.y,
                 sprite.color,
                 DrawTextureParams {
-                    dest_size: None,
+                    dest_size: Some(sprite.rect.size() * sprite.scale),
                     source: Some(sprite.rect),
                     rotation: sprite.tf.angle,
-                    flip_x: false,
-                    flip_y: false,
+                    flip_x: sprite.flip_x,
+                    flip_y: sprite.flip_y,
                     pivot: Some(sprite.tf.pos),
                 },
             );
@@ -192,6 +202,9 @@ impl Render {
                 rect: sprite.rect,
                 color: sprite.color,
                 sort_offset: sprite.sort_offset,
+                scale: sprite.scale,
+                flip_x: sprite.flip_x,
+                flip_y: sprite.flip_y,
             });
         }
     }
@@ -264,6 +277,12 @@ impl Render {
         }
     }
 
+    fn draw_rects(&mut self) {
+        for (rect, color) in self.rect_buffer.iter() {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, *color);
+        }
+    }
+
     fn draw_texts(&mut self, resources: &Resources) {
         for text in self.text_buffer.iter() {
             let tint = text.color;
@@ -337,6 +356,9 @@ pub struct SpriteData {
     pub rect: Rect,
     pub color: Color,
     pub sort_offset: f32,
+    pub scale: Vec2,
+    pub flip_x: bool,
+    pub flip_y: bool,
 }
 
 #[derive(Clone, Debug)]
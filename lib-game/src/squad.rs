@@ -0,0 +1,244 @@
+//! Attack coordination. Enemies ask their target for an attack token
+//! before attacking, so only a few of them swing at once, while the
+//! rest wait around the target.
+
+use hecs::{Entity, World};
+use macroquad::prelude::*;
+
+use crate::{Resources, Transform};
+
+/// Lives on the characters, that get attacked.
+/// Limits how many enemies may attack the character at once.
+#[derive(Debug, Clone)]
+pub struct AttackTokens {
+    max_attackers: u32,
+    holders: Vec<Entity>,
+}
+
+impl AttackTokens {
+    pub fn new(max_attackers: u32) -> Self {
+        Self {
+            max_attackers,
+            holders: Vec::new(),
+        }
+    }
+
+    pub fn holders(&self) -> &[Entity] {
+        &self.holders
+    }
+
+    fn try_take(&mut self, attacker: Entity) -> bool {
+        if self.holders.contains(&attacker) {
+            return true;
+        }
+        if self.holders.len() >= self.max_attackers as usize {
+            return false;
+        }
+        self.holders.push(attacker);
+        true
+    }
+}
+
+/// Lives on the enemies, that coordinate their attacks through [AttackTokens]
+#[derive(Debug, Clone, Copy)]
+pub struct SquadMember {
+    token: Option<Entity>,
+    held_for: f32,
+    since_release: f32,
+}
+
+impl SquadMember {
+    pub fn new() -> Self {
+        Self {
+            token: None,
+            held_for: 0.0,
+            since_release: f32::INFINITY,
+        }
+    }
+
+    /// The target, whose token the member holds
+    pub fn token(&self) -> Option<Entity> {
+        self.token
+    }
+
+    /// Ask `target` for a permission to attack. `tokens` are the [AttackTokens]
+    /// of the target. Targets without them can be attacked by anyone. A member
+    /// has to rest for `rest_time` seconds after giving a token back.
+    pub fn request_token(
+        &mut self,
+        member: Entity,
+        target: Entity,
+        tokens: Option<&mut AttackTokens>,
+        rest_time: f32,
+    ) -> bool {
+        if self.token == Some(target) {
+            return true;
+        }
+        self.release_token();
+        let Some(tokens) = tokens else {
+            return true;
+        };
+        if self.since_release < rest_time || !tokens.try_take(member) {
+            return false;
+        }
+        self.token = Some(target);
+        self.held_for = 0.0;
+        true
+    }
+
+    /// Give the token back. Call this once the attack is over.
+    pub fn release_token(&mut self) {
+        if self.token.take().is_some() {
+            self.since_release = 0.0;
+        }
+    }
+}
+
+impl Default for SquadMember {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Take the tokens back from the members, that hold them for too long,
+/// have already released them or are gone.
+pub(crate) fn update(dt: f32, world: &mut World, resources: &Resources) {
+    let cfg = &resources.cfg.squad;
+    for (_, member) in world.query_mut::<&mut SquadMember>() {
+        member.since_release += dt;
+        if member.token.is_none() {
+            continue;
+        }
+        member.held_for += dt;
+        if member.held_for >= cfg.token_timeout {
+            member.release_token();
+        }
+    }
+
+    let mut members = world.query::<&SquadMember>();
+    let members = members.view();
+    for (target, tokens) in &mut world.query::<&mut AttackTokens>() {
+        tokens.holders.retain(|holder| {
+            members
+                .get(*holder)
+                .is_some_and(|member| member.token == Some(target))
+        });
+    }
+}
+
+/// A spot `radius` away from `target`, that keeps at least `spread`
+/// radians away from the `allies` around the target. Going there makes
+/// a squad surround the target instead of queueing up on one side.
+pub fn flank_point(
+    target: Vec2,
+    pos: Vec2,
+    allies: impl IntoIterator<Item = Vec2>,
+    radius: f32,
+    spread: f32,
+) -> Vec2 {
+    let dir = (pos - target).normalize_or(Vec2::Y);
+    let mut push = Vec2::ZERO;
+    for ally in allies {
+        let ally_dir = (ally - target).normalize_or_zero();
+        if ally_dir == Vec2::ZERO {
+            continue;
+        }
+        let angle = dir.angle_to(ally_dir);
+        if angle.abs() >= spread {
+            continue;
+        }
+        // Turn away from the ally, the closer it is the harder
+        let away = if angle >= 0.0 {
+            -dir.perp()
+        } else {
+            dir.perp()
+        };
+        push += away * (1.0 - angle.abs() / spread);
+    }
+    target + (dir + push).normalize_or(dir) * radius
+}
+
+pub fn draw_squads(world: &World, resources: &Resources) {
+    for (_, (tf, tokens)) in &mut world.query::<(&Transform, &AttackTokens)>() {
+        draw_circle_lines(
+            tf.pos.x,
+            tf.pos.y,
+            resources.cfg.squad.wait_radius,
+            1.0,
+            SKYBLUE,
+        );
+        for holder in tokens.holders() {
+            let Ok(holder_tf) = world.get::<&Transform>(*holder) else {
+                continue;
+            };
+            draw_line(
+                tf.pos.x,
+                tf.pos.y,
+                holder_tf.pos.x,
+                holder_tf.pos.y,
+                1.0,
+                SKYBLUE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hecs::World;
+    use macroquad::prelude::*;
+
+    use super::{AttackTokens, SquadMember, flank_point};
+
+    #[test]
+    fn test_token_limit() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let [a, b, c] = [(); 3].map(|_| world.spawn(()));
+        let mut tokens = AttackTokens::new(2);
+        let mut members = [SquadMember::new(); 3];
+
+        assert!(members[0].request_token(a, target, Some(&mut tokens), 1.0));
+        assert!(members[1].request_token(b, target, Some(&mut tokens), 1.0));
+        assert!(!members[2].request_token(c, target, Some(&mut tokens), 1.0));
+        // Asking again is fine
+        assert!(members[0].request_token(a, target, Some(&mut tokens), 1.0));
+        assert_eq!(tokens.holders(), &[a, b]);
+
+        // Anyone can attack targets without the tokens
+        assert!(members[2].request_token(c, target, None, 1.0));
+    }
+
+    #[test]
+    fn test_rest_after_release() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let member_ent = world.spawn(());
+        let mut tokens = AttackTokens::new(2);
+        let mut member = SquadMember::new();
+
+        assert!(member.request_token(member_ent, target, Some(&mut tokens), 1.0));
+        member.release_token();
+        tokens.holders.clear();
+        assert!(!member.request_token(member_ent, target, Some(&mut tokens), 1.0));
+        member.since_release = 1.0;
+        assert!(member.request_token(member_ent, target, Some(&mut tokens), 1.0));
+    }
+
+    #[test]
+    fn test_flank_point() {
+        let target = Vec2::ZERO;
+        // Nobody around, stay on the own side
+        let point = flank_point(target, vec2(10.0, 0.0), [], 32.0, 1.0);
+        assert!(point.abs_diff_eq(vec2(32.0, 0.0), 1e-4));
+
+        // An ally on the same side pushes the member aside
+        let point = flank_point(target, vec2(10.0, 0.0), [vec2(20.0, 1.0)], 32.0, 1.0);
+        assert!((point.length() - 32.0).abs() < 1e-4);
+        assert!(point.y < 0.0);
+
+        // Allies far around the circle don't matter
+        let point = flank_point(target, vec2(10.0, 0.0), [vec2(-20.0, 0.0)], 32.0, 1.0);
+        assert!(point.abs_diff_eq(vec2(32.0, 0.0), 1e-4));
+    }
+}
@@ -0,0 +1,302 @@
+use hashbrown::HashSet;
+use hecs::{Query, World};
+use lib_asset::{
+    AssetKey, StateAnimation, StateMachine, Symbol, Trigger, animation_manifest::AnimationId,
+};
+use log::warn;
+
+use crate::{Character, CharacterData, CharacterLook, Resources, for_each_character};
+
+/// The current state of a character's [StateMachine].
+/// The code drives the machine by sending signals and
+/// raising flags, the rest is described by the asset.
+#[derive(Debug, Clone)]
+pub struct MachineState {
+    pub machine: AssetKey,
+    state: Symbol,
+    elapsed: f32,
+    signals: Vec<Symbol>,
+    flags: HashSet<Symbol>,
+}
+
+impl MachineState {
+    pub fn new(key: AssetKey, machine: &StateMachine) -> Self {
+        Self {
+            machine: key,
+            state: machine.initial,
+            elapsed: 0.0,
+            signals: Vec::new(),
+            flags: HashSet::new(),
+        }
+    }
+
+    pub fn state(&self) -> Symbol {
+        self.state
+    }
+
+    pub fn is(&self, state: Symbol) -> bool {
+        self.state == state
+    }
+
+    /// Seconds spent in the current state
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Send a signal. The signals are only kept till the end of the tick.
+    pub fn signal(&mut self, signal: Symbol) {
+        self.signals.push(signal);
+    }
+
+    pub fn set_flag(&mut self, flag: Symbol, raised: bool) {
+        if raised {
+            self.flags.insert(flag);
+        } else {
+            self.flags.remove(&flag);
+        }
+    }
+
+    pub fn has_flag(&self, flag: Symbol) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    fn enter(&mut self, state: Symbol) {
+        self.state = state;
+        self.elapsed = 0.0;
+    }
+
+    fn fires(&self, trigger: &Trigger) -> bool {
+        match *trigger {
+            // Handled by [CharacterData::on_anim_end]
+            Trigger::AnimEnd => false,
+            Trigger::Signal { signal } => self.signals.contains(&signal),
+            Trigger::Flag { flag } => self.has_flag(flag),
+            Trigger::NotFlag { flag } => !self.has_flag(flag),
+            Trigger::After { seconds } => self.elapsed >= seconds,
+        }
+    }
+}
+
+/// A character, whose states are fully described by a [StateMachine].
+/// Characters with special needs can still implement [CharacterData] by hand.
+#[derive(Query)]
+pub struct MachineData<'a> {
+    pub state: &'a mut MachineState,
+}
+
+fn machine<'a>(character: &Character<'a, MachineData>) -> &'a StateMachine {
+    character
+        .resources
+        .state_machines
+        .get(character.data.state.machine)
+        .expect("state machines stay loaded while the level runs")
+}
+
+impl CharacterData for MachineData<'_> {
+    type StateId = Symbol;
+
+    fn get_state(&self) -> Self::StateId {
+        self.state.state
+    }
+    fn set_state(&mut self, new_state: Self::StateId) {
+        self.state.enter(new_state)
+    }
+    fn state_to_anim(character: &Character<Self>) -> (AnimationId, bool) {
+        let machine = machine(character);
+        // The unknown states are left on the next run of the machine
+        let state = machine
+            .state(character.get_state())
+            .or_else(|| machine.state(machine.initial))
+            .expect("validated state machines have the initial state");
+        pick_state_animation(&state.animation, *character.character_q.look)
+    }
+    fn on_anim_end(character: &mut Character<Self>) {
+        let next = machine(character).next_state(character.get_state(), |trigger| {
+            *trigger == Trigger::AnimEnd
+        });
+        if let Some(next) = next {
+            character.set_state(next);
+        }
+    }
+}
+
+/// The animation to play for the look, and whether it must be mirrored
+pub fn pick_state_animation(
+    animation: &StateAnimation,
+    look: CharacterLook,
+) -> (AnimationId, bool) {
+    match animation {
+        StateAnimation::Single(animation) => (*animation, false),
+        StateAnimation::Directions { directions } => look
+            .pick_animation(directions)
+            .expect("validated state machines have a direction"),
+    }
+}
+
+/// Fire the state machine transitions. Run this after the
+/// code, that sends the signals to the machines.
+pub fn run_state_machines(dt: f32, world: &mut World, resources: &Resources) {
+    for_each_character::<MachineData>(world, resources, |_, mut character| {
+        let dt = character.local_dt(dt);
+        let machine = machine(&character);
+        let state = &mut *character.data.state;
+        if machine.state(state.state).is_none() {
+            warn!(
+                "No state {:?} in the machine, going back to {:?}",
+                state.state, machine.initial
+            );
+            state.enter(machine.initial);
+        }
+        state.elapsed += dt;
+        let next = machine.next_state(state.state, |trigger| state.fires(trigger));
+        state.signals.clear();
+        if let Some(next) = next {
+            character.set_state(next);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+    use lib_asset::{
+        StateAnimation, StateDef, StateMachine, Symbol, Transition, Trigger,
+        animation_manifest::AnimationId,
+    };
+
+    use super::{MachineState, pick_state_animation};
+    use crate::{AssetContainer, CharacterLook};
+
+    fn machine() -> StateMachine {
+        let state = |transitions| StateDef {
+            animation: StateAnimation::Single(AnimationId::ShooterIdle),
+            transitions,
+        };
+        let to = |to: &str, on| Transition {
+            to: Symbol::new(to),
+            on,
+        };
+        StateMachine {
+            initial: Symbol::new("idle"),
+            states: HashMap::from_iter([
+                (
+                    Symbol::new("idle"),
+                    state(vec![
+                        to(
+                            "attack",
+                            Trigger::Signal {
+                                signal: Symbol::new("attack"),
+                            },
+                        ),
+                        to(
+                            "walk",
+                            Trigger::Flag {
+                                flag: Symbol::new("moving"),
+                            },
+                        ),
+                    ]),
+                ),
+                (
+                    Symbol::new("walk"),
+                    state(vec![to(
+                        "idle",
+                        Trigger::NotFlag {
+                            flag: Symbol::new("moving"),
+                        },
+                    )]),
+                ),
+                (
+                    Symbol::new("attack"),
+                    state(vec![to("idle", Trigger::After { seconds: 0.5 })]),
+                ),
+            ]),
+        }
+    }
+
+    fn next(machine: &StateMachine, state: &MachineState) -> Option<Symbol> {
+        machine.next_state(state.state(), |trigger| state.fires(trigger))
+    }
+
+    #[test]
+    fn test_triggers() {
+        let mut machines = AssetContainer::new();
+        let key = machines.insert("fsm/test.json", machine());
+        let machine = machines.get(key).unwrap();
+        let mut state = MachineState::new(key, machine);
+        assert!(state.is(Symbol::new("idle")));
+        assert_eq!(next(machine, &state), None);
+
+        state.set_flag(Symbol::new("moving"), true);
+        assert_eq!(next(machine, &state), Some(Symbol::new("walk")));
+        // Earlier transitions take priority
+        state.signal(Symbol::new("attack"));
+        assert_eq!(next(machine, &state), Some(Symbol::new("attack")));
+
+        state.enter(Symbol::new("walk"));
+        assert_eq!(next(machine, &state), None);
+        state.set_flag(Symbol::new("moving"), false);
+        assert_eq!(next(machine, &state), Some(Symbol::new("idle")));
+
+        state.enter(Symbol::new("attack"));
+        state.elapsed += 0.25;
+        assert_eq!(next(machine, &state), None);
+        state.elapsed += 0.25;
+        assert_eq!(next(machine, &state), Some(Symbol::new("idle")));
+    }
+
+    #[test]
+    fn test_directions() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+        let four = StateAnimation::Directions {
+            directions: vec![
+                Some(AnimationId::BnuuyIdleR),
+                Some(AnimationId::BnuuyIdleD),
+                Some(AnimationId::BnuuyIdleL),
+                Some(AnimationId::BnuuyIdleU),
+            ],
+        };
+        let pick = |animation: &StateAnimation, angle| {
+            pick_state_animation(animation, CharacterLook(angle))
+        };
+        assert_eq!(pick(&four, 0.1), (AnimationId::BnuuyIdleR, false));
+        assert_eq!(pick(&four, FRAC_PI_2), (AnimationId::BnuuyIdleD, false));
+        assert_eq!(pick(&four, -PI), (AnimationId::BnuuyIdleL, false));
+        assert_eq!(pick(&four, -FRAC_PI_2), (AnimationId::BnuuyIdleU, false));
+
+        // Right, down right, down and up. The left side is mirrored.
+        let eight = StateAnimation::Directions {
+            directions: vec![
+                Some(AnimationId::BnuuyWalkR),
+                Some(AnimationId::BnuuyWalkD),
+                Some(AnimationId::BnuuyIdleD),
+                None,
+                None,
+                None,
+                Some(AnimationId::BnuuyWalkU),
+                None,
+            ],
+        };
+        assert_eq!(pick(&eight, FRAC_PI_4), (AnimationId::BnuuyWalkD, false));
+        assert_eq!(pick(&eight, PI), (AnimationId::BnuuyWalkR, true));
+        assert_eq!(
+            pick(&eight, 3.0 * FRAC_PI_4),
+            (AnimationId::BnuuyWalkD, true)
+        );
+        // Up left mirrors into the missing up right, so the nearest is used
+        assert_eq!(
+            pick(&eight, -3.0 * FRAC_PI_4),
+            (AnimationId::BnuuyWalkU, false)
+        );
+        // Otherwise the side the look leans to wins
+        let up_right = -FRAC_PI_4;
+        assert_eq!(
+            pick(&eight, up_right - 0.1),
+            (AnimationId::BnuuyWalkU, false)
+        );
+        assert_eq!(
+            pick(&eight, up_right + 0.1),
+            (AnimationId::BnuuyWalkR, false)
+        );
+    }
+}
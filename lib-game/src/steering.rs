@@ -0,0 +1,210 @@
+//! Steering behaviours for the AI movement. Every behaviour returns
+//! a desired walk direction with a length between `0.0` and `1.0`,
+//! so the results can be mixed with [blend]. Multiply the result by
+//! the speed and `dt` to get the step for [crate::Character::set_walk_step].
+
+use lib_col::Shape;
+use macroquad::prelude::*;
+
+use crate::NavGrid;
+
+/// Go straight to `target`
+pub fn seek(pos: Vec2, target: Vec2) -> Vec2 {
+    (target - pos).normalize_or_zero()
+}
+
+/// Go straight away from `threat`
+pub fn flee(pos: Vec2, threat: Vec2) -> Vec2 {
+    -seek(pos, threat)
+}
+
+/// Go to `target`, slowing down once closer than `slow_radius`
+pub fn arrive(pos: Vec2, target: Vec2, slow_radius: f32) -> Vec2 {
+    let off = target - pos;
+    let dist = off.length();
+    if dist <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+    off / dist * (dist / slow_radius).min(1.0)
+}
+
+/// Circle around `center`, staying `radius` away from it
+pub fn orbit(pos: Vec2, center: Vec2, radius: f32, clockwise: bool) -> Vec2 {
+    let off = pos - center;
+    let dist = off.length();
+    if dist <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+    let outwards = off / dist;
+    // The Y axis points down, so `perp` turns clockwise on the screen
+    let tangent = if clockwise {
+        outwards.perp()
+    } else {
+        -outwards.perp()
+    };
+    let correction = ((radius - dist) / radius).clamp(-1.0, 1.0);
+    (tangent + outwards * correction).normalize_or_zero()
+}
+
+/// Keep away from the `neighbours` closer than `radius`.
+/// The closer a neighbour is, the harder it pushes.
+pub fn separation(pos: Vec2, neighbours: impl IntoIterator<Item = Vec2>, radius: f32) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    for neighbour in neighbours {
+        let off = pos - neighbour;
+        let dist = off.length();
+        if dist <= f32::EPSILON || dist >= radius {
+            continue;
+        }
+        push += off / dist * (1.0 - dist / radius);
+    }
+    push.clamp_length_max(1.0)
+}
+
+/// The `half_width` to use in [avoid_walls] for a body, so the
+/// whiskers clear it whichever way it is turned
+pub fn body_half_width(shape: Shape) -> f32 {
+    match shape {
+        Shape::Rect { width, height } => width.max(height) / 2.0,
+        Shape::Circle { radius } => radius,
+    }
+}
+
+/// Steer away from the walls in front of a character walking along `dir`.
+/// Three rays are cast: one from the center and two from the sides,
+/// which are `half_width` away from it.
+pub fn avoid_walls(nav: &NavGrid, pos: Vec2, dir: Vec2, half_width: f32, look_ahead: f32) -> Vec2 {
+    let dir = dir.normalize_or_zero();
+    if dir == Vec2::ZERO {
+        return Vec2::ZERO;
+    }
+
+    let side = dir.perp();
+    let whiskers = [
+        (pos, -dir),
+        (pos + side * half_width, -side),
+        (pos - side * half_width, side),
+    ];
+    let mut push = Vec2::ZERO;
+    for (origin, away) in whiskers {
+        if let Some(dist) = nav.cast(origin, dir, look_ahead) {
+            push += away * (1.0 - dist / look_ahead);
+        }
+    }
+    push.clamp_length_max(1.0)
+}
+
+/// Wander around aimlessly. The direction drifts by a random
+/// angle every step. The randomness is seeded, so the wandering
+/// is the same every time.
+#[derive(Debug, Clone, Copy)]
+pub struct Wander {
+    pub angle: f32,
+    state: u32,
+}
+
+impl Wander {
+    pub fn new(seed: u32, angle: f32) -> Self {
+        Self {
+            angle,
+            // Xorshift gets stuck on zero
+            state: seed.max(1),
+        }
+    }
+
+    /// Turn by up to `jitter` radians and return the new direction
+    pub fn steer(&mut self, jitter: f32) -> Vec2 {
+        self.angle += (self.next_random() * 2.0 - 1.0) * jitter;
+        Vec2::from_angle(self.angle)
+    }
+
+    /// A number in `0.0..1.0`
+    fn next_random(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Mix the behaviours by their weights. The result is
+/// clamped to the length of `1.0`.
+pub fn blend(behaviours: &[(f32, Vec2)]) -> Vec2 {
+    behaviours
+        .iter()
+        .map(|(weight, dir)| *weight * *dir)
+        .sum::<Vec2>()
+        .clamp_length_max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::{Vec2, ivec2, vec2};
+
+    use super::*;
+    use crate::navigation::tests::map;
+
+    #[test]
+    fn test_arrive() {
+        assert_eq!(arrive(Vec2::ZERO, vec2(10.0, 0.0), 5.0), Vec2::X);
+        assert_eq!(arrive(Vec2::ZERO, vec2(2.0, 0.0), 4.0), vec2(0.5, 0.0));
+        assert_eq!(arrive(Vec2::ZERO, Vec2::ZERO, 4.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_orbit() {
+        // On the circle: just go around
+        let dir = orbit(vec2(10.0, 0.0), Vec2::ZERO, 10.0, true);
+        assert!(dir.abs_diff_eq(Vec2::Y, 1e-5));
+        let dir = orbit(vec2(10.0, 0.0), Vec2::ZERO, 10.0, false);
+        assert!(dir.abs_diff_eq(-Vec2::Y, 1e-5));
+
+        // Too far: get closer
+        let dir = orbit(vec2(20.0, 0.0), Vec2::ZERO, 10.0, true);
+        assert!(dir.x < 0.0 && dir.y > 0.0);
+    }
+
+    #[test]
+    fn test_separation() {
+        let pos = Vec2::ZERO;
+        assert_eq!(separation(pos, [vec2(20.0, 0.0)], 10.0), Vec2::ZERO);
+        assert_eq!(separation(pos, [vec2(5.0, 0.0)], 10.0), vec2(-0.5, 0.0));
+        assert_eq!(
+            separation(pos, [vec2(5.0, 0.0), vec2(-5.0, 0.0)], 10.0),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_avoid_walls() {
+        #[rustfmt::skip]
+        let nav = map(&[
+            ".....",
+            ".....",
+            "...#.",
+            ".....",
+            ".....",
+        ]);
+        let pos = crate::NavGrid::cell_center(ivec2(0, 2));
+        assert_eq!(avoid_walls(&nav, pos, Vec2::Y, 4.0, 16.0), Vec2::ZERO);
+        let push = avoid_walls(&nav, pos, Vec2::X, 4.0, 64.0);
+        assert!(push.x < 0.0);
+    }
+
+    #[test]
+    fn test_wander() {
+        let mut a = Wander::new(42, 0.0);
+        let mut b = Wander::new(42, 0.0);
+        for _ in 0..100 {
+            let prev = a.angle;
+            assert_eq!(a.steer(0.1), b.steer(0.1));
+            assert!((a.angle - prev).abs() <= 0.1);
+        }
+    }
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend(&[(0.5, Vec2::X), (0.25, Vec2::Y)]), vec2(0.5, 0.25));
+        assert_eq!(blend(&[(1.0, Vec2::X), (1.0, Vec2::X)]), Vec2::X);
+    }
+}
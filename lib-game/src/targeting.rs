@@ -0,0 +1,52 @@
+use hecs::{Entity, World};
+use macroquad::prelude::*;
+
+use crate::{Faction, Health, Resources, Transform};
+
+/// A character the AI can pick as its target.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetCandidate {
+    pub entity: Entity,
+    pub pos: Vec2,
+    pub faction: Faction,
+}
+
+/// Gather everything that can be targeted. Usually done once per tick
+/// before running the AI, as the character iteration borrows the world.
+pub fn collect_targets(world: &World) -> Vec<TargetCandidate> {
+    world
+        .query::<(&Transform, &Faction)>()
+        .with::<&Health>()
+        .iter()
+        .map(|(entity, (tf, faction))| TargetCandidate {
+            entity,
+            pos: tf.pos,
+            faction: *faction,
+        })
+        .collect()
+}
+
+/// Find the closest candidate that `faction` is hostile towards.
+pub fn nearest_hostile(
+    resources: &Resources,
+    targets: &[TargetCandidate],
+    seeker: Entity,
+    pos: Vec2,
+    faction: Faction,
+) -> Option<TargetCandidate> {
+    targets
+        .iter()
+        .filter(|target| target.entity != seeker)
+        .filter(|target| {
+            resources
+                .factions
+                .relation(faction, target.faction)
+                .is_hostile()
+        })
+        .min_by(|a, b| {
+            let dist_a = a.pos.distance_squared(pos);
+            let dist_b = b.pos.distance_squared(pos);
+            f32::total_cmp(&dist_a, &dist_b)
+        })
+        .copied()
+}
@@ -0,0 +1,98 @@
+use std::cell::Cell;
+
+/// [TimeScale] controls how fast the game time flows.
+/// Everything that gets a `dt` from the [crate::App] is affected:
+/// animations, AI, physics, cooldowns.
+///
+/// The methods take `&self`, so the game can request slow-motion
+/// and hit-stops from any phase.
+#[derive(Debug)]
+pub struct TimeScale {
+    scale: Cell<f32>,
+    hitstop: Cell<f32>,
+}
+
+impl TimeScale {
+    pub fn new() -> Self {
+        Self {
+            scale: Cell::new(1.0),
+            hitstop: Cell::new(0.0),
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale.get()
+    }
+
+    /// Set the time multiplier. `0.5` is half speed.
+    pub fn set_scale(&self, scale: f32) {
+        self.scale.set(scale.max(0.0));
+    }
+
+    /// Freeze the game for `duration` seconds of real time.
+    /// Hit-stops do not stack: the longest one wins.
+    pub fn hitstop(&self, duration: f32) {
+        self.hitstop.set(self.hitstop.get().max(duration));
+    }
+
+    pub fn hitstop_remaining(&self) -> f32 {
+        self.hitstop.get()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.hitstop.get() > 0.0
+    }
+
+    /// Get the game dt for a tick lasting `real_dt` and advance the hit-stop timer.
+    pub(crate) fn advance(&self, real_dt: f32) -> f32 {
+        if self.is_frozen() {
+            self.hitstop.set((self.hitstop.get() - real_dt).max(0.0));
+            return 0.0;
+        }
+        real_dt * self.scale.get()
+    }
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale::new()
+    }
+}
+
+/// Per-entity multiplier on top of the [TimeScale].
+/// Put `0.0` to freeze a single entity and `> 1.0` to let
+/// it move faster than the rest of the world.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalTimeScale(pub f32);
+
+pub fn local_dt(dt: f32, local: Option<&LocalTimeScale>) -> f32 {
+    match local {
+        Some(local) => dt * local.0,
+        None => dt,
+    }
+}
+
+/// Attacks with this component freeze the game for the
+/// specified amount of seconds when they deal damage.
+#[derive(Debug, Clone, Copy)]
+pub struct HitStop(pub f32);
+
+#[cfg(test)]
+mod tests {
+    use super::TimeScale;
+
+    #[test]
+    fn test_hitstop() {
+        let time = TimeScale::new();
+        time.set_scale(0.5);
+        assert_eq!(time.advance(1.0), 0.5);
+
+        time.hitstop(1.5);
+        time.hitstop(0.5);
+        assert_eq!(time.advance(1.0), 0.0);
+        assert_eq!(time.hitstop_remaining(), 0.5);
+        assert_eq!(time.advance(1.0), 0.0);
+        assert!(!time.is_frozen());
+        assert_eq!(time.advance(1.0), 0.5);
+    }
+}
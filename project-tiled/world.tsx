@@ -33,6 +33,7 @@
  <tile id="17" type="Tile">
   <properties>
    <property name="description" value="Brick wall top"/>
+   <property name="door" type="bool" value="true"/>
    <property name="ty" propertytype="tile_ty" value="Wall"/>
   </properties>
  </tile>
@@ -1,13 +1,25 @@
 use super::prelude::*;
 
-pub fn init(builder: &mut EntityBuilder, pos: Vec2, look_angle: f32, resources: &Resources) {
+pub fn init(
+    builder: &mut EntityBuilder,
+    pos: Vec2,
+    faction: Faction,
+    look_angle: f32,
+    behaviour: Option<ProjectileBehaviour>,
+    resources: &Resources,
+) {
+    let cfg = &resources.cfg.basic_bullet;
     build_projectile(
         builder,
         pos,
-        resources.cfg.basic_bullet.shape,
+        faction,
         Vec2::from_angle(look_angle),
-        resources.cfg.basic_bullet.graze_value,
-        resources.cfg.basic_bullet.speed,
+        ProjectileParams {
+            shape: cfg.shape,
+            graze_value: cfg.graze_value,
+            speed: cfg.speed,
+            behaviour: behaviour.unwrap_or(cfg.behaviour),
+        },
     );
     builder.add(Sprite {
         layer: 0,
@@ -21,5 +33,8 @@ pub fn init(builder: &mut EntityBuilder, pos: Vec2, look_angle: f32, resources:
         color: WHITE,
         sort_offset: 0.0,
         local_offset: Vec2::splat(-TILE_SIDE_F32 / 2.0),
+        scale: Vec2::ONE,
+        flip_x: false,
+        flip_y: false,
     });
 }
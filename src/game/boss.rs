@@ -0,0 +1,34 @@
+use super::prelude::*;
+
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, boss: Symbol, resources: &Resources) {
+    let boss_key = resources
+        .bosses
+        .resolve(boss.as_str())
+        .unwrap_or_else(|| panic!("Boss {boss:?} is not loaded"));
+    let boss = resources.bosses.get(boss_key).unwrap();
+    let kind = boss.phases[0].kind;
+    let key = resources
+        .enemy_kinds
+        .resolve(kind.as_str())
+        .unwrap_or_else(|| panic!("Enemy kind {kind:?} is not loaded"));
+    let def = resources.enemy_kinds.get(key).unwrap();
+
+    build_enemy(
+        builder,
+        pos,
+        def.shape,
+        boss.max_hp,
+        resources.factions.resolve(&def.faction).unwrap(),
+    );
+    builder.add_bundle((
+        DamageCooldown::new(def.hit_cooldown),
+        SpriteFlash::new(&resources.cfg.sprite_flash),
+        BehaviourAgent::new(key, def),
+        PathFollower::new(),
+        Perception::new(def.senses),
+        Boss::new(boss_key, boss.max_hp),
+    ));
+    if let Some(emitter) = kind_emitter(def) {
+        builder.add(emitter);
+    }
+}
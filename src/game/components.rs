@@ -1,25 +1,72 @@
+use lib_asset::{AssetKey, Faction, FactionTable};
 use macroquad::prelude::*;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum StabberState {
-    Idle,
-    Attacking,
+const PLAYER_FACTION: &str = "player";
+const ENEMY_FACTION: &str = "enemy";
+/// Nobody is hostile to it, so the effects are never hit or chased
+const EFFECT_FACTION: &str = "effect";
+
+/// The factions, that the code refers to by name
+#[derive(Debug, Clone, Copy)]
+pub struct GameFactions {
+    pub player: Faction,
+    pub enemy: Faction,
+    pub effect: Faction,
+}
+
+impl GameFactions {
+    pub fn resolve(table: &FactionTable) -> Self {
+        let resolve = |name| {
+            table
+                .resolve(name)
+                .unwrap_or_else(|| panic!("No {name:?} faction in the faction table"))
+        };
+        Self {
+            player: resolve(PLAYER_FACTION),
+            enemy: resolve(ENEMY_FACTION),
+            effect: resolve(EFFECT_FACTION),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ShooterState {
+pub enum StabberState {
     Idle,
     Attacking,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct ShooterTag;
+
 #[derive(Debug, Clone, Copy)]
 pub enum PlayerState {
-    Idle = 0,
-    Walking = 1,
-    Attacking = 2,
-    Dashing = 3,
+    Idle,
+    Walking,
+    /// Doing the `step`-th attack of the combo
+    Attacking {
+        step: usize,
+    },
+    /// Holding the attack button
+    Charging,
+    ChargedAttack {
+        level: usize,
+    },
+    Dashing,
+    Blocking,
 }
 
+/// For how long the player has been holding the attack button
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AttackCharge {
+    pub hold_time: f32,
+    /// The button has not been let go since the combo was started
+    pub holding: bool,
+}
+
+/// The [lib_asset::Moveset] the character attacks with
+#[derive(Debug, Clone, Copy)]
+pub struct MovesetKey(pub AssetKey);
+
 #[derive(Debug, Clone, Copy)]
 pub struct GoalTag {
     pub achieved: bool,
@@ -1,10 +1,10 @@
 use super::prelude::*;
 
-pub fn init(builder: &mut EntityBuilder, pos: Vec2) {
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, faction: Faction) {
     build_attack(
         builder,
         Transform::from_pos(pos),
-        Team::Enemy,
+        faction,
         Shape::Rect {
             width: 16.0,
             height: 16.0,
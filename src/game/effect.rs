@@ -0,0 +1,55 @@
+use super::prelude::*;
+
+/// A character, that plays its animation once and goes away.
+/// This way the hit and death effects get their sprites and sounds
+/// from the animation clips.
+pub struct Effect;
+
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, animation: AnimationId, faction: Faction) {
+    builder.add_bundle((
+        Effect,
+        faction,
+        Transform::from_pos(pos),
+        CharacterLook(0.0),
+        Health::new(1),
+        KinematicControl::new_slide(col_group::NONE),
+        BodyTag {
+            groups: col_group::NONE,
+            shape: Shape::Circle { radius: 0.0 },
+        },
+        AnimationPlay::new(animation),
+    ));
+}
+
+/// Play the effects of the characters, that got hurt this frame.
+/// Must run before the dead characters get despawned.
+pub fn on_damage(world: &mut World, cmds: &mut CommandBuffer, faction: Faction) {
+    for (_, (tf, hp)) in world
+        .query_mut::<(&Transform, &Health)>()
+        .without::<&Effect>()
+    {
+        if hp.damage <= 0 || hp.is_invulnerable {
+            continue;
+        }
+        let animation = if hp.value <= 0 {
+            AnimationId::DeathEffect
+        } else {
+            AnimationId::HitEffect
+        };
+        let mut builder = EntityBuilder::new();
+        init(&mut builder, tf.pos, animation, faction);
+        cmds.spawn(builder.build());
+    }
+}
+
+pub fn despawn_finished(world: &mut World, resources: &Resources, cmds: &mut CommandBuffer) {
+    for (ent, play) in world.query_mut::<&AnimationPlay>().with::<&Effect>() {
+        let done = resources
+            .animations
+            .get(&play.animation)
+            .is_none_or(|animation| play.is_done(animation));
+        if done {
+            cmds.despawn(ent);
+        }
+    }
+}
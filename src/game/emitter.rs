@@ -0,0 +1,15 @@
+use super::prelude::*;
+
+pub fn init(
+    builder: &mut EntityBuilder,
+    pos: Vec2,
+    faction: Faction,
+    look_angle: f32,
+    pattern: Symbol,
+) {
+    builder.add_bundle((
+        Transform::from_pos(pos),
+        faction,
+        Emitter::new(pattern, look_angle),
+    ));
+}
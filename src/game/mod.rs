@@ -1,10 +1,14 @@
 mod basic_bullet;
+mod boss;
 mod components;
 mod damager;
+mod effect;
+mod emitter;
 mod goal;
 mod player;
 mod prelude;
 mod render;
+mod scripted;
 mod shooter;
 mod stabber;
 
@@ -21,12 +25,17 @@ fn decide_next_state(world: &mut World) -> Option<AppState> {
         .query_mut::<&GoalTag>()
         .into_iter()
         .any(|(_, goal)| goal.achieved);
+    let boss_defeated = world
+        .query_mut::<&BossDefeated>()
+        .into_iter()
+        .next()
+        .is_some();
 
     if player_dead {
         return Some(AppState::GameOver);
     }
 
-    if goal_achieved {
+    if goal_achieved || boss_defeated {
         return Some(AppState::Win);
     }
 
@@ -40,16 +49,26 @@ async fn load_resources(resources: &mut Resources) -> AssetKey {
     resources.load_texture("atlas/bnuuy.png").await;
     resources.load_texture("atlas/world.png").await;
     build_textures_atlas();
+    // The animations refer to the sounds
+    resources.load_sound("sound/ball.wav").await;
+    resources.load_sound("sound/break.wav").await;
+    resources.load_sound("sound/dead.wav").await;
 
     resources.load_animation_pack("anim/bnuuy.json").await;
     resources.load_animation_pack("anim/stabber.json").await;
     resources.load_animation_pack("anim/shooter.json").await;
+    resources.load_animation_pack("anim/effects.json").await;
+    resources.load_factions("factions.json").await;
+    resources.load_moveset("moveset/bnuuy.json").await;
+    resources.load_bullet_patterns("bullet_patterns.json").await;
+    resources.load_state_machine("fsm/shooter.json").await;
     ui_font
 }
 
 pub struct Project {
     do_ai: bool,
     ui_font: AssetKey,
+    factions: GameFactions,
     do_player_controls: bool,
     transitions: Vec<fn(&mut World, &Resources)>,
     ais: Vec<fn(f32, &mut World, &Resources)>,
@@ -68,10 +87,14 @@ impl Project {
             ais: Vec::new(),
             anim_syncs: Vec::new(),
             ui_font,
+            factions: GameFactions::resolve(&app.resources.factions),
         };
         proj.register_character::<player::PlayerData>(None);
-        proj.register_character::<&mut StabberState>(Some(stabber::ai));
-        proj.register_character::<&mut ShooterState>(Some(shooter::ai));
+        proj.register_character::<stabber::StabberData>(Some(stabber::ai));
+        proj.register_ai(shooter::ai);
+        proj.register_character::<BehaviourData>(Some(run_behaviours));
+        // Goes after the AIs, so the machines get their signals right away
+        proj.register_character::<MachineData>(Some(run_state_machines));
 
         proj
     }
@@ -80,12 +103,18 @@ impl Project {
     where
         for<'a> Q::Item<'a>: CharacterData,
     {
+        self.transitions.push(dispatch_anim_events::<Q>);
         self.transitions.push(do_auto_state_transition::<Q>);
         if let Some(ai) = ai {
             self.ais.push(ai);
         }
         self.anim_syncs.push(state_to_anim::<Q>);
     }
+
+    /// Register an AI for characters, that are already registered
+    pub fn register_ai(&mut self, ai: fn(f32, &mut World, &Resources)) {
+        self.ais.push(ai);
+    }
 }
 
 impl Game for Project {
@@ -105,6 +134,10 @@ impl Game for Project {
             ("phys", draw_physics_debug),
             ("ch", draw_char_state),
             ("dmg", debug_damage_boxes),
+            ("nav", draw_paths),
+            ("senses", draw_perception),
+            ("arena", draw_encounters),
+            ("squad", draw_squads),
         ]
     }
 
@@ -146,12 +179,16 @@ impl Game for Project {
     fn update(
         &mut self,
         _dt: f32,
-        _resources: &lib_game::Resources,
+        resources: &lib_game::Resources,
         world: &mut World,
         _collisions: &CollisionSolver,
-        _cmds: &mut CommandBuffer,
+        cmds: &mut CommandBuffer,
     ) -> Option<lib_game::AppState> {
         goal::check(world);
+        player::make_noise(world, resources, cmds);
+        player::flash_on_hit(world);
+        effect::on_damage(world, cmds, self.factions.effect);
+        effect::despawn_finished(world, resources, cmds);
 
         decide_next_state(world)
     }
@@ -159,12 +196,13 @@ impl Game for Project {
     fn render_export(
         &self,
         app_state: &AppState,
-        _resources: &lib_game::Resources,
+        resources: &lib_game::Resources,
         world: &World,
         render: &mut Render,
     ) {
         if app_state.is_presentable() {
             render::stabber_hp(render, world, self.ui_font);
+            render::boss_hp(render, world, resources, self.ui_font);
             render::game_ui(render, world, self.ui_font);
         }
 
@@ -178,14 +216,34 @@ impl Game for Project {
         def: CharacterDef,
     ) {
         match def.info {
-            CharacterInfo::Player {} => player::init(builder, def.pos, resources),
+            CharacterInfo::Player {} => {
+                player::init(builder, def.pos, self.factions.player, resources)
+            }
             CharacterInfo::Goal {} => goal::init(builder, def.pos),
-            CharacterInfo::Damager {} => damager::init(builder, def.pos),
-            CharacterInfo::Stabber {} => stabber::init(builder, def.pos, resources),
-            CharacterInfo::BasicBullet {} => {
-                basic_bullet::init(builder, def.pos, def.look_angle, resources)
+            CharacterInfo::Damager {} => damager::init(builder, def.pos, self.factions.enemy),
+            CharacterInfo::Stabber {} => {
+                stabber::init(builder, def.pos, self.factions.enemy, resources)
+            }
+            CharacterInfo::BasicBullet { behaviour } => basic_bullet::init(
+                builder,
+                def.pos,
+                self.factions.enemy,
+                def.look_angle,
+                behaviour,
+                resources,
+            ),
+            CharacterInfo::Shooter {} => {
+                shooter::init(builder, def.pos, self.factions.enemy, resources)
             }
-            CharacterInfo::Shooter {} => shooter::init(builder, def.pos, resources),
+            CharacterInfo::Emitter { pattern } => emitter::init(
+                builder,
+                def.pos,
+                self.factions.enemy,
+                def.look_angle,
+                pattern,
+            ),
+            CharacterInfo::Scripted { kind } => scripted::init(builder, def.pos, kind, resources),
+            CharacterInfo::Boss { boss } => boss::init(builder, def.pos, boss, resources),
         }
     }
 }
@@ -1,15 +1,42 @@
+use std::sync::LazyLock;
+
 use super::prelude::*;
 
-pub fn init(builder: &mut EntityBuilder, pos: Vec2, resources: &Resources) {
+// What the cancel windows of the player's animations lead into
+static ATTACK: LazyLock<Symbol> = LazyLock::new(|| Symbol::new("attack"));
+static DASH: LazyLock<Symbol> = LazyLock::new(|| Symbol::new("dash"));
+
+// The facings of the idle and walk cycles, see [CharacterLook::pick_animation]
+const IDLE_DIRECTIONS: [Option<AnimationId>; 4] = [
+    Some(AnimationId::BnuuyIdleR),
+    Some(AnimationId::BnuuyIdleD),
+    Some(AnimationId::BnuuyIdleL),
+    Some(AnimationId::BnuuyIdleU),
+];
+const WALK_DIRECTIONS: [Option<AnimationId>; 4] = [
+    Some(AnimationId::BnuuyWalkR),
+    Some(AnimationId::BnuuyWalkD),
+    Some(AnimationId::BnuuyWalkL),
+    Some(AnimationId::BnuuyWalkU),
+];
+
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, faction: Faction, resources: &Resources) {
     build_player(
         builder,
         pos,
         resources.cfg.player.shape,
         resources.cfg.player.max_hp,
+        faction,
     );
+    let moveset = resources.movesets.resolve("moveset/bnuuy.json").unwrap();
     builder.add_bundle((
         PlayerState::Idle,
+        MovesetKey(moveset),
+        AttackCharge::default(),
         DamageCooldown::new(resources.cfg.player.hit_cooldown),
+        SpriteFlash::new(&resources.cfg.sprite_flash),
+        AnimationLayers::default(),
+        AttackTokens::new(resources.cfg.squad.max_attackers),
         GrazeGain {
             value: 0.0,
             max_value: resources.cfg.player.max_stamina,
@@ -45,27 +72,48 @@ pub fn controls(dt: f32, input: &InputModel, world: &mut World, resources: &Reso
     walk_dir = walk_dir.normalize_or_zero();
 
     for_each_character::<PlayerData>(world, resources, |_, mut c| {
+        let dt = c.local_dt(dt);
         let look_dir = (input.aim - c.pos()).normalize_or(vec2(0.0, 1.0));
 
-        if input.attack_down && can_attack(&c, cfg) {
-            c.set_state(PlayerState::Attacking);
-            c.data.substract_stamina(cfg.player.attack_cost);
+        if !input.attack_down {
+            c.data.charge.holding = false;
+        }
+
+        if matches!(c.get_state(), PlayerState::Charging) {
+            if c.data.charge.holding {
+                c.data.charge.hold_time += dt;
+            } else {
+                release_charge(&mut c);
+            }
+        } else if input.attack_pressed && can_attack(&c) {
+            start_attack(&mut c);
+        } else if input.attack_pressed
+            && let PlayerState::Attacking { step } = c.get_state()
+            && c.can_cancel_into(*ATTACK)
+            && let Some(next) = moveset(&c).next_step(step)
+        {
+            start_combo_step(&mut c, next);
         } else if input.dash_pressed && can_dash(&c, cfg) {
             c.set_state(PlayerState::Dashing);
             c.data.substract_stamina(cfg.player.dash_cost);
+        } else if input.block_down && can_block(&c) {
+            c.set_state(PlayerState::Blocking);
+        } else if !input.block_down && matches!(c.get_state(), PlayerState::Blocking) {
+            c.set_state(PlayerState::Idle);
         } else if do_walk && can_walk(&c) {
             c.set_state(PlayerState::Walking);
         } else if !do_walk && matches!(c.get_state(), PlayerState::Walking) {
             c.set_state(PlayerState::Idle);
         }
 
+        if c.data.charge.holding && can_charge(&c) {
+            hold_charge(&mut c, dt);
+        }
+
         c.set_walk_step(Vec2::ZERO);
-        match c.get_state() {
-            PlayerState::Walking => c.set_walk_step(walk_dir * cfg.player.speed * dt),
-            PlayerState::Dashing => {
-                c.set_walk_step(c.look_direction() * cfg.player.dash_speed * dt)
-            }
-            _ => (),
+        // The dash is moved by its animation
+        if matches!(c.get_state(), PlayerState::Walking) {
+            c.set_walk_step(walk_dir * cfg.player.speed * dt);
         }
 
         if c.get_input_flags().1 {
@@ -74,14 +122,82 @@ pub fn controls(dt: f32, input: &InputModel, world: &mut World, resources: &Reso
     });
 }
 
-fn can_attack(c: &Character<PlayerData>, cfg: &GameCfg) -> bool {
+fn moveset<'a>(c: &Character<'a, PlayerData>) -> &'a Moveset {
+    c.resources
+        .movesets
+        .get(c.data.moveset.0)
+        .expect("the player moveset is not loaded")
+}
+
+fn can_attack(c: &Character<PlayerData>) -> bool {
     matches!(c.get_state(), PlayerState::Idle | PlayerState::Walking)
-        && c.data.can_do_action(cfg.player.attack_cost)
+        && c.data.can_do_action(moveset(c).combo[0].stamina_cost)
+}
+
+/// The combo starts right on the press. Holding the button
+/// can then upgrade it to a charged attack.
+fn start_attack(c: &mut Character<PlayerData>) {
+    c.data.charge = AttackCharge {
+        hold_time: 0.0,
+        holding: true,
+    };
+    start_combo_step(c, 0);
+}
+
+/// The charge can only grow out of the combo opener
+fn can_charge(c: &Character<PlayerData>) -> bool {
+    matches!(
+        c.get_state(),
+        PlayerState::Idle | PlayerState::Walking | PlayerState::Attacking { step: 0 }
+    )
+}
+
+/// Once the button is held long enough for the first charge level,
+/// the opener is dropped for the charge animation.
+fn hold_charge(c: &mut Character<PlayerData>, dt: f32) {
+    c.data.charge.hold_time += dt;
+    if moveset(c).charge_level(c.data.charge.hold_time).is_some() {
+        c.set_state(PlayerState::Charging);
+    }
+}
+
+fn start_combo_step(c: &mut Character<PlayerData>, step: usize) {
+    let cost = moveset(c).combo[step].stamina_cost;
+    if !c.data.can_do_action(cost) {
+        return;
+    }
+    c.set_state(PlayerState::Attacking { step });
+    c.data.substract_stamina(cost);
+}
+
+/// Do the highest charged attack reached, if there is enough stamina for it
+fn release_charge(c: &mut Character<PlayerData>) {
+    let moveset = moveset(c);
+    let level = moveset
+        .charge_level(c.data.charge.hold_time)
+        .and_then(|level| {
+            let charge = moveset.charge.as_ref()?;
+            Some((level, charge.levels[level].stamina_cost))
+        });
+    match level {
+        Some((level, cost)) if c.data.can_do_action(cost) => {
+            c.set_state(PlayerState::ChargedAttack { level });
+            c.data.substract_stamina(cost);
+        }
+        _ => c.set_state(PlayerState::Idle),
+    }
 }
 
 fn can_dash(c: &Character<PlayerData>, cfg: &GameCfg) -> bool {
+    let can_start = matches!(
+        c.get_state(),
+        PlayerState::Idle | PlayerState::Walking | PlayerState::Blocking
+    ) || c.can_cancel_into(*DASH);
+    can_start && c.data.can_do_action(cfg.player.dash_cost)
+}
+
+fn can_block(c: &Character<PlayerData>) -> bool {
     matches!(c.get_state(), PlayerState::Idle | PlayerState::Walking)
-        && c.data.can_do_action(cfg.player.dash_cost)
 }
 
 fn can_walk(c: &Character<PlayerData>) -> bool {
@@ -100,33 +216,76 @@ impl CharacterData for PlayerData<'_> {
         *self.state = new_state
     }
 
-    fn state_to_anim(character: &Character<Self>) -> AnimationId {
-        match (character.get_state(), character.look_dir_enum()) {
-            (PlayerState::Idle, Direction::Right) => AnimationId::BnuuyIdleR,
-            (PlayerState::Idle, Direction::Down) => AnimationId::BnuuyIdleD,
-            (PlayerState::Idle, Direction::Left) => AnimationId::BnuuyIdleL,
-            (PlayerState::Idle, Direction::Up) => AnimationId::BnuuyIdleU,
-            (PlayerState::Walking, Direction::Right) => AnimationId::BnuuyWalkR,
-            (PlayerState::Walking, Direction::Down) => AnimationId::BnuuyWalkD,
-            (PlayerState::Walking, Direction::Left) => AnimationId::BnuuyWalkL,
-            (PlayerState::Walking, Direction::Up) => AnimationId::BnuuyWalkU,
-            (PlayerState::Attacking, _) => AnimationId::BnuuyAttackD,
-            (PlayerState::Dashing, _) => AnimationId::BnuuyDash,
-        }
+    fn state_to_anim(character: &Character<Self>) -> (AnimationId, bool) {
+        let look = *character.character_q.look;
+        let animation = match character.get_state() {
+            PlayerState::Idle => return look.pick_animation(&IDLE_DIRECTIONS).unwrap(),
+            PlayerState::Walking => return look.pick_animation(&WALK_DIRECTIONS).unwrap(),
+            PlayerState::Attacking { step } => moveset(character).combo[step].animation,
+            PlayerState::Charging => charge_attack(character).charge_animation,
+            PlayerState::ChargedAttack { level } => {
+                charge_attack(character).levels[level].animation
+            }
+            PlayerState::Dashing => AnimationId::BnuuyDash,
+            PlayerState::Blocking => AnimationId::BnuuyBlock,
+        };
+        (animation, false)
     }
 
     fn on_anim_end(character: &mut Character<Self>) {
         match character.get_state() {
-            PlayerState::Attacking | PlayerState::Dashing => character.set_state(PlayerState::Idle),
+            PlayerState::Attacking { .. }
+            | PlayerState::ChargedAttack { .. }
+            | PlayerState::Dashing => character.set_state(PlayerState::Idle),
             _ => (),
         }
     }
 }
 
+fn charge_attack<'a>(c: &Character<'a, PlayerData>) -> &'a ChargeAttack {
+    moveset(c)
+        .charge
+        .as_ref()
+        .expect("the moveset has no charge attack")
+}
+
+/// Pops the hit stars over the player's head, whatever it is doing
+pub fn flash_on_hit(world: &mut World) {
+    for (_, (hp, layers)) in world.query_mut::<(&Health, &mut AnimationLayers)>() {
+        if hp.damage > 0 && !hp.is_invulnerable {
+            layers.play(AnimationId::BnuuyHit);
+        }
+    }
+}
+
+/// Attacking and dashing are loud enough for the enemies to hear
+pub fn make_noise(world: &mut World, resources: &Resources, cmds: &mut CommandBuffer) {
+    for (_, (tf, state, faction)) in world.query_mut::<(&Transform, &PlayerState, &Faction)>() {
+        if !matches!(
+            state,
+            PlayerState::Attacking { .. }
+                | PlayerState::ChargedAttack { .. }
+                | PlayerState::Dashing
+        ) {
+            continue;
+        }
+        emit_event(
+            cmds,
+            Noise {
+                pos: tf.pos,
+                radius: resources.cfg.player.noise_radius,
+                faction: *faction,
+            },
+        );
+    }
+}
+
 #[derive(Query)]
 pub struct PlayerData<'a> {
     pub state: &'a mut PlayerState,
     pub graze_gain: &'a mut GrazeGain,
+    pub moveset: &'a MovesetKey,
+    pub charge: &'a mut AttackCharge,
 }
 
 impl<'a> PlayerData<'a> {
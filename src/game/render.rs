@@ -87,6 +87,36 @@ pub fn stabber_hp(render: &mut Render, world: &World, ui_font: AssetKey) {
     }
 }
 
+pub fn boss_hp(render: &mut Render, world: &World, resources: &Resources, ui_font: AssetKey) {
+    let off_y = 16.0;
+    let ui_x = TILE_SIDE_F32 * 16.0;
+    let bar_size = vec2(TILE_SIDE_F32 * 6.0, 8.0);
+
+    let mut boss_q = world.query::<(&Boss, &Health)>();
+    let Some((_, (boss, health))) = boss_q.into_iter().next() else {
+        return;
+    };
+    let Some(def) = resources.bosses.get(boss.def) else {
+        return;
+    };
+    let bar_color = if health.is_invulnerable { GRAY } else { RED };
+
+    render.put_text(vec2(ui_x, off_y * 8.0), RED, ui_font, 16.0, &def.name);
+    render.put_rect(
+        Rect::new(ui_x, off_y * 8.0 + 4.0, bar_size.x, bar_size.y),
+        Color::from_hex(0x3F0000),
+    );
+    render.put_rect(
+        Rect::new(
+            ui_x,
+            off_y * 8.0 + 4.0,
+            bar_size.x * boss.health_fraction(health),
+            bar_size.y,
+        ),
+        bar_color,
+    );
+}
+
 pub fn toplevel_ui(app_state: &AppState, render: &mut Render) {
     match app_state {
         AppState::Start => {
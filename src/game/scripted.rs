@@ -0,0 +1,27 @@
+use super::prelude::*;
+
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, kind: Symbol, resources: &Resources) {
+    let key = resources
+        .enemy_kinds
+        .resolve(kind.as_str())
+        .unwrap_or_else(|| panic!("Enemy kind {kind:?} is not loaded"));
+    let def = resources.enemy_kinds.get(key).unwrap();
+
+    build_enemy(
+        builder,
+        pos,
+        def.shape,
+        def.max_hp,
+        resources.factions.resolve(&def.faction).unwrap(),
+    );
+    builder.add_bundle((
+        DamageCooldown::new(def.hit_cooldown),
+        SpriteFlash::new(&resources.cfg.sprite_flash),
+        BehaviourAgent::new(key, def),
+        PathFollower::new(),
+        Perception::new(def.senses),
+    ));
+    if let Some(emitter) = kind_emitter(def) {
+        builder.add(emitter);
+    }
+}
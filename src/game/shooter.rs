@@ -1,58 +1,60 @@
-use super::prelude::*;
+use std::sync::LazyLock;
 
-impl CharacterData for &mut ShooterState {
-    type StateId = ShooterState;
+use super::prelude::*;
 
-    fn get_state(&self) -> Self::StateId {
-        **self
-    }
-    fn set_state(&mut self, new_state: Self::StateId) {
-        **self = new_state
-    }
-    fn state_to_anim(character: &Character<Self>) -> AnimationId {
-        match character.get_state() {
-            ShooterState::Idle => AnimationId::ShooterIdle,
-            ShooterState::Attacking => AnimationId::ShooterAttack,
-        }
-    }
-    fn on_anim_end(character: &mut Character<Self>) {
-        if character.get_state() == ShooterState::Attacking {
-            character.set_state(ShooterState::Idle);
-        }
-    }
-}
+// The names from `fsm/shooter.json`, interned once
+static IDLE: LazyLock<Symbol> = LazyLock::new(|| Symbol::new("idle"));
+static ATTACK: LazyLock<Symbol> = LazyLock::new(|| Symbol::new("attack"));
 
-pub fn init(builder: &mut EntityBuilder, pos: Vec2, resources: &Resources) {
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, faction: Faction, resources: &Resources) {
     build_enemy(
         builder,
         pos,
         resources.cfg.shooter.shape,
         resources.cfg.shooter.max_hp,
+        faction,
     );
+    let key = resources
+        .state_machines
+        .resolve("fsm/shooter.json")
+        .unwrap();
+    let machine = resources.state_machines.get(key).unwrap();
     builder.add_bundle((
         DamageCooldown::new(resources.cfg.shooter.hit_cooldown),
-        ShooterState::Idle,
+        SpriteFlash::new(&resources.cfg.sprite_flash),
+        ShooterTag,
+        MachineState::new(key, machine),
+        Perception::new(resources.cfg.shooter.senses),
     ));
 }
 
-pub fn ai(_dt: f32, world: &mut World, resources: &Resources) {
-    let Some((_, (player_tf, _))) = world
-        .query_mut::<(&Transform, &PlayerState)>()
-        .into_iter()
-        .next()
-    else {
-        return;
-    };
-    let player_tf = *player_tf;
-
-    for_each_character::<&mut ShooterState>(world, resources, |_, mut character| {
-        let off_to_player = player_tf.pos - character.pos();
-        let dir = off_to_player.normalize_or(Vec2::Y);
-
+pub fn ai(dt: f32, world: &mut World, resources: &Resources) {
+    for_each_character::<ShooterData>(world, resources, |_, mut character| {
         character.set_walk_step(Vec2::ZERO);
-        if character.get_state() == ShooterState::Idle {
+        // A frozen shooter can't react
+        if character.local_dt(dt) == 0.0 {
+            return;
+        }
+        let perception = *character.data.perception;
+        if !perception.is_aggro() || !perception.sees_target() {
+            return;
+        }
+        let Some(target_pos) = perception.last_seen() else {
+            return;
+        };
+        let dir = (target_pos - character.pos()).normalize_or(Vec2::Y);
+
+        if character.data.state.is(*IDLE) {
             character.set_look_direction(dir);
-            character.set_state(ShooterState::Attacking);
+            character.data.state.signal(*ATTACK);
         }
     });
 }
+
+/// The shooter's states are described in `fsm/shooter.json`
+#[derive(Query)]
+pub struct ShooterData<'a> {
+    pub tag: &'a ShooterTag,
+    pub state: &'a mut MachineState,
+    pub perception: &'a Perception,
+}
@@ -1,62 +1,148 @@
 use super::prelude::*;
 
-impl CharacterData for &mut StabberState {
+/// How close the stabber goes to a noise before losing interest
+const NOISE_CHECKED_DIST: f32 = 8.0;
+
+impl CharacterData for StabberData<'_> {
     type StateId = StabberState;
 
     fn get_state(&self) -> Self::StateId {
-        **self
+        *self.state
     }
     fn set_state(&mut self, new_state: Self::StateId) {
-        **self = new_state
+        *self.state = new_state
     }
-    fn state_to_anim(character: &Character<Self>) -> AnimationId {
-        match character.get_state() {
+    fn state_to_anim(character: &Character<Self>) -> (AnimationId, bool) {
+        let animation = match character.get_state() {
             StabberState::Idle => AnimationId::StabberIdle,
             StabberState::Attacking => AnimationId::StabberAttack,
-        }
+        };
+        // The stabber is only drawn facing right, so it is mirrored to face left
+        character
+            .character_q
+            .look
+            .pick_animation(&[Some(animation), None, None, None])
+            .unwrap()
     }
     fn on_anim_end(character: &mut Character<Self>) {
         if character.get_state() == StabberState::Attacking {
+            character.data.squad.release_token();
             character.set_state(StabberState::Idle);
         }
     }
 }
 
-pub fn init(builder: &mut EntityBuilder, pos: Vec2, resources: &Resources) {
+pub fn init(builder: &mut EntityBuilder, pos: Vec2, faction: Faction, resources: &Resources) {
     build_enemy(
         builder,
         pos,
         resources.cfg.stabber.shape,
         resources.cfg.stabber.max_hp,
+        faction,
     );
     builder.add_bundle((
         DamageCooldown::new(resources.cfg.stabber.hit_cooldown),
+        SpriteFlash::new(&resources.cfg.sprite_flash),
         StabberState::Idle,
+        PathFollower::new(),
+        Perception::new(resources.cfg.stabber.senses),
+        SquadMember::new(),
     ));
 }
 
 pub fn ai(dt: f32, world: &mut World, resources: &Resources) {
     let cfg = &resources.cfg;
-    let Some((_, (player_tf, _))) = world
-        .query_mut::<(&Transform, &PlayerState)>()
-        .into_iter()
-        .next()
-    else {
-        return;
-    };
-    let player_tf = *player_tf;
-
-    for_each_character::<&mut StabberState>(world, resources, |_, mut character| {
-        let off_to_player = player_tf.pos - character.pos();
-        let dir = off_to_player.normalize_or(Vec2::Y);
+    let targets = collect_targets(world);
+    let mut tokens = world.query::<&mut AttackTokens>();
+    let mut tokens = tokens.view();
 
+    for_each_character::<StabberData>(world, resources, |ent, mut character| {
+        let dt = character.local_dt(dt);
         character.set_walk_step(Vec2::ZERO);
-        if character.get_state() == StabberState::Idle {
-            character.set_look_direction(dir);
-            character.set_walk_step(dir * cfg.stabber.speed * dt);
-            if off_to_player.length() <= cfg.stabber.attack_range {
-                character.set_state(StabberState::Attacking);
+        if character.get_state() != StabberState::Idle {
+            return;
+        }
+
+        let perception = *character.data.perception;
+        let goal = if perception.is_aggro() {
+            perception.last_seen()
+        } else {
+            perception.heard_noise()
+        };
+        let Some(goal) = goal else {
+            return;
+        };
+        let off_to_goal = goal - character.pos();
+        let faction = character.faction();
+        let allies = targets
+            .iter()
+            .filter(|x| x.entity != ent && x.faction == faction)
+            .map(|x| x.pos);
+
+        // Close enemies ask for a token, the rest wait around the target
+        let engaged = perception.is_aggro()
+            && perception.sees_target()
+            && off_to_goal.length() <= cfg.squad.wait_radius;
+        let has_token = match perception.target() {
+            Some(target) if engaged => character.data.squad.request_token(
+                ent,
+                target,
+                tokens.get_mut(target),
+                cfg.squad.rest_time,
+            ),
+            _ => {
+                character.data.squad.release_token();
+                false
             }
+        };
+
+        if has_token && off_to_goal.length() <= cfg.stabber.attack_range {
+            character.set_look_direction(off_to_goal.normalize_or(Vec2::Y));
+            character.set_state(StabberState::Attacking);
+            return;
+        }
+        if !perception.is_aggro() && off_to_goal.length() <= NOISE_CHECKED_DIST {
+            character.data.perception.forget_noise();
+            return;
+        }
+
+        let pos = character.pos();
+        let goal = if engaged {
+            let radius = if has_token {
+                cfg.stabber.attack_range / 2.0
+            } else {
+                cfg.squad.wait_radius
+            };
+            flank_point(goal, pos, allies.clone(), radius, cfg.squad.flank_spread)
+        } else {
+            goal
+        };
+        let path_offset = character.path_offset(&resources.nav, goal, |data| &mut *data.path);
+        let walk = if engaged && !has_token {
+            steering::arrive(Vec2::ZERO, path_offset, TILE_SIDE_F32)
+        } else {
+            steering::seek(Vec2::ZERO, path_offset)
+        };
+        let dir = steering::blend(&[
+            (1.0, walk),
+            (
+                cfg.stabber.separation_weight,
+                steering::separation(character.pos(), allies, cfg.stabber.separation_radius),
+            ),
+        ]);
+        character.set_walk_step(dir * cfg.stabber.speed * dt);
+        if engaged {
+            character.set_look_direction(off_to_goal.normalize_or(Vec2::Y));
+        } else if dir != Vec2::ZERO {
+            character.set_look_direction(dir);
         }
     });
 }
+
+#[derive(Query)]
+pub struct StabberData<'a> {
+    pub state: &'a mut StabberState,
+    pub path: &'a mut PathFollower,
+    pub perception: &'a mut Perception,
+    pub squad: &'a mut SquadMember,
+}